@@ -0,0 +1,182 @@
+//! Compare the payload size, save and load time of table codecs on a large
+//! synthetic scene.
+//!
+//! Save times are [`FastScene::from_bevy`] then serializing the scene, load
+//! times are spawning the archived scene in a new world with
+//! [`ArchivedFastScene::to_bevy`]. Nothing is read from or written to disk.
+//!
+//! ```sh
+//! cargo bench --bench codecs
+//! ```
+use std::time::{Duration, Instant};
+
+use bevy::prelude::*;
+use bvyfst_hollow_scene::codec::{BitPacked, Delta, Dictionary, Words};
+use bvyfst_hollow_scene::{proxy::Id, Archive, Deserialize, Serialize};
+use bvyfst_hollow_scene::{DedupTable, Encoded, FastScene, HCons, Inline, Table};
+
+/// Less than the 65535 values a table can hold.
+const ENTITIES: u32 = 60_000;
+const GRID_WIDTH: u32 = 300;
+const RUNS: u32 = 5;
+
+/// A cell on a grid, entities are spawned in row order.
+#[derive(Component, Clone, Copy, PartialEq, Default, Archive, Deserialize, Serialize)]
+struct Position {
+    x: i32,
+    y: i32,
+}
+impl Words for Position {
+    const WORDS: usize = 2;
+    fn to_words(&self, out: &mut Vec<u32>) {
+        self.x.to_words(out);
+        self.y.to_words(out);
+    }
+    fn from_words(words: &[u32]) -> Self {
+        Position {
+            x: i32::from_words(&words[..1]),
+            y: i32::from_words(&words[1..]),
+        }
+    }
+}
+
+#[derive(Component, Clone, Copy, PartialEq, Default, Archive, Deserialize, Serialize)]
+enum Kind {
+    #[default]
+    Grass,
+    Water,
+    Rock,
+    Tree,
+}
+impl Words for Kind {
+    const WORDS: usize = 1;
+    fn to_words(&self, out: &mut Vec<u32>) {
+        out.push(*self as u32);
+    }
+    fn from_words(words: &[u32]) -> Self {
+        match words[0] {
+            1 => Kind::Water,
+            2 => Kind::Rock,
+            3 => Kind::Tree,
+            _ => Kind::Grass,
+        }
+    }
+}
+
+#[derive(Component, Clone, Copy, PartialEq, Default, Archive, Deserialize, Serialize)]
+struct Count(u16);
+
+impl Words for Count {
+    const WORDS: usize = 1;
+    fn to_words(&self, out: &mut Vec<u32>) {
+        self.0.to_words(out);
+    }
+    fn from_words(words: &[u32]) -> Self {
+        Count(u16::from_words(words))
+    }
+}
+
+/// The synthetic scene, a grid of `ENTITIES` cells.
+fn synthetic_scene() -> Scene {
+    let mut world = World::new();
+    // A xorshift, so that kinds look random but are the same on each run.
+    let mut state = 0x2545_f491_u32;
+    for i in 0..ENTITIES {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        let kind = Kind::from_words(&[state % 4]);
+        let position = Position {
+            x: (i % GRID_WIDTH) as i32,
+            y: (i / GRID_WIDTH) as i32,
+        };
+        world.spawn((position, kind, Count((i % 40) as u16)));
+    }
+    Scene::new(world)
+}
+
+/// The fastest of `RUNS` runs of `f`.
+fn fastest<R>(mut f: impl FnMut() -> R) -> (R, Duration) {
+    let mut best = Duration::MAX;
+    let mut result = None;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        result = Some(f());
+        best = best.min(start.elapsed());
+    }
+    // unwrap: RUNS is not zero
+    (result.unwrap(), best)
+}
+
+/// The type lists `Plugin!` builds, spelled out so that the bench can name
+/// `FastScene<Ts, Is>`.
+macro_rules! list {
+    () => { () };
+    ($head:ty, $($tail:ty,)*) => { HCons<$head, list!($($tail,)*)> };
+}
+
+macro_rules! bench {
+    ($name:literal, $tables:ty, $inlines:ty, $scene:expr) => {{
+        type Fast = FastScene<$tables, $inlines>;
+        let scene: &mut Scene = $scene;
+        let (bytes, save) = fastest(|| {
+            let fast_scene = Fast::from_bevy(scene);
+            rkyv::to_bytes::<_, 1024>(&fast_scene).unwrap()
+        });
+        // SAFETY: `bytes` was just serialized from a `Fast`.
+        let archived = unsafe { rkyv::archived_root::<Fast>(&bytes) };
+        let (mut loaded, load) = fastest(|| archived.to_bevy());
+        // The wrapper root and the placeholder are spawned too.
+        assert_eq!(loaded.world.entities().len(), ENTITIES + 2);
+        assert_eq!(
+            loaded.world.query::<&Kind>().iter(&loaded.world).count(),
+            ENTITIES as usize
+        );
+        println!(
+            "{:<24} {:>10} {:>10.2?} {:>10.2?}",
+            $name,
+            bytes.len(),
+            save,
+            load
+        );
+    }};
+}
+
+fn main() {
+    let mut scene = synthetic_scene();
+    let scene = &mut scene;
+
+    println!("{ENTITIES} entities, fastest of {RUNS} runs");
+    println!(
+        "{:<24} {:>10} {:>10} {:>10}",
+        "storage", "bytes", "save", "load"
+    );
+    bench!(
+        "Inline",
+        (),
+        list![Inline<Id<Position>>, Inline<Id<Kind>>, Inline<Id<Count>>,],
+        scene
+    );
+    bench!(
+        "Table",
+        list![Table<Id<Position>>, Table<Id<Kind>>, Table<Id<Count>>,],
+        (),
+        scene
+    );
+    bench!(
+        "DedupTable kinds",
+        list![DedupTable<Id<Kind>>, Table<Id<Position>>, Table<Id<Count>>,],
+        (),
+        scene
+    );
+    bench!(
+        "Table with codecs",
+        list![
+            Encoded<Table<Id<Position>>, Delta>,
+            Encoded<Table<Id<Kind>>, Dictionary>,
+            Encoded<Table<Id<Count>>, BitPacked>,
+        ],
+        (),
+        scene
+    );
+}
@@ -1,66 +1,39 @@
-use bvyfst_hollow_scene::{proxy, Archive, ArchiveProxy, Deserialize, Plugin, Serialize};
+mod schema;
 
-use bevy::prelude::*;
+use bvyfst_hollow_scene::{proxy, Plugin};
 
+use bevy::{core::TypeRegistrationPlugin, prelude::*};
+use schema::{ComponentA, ComponentB, MyTransform};
+
+/// Run with `cargo run --example basic_scene -- convert assets` to convert
+/// the scenes in `assets` without starting the game.
 fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins.set(AssetPlugin::processed_dev()))
+    let plugin = Plugin!(
+        Inline[proxy::Id<ComponentA>]
+        DedupTable[MyTransform]
+        Table[proxy::Id<ComponentB>]
+    );
+    let mut app = App::new();
+    let mut args = std::env::args_os().skip(1);
+    if args.next().is_some_and(|arg| arg == "convert") {
+        app.add_plugin(TypeRegistrationPlugin)
+            .add_plugin(TransformPlugin)
+            .add_plugin(HierarchyPlugin)
+            .register_type::<ComponentA>()
+            .register_type::<ComponentB>();
+        let registry = app.world.resource::<AppTypeRegistry>().clone();
+        plugin.converter(registry).run(args).unwrap();
+        return;
+    }
+    app.add_plugins(DefaultPlugins.set(AssetPlugin::processed_dev()))
         .register_type::<ComponentA>()
         .register_type::<ComponentB>()
-        .add_plugin(Plugin!(
-            Inline[proxy::Id<ComponentA>]
-            DedupTable[MyTransform]
-            Table[proxy::Id<ComponentB>]
-        ))
+        .add_plugin(plugin)
         .add_systems(Startup, (load_scene_system, infotext_system))
         .add_systems(Update, log_system)
         .run();
 }
 
-#[derive(Clone, Copy, PartialEq, Default, Archive, Deserialize, Serialize)]
-pub struct MyTransform {
-    pub translation: [f32; 3],
-    pub rotation: [f32; 4],
-    pub scale: [f32; 3],
-}
-impl ArchiveProxy for MyTransform {
-    type Target = Transform;
-
-    fn to_target(value: &Self::Archived) -> Self::Target {
-        Transform {
-            translation: value.translation.into(),
-            rotation: Quat::from_array(value.rotation),
-            scale: value.scale.into(),
-        }
-    }
-    fn from_target(bevy: &Self::Target) -> Self {
-        MyTransform {
-            translation: bevy.translation.into(),
-            rotation: bevy.rotation.into(),
-            scale: bevy.scale.into(),
-        }
-    }
-}
-impl PartialEq<Transform> for MyTransform {
-    fn eq(&self, other: &Transform) -> bool {
-        let other = Self::from_target(other);
-        self == &other
-    }
-}
-
-#[derive(Component, Clone, Reflect, Default, Archive, Deserialize, Serialize)]
-#[reflect(Component)]
-struct ComponentA {
-    pub x: f32,
-    pub y: f32,
-}
-
-#[derive(Component, Clone, Reflect, Default, Archive, Deserialize, Serialize)]
-#[reflect(Component)]
-struct ComponentB {
-    pub value: String,
-}
-
 const SCENE_FILE_PATH: &str = "load_scene_example.myscn.ron";
 
 fn load_scene_system(mut commands: Commands, asset_server: Res<AssetServer>) {
@@ -0,0 +1,19 @@
+//! Inspect `.hollow_bvyfst` files saved by the `basic_scene` example.
+//!
+//! ```sh
+//! cargo run --example bvyfst -- --dump imported_assets/load_scene_example.myscn.ron
+//! ```
+mod schema;
+
+use bvyfst_hollow_scene::{proxy, Plugin};
+
+use schema::{ComponentA, ComponentB, MyTransform};
+
+fn main() -> anyhow::Result<()> {
+    Plugin!(
+        Inline[proxy::Id<ComponentA>]
+        DedupTable[MyTransform]
+        Table[proxy::Id<ComponentB>]
+    )
+    .inspect_cli()
+}
@@ -0,0 +1,30 @@
+//! Convert the dynamic scenes of `assets` into `.hollow_bvyfst` files, with
+//! the same schema as the `basic_scene` example.
+//!
+//! ```sh
+//! cargo run --example convert_scenes -- assets converted_assets
+//! ```
+mod schema;
+
+use bvyfst_hollow_scene::{proxy, Plugin};
+
+use bevy::{core::TypeRegistrationPlugin, prelude::*};
+use schema::{ComponentA, ComponentB, MyTransform};
+
+fn main() -> anyhow::Result<()> {
+    let mut app = App::new();
+    app.add_plugin(TypeRegistrationPlugin)
+        .add_plugin(TransformPlugin)
+        .add_plugin(HierarchyPlugin)
+        .register_type::<ComponentA>()
+        .register_type::<ComponentB>();
+    let registry = app.world.resource::<AppTypeRegistry>().clone();
+
+    Plugin!(
+        Inline[proxy::Id<ComponentA>]
+        DedupTable[MyTransform]
+        Table[proxy::Id<ComponentB>]
+    )
+    .with_parallel_save()
+    .convert_cli(registry)
+}
@@ -0,0 +1,49 @@
+//! Components and proxies shared by the examples.
+
+use bvyfst_hollow_scene::{Archive, ArchiveProxy, Deserialize, Serialize};
+
+use bevy::prelude::*;
+
+#[derive(Clone, Copy, PartialEq, Default, Archive, Deserialize, Serialize)]
+pub struct MyTransform {
+    pub translation: [f32; 3],
+    pub rotation: [f32; 4],
+    pub scale: [f32; 3],
+}
+impl ArchiveProxy for MyTransform {
+    type Target = Transform;
+
+    fn to_target(value: &Self::Archived) -> Self::Target {
+        Transform {
+            translation: value.translation.into(),
+            rotation: Quat::from_array(value.rotation),
+            scale: value.scale.into(),
+        }
+    }
+    fn from_target(bevy: &Self::Target) -> Self {
+        MyTransform {
+            translation: bevy.translation.into(),
+            rotation: bevy.rotation.into(),
+            scale: bevy.scale.into(),
+        }
+    }
+}
+impl PartialEq<Transform> for MyTransform {
+    fn eq(&self, other: &Transform) -> bool {
+        let other = Self::from_target(other);
+        self == &other
+    }
+}
+
+#[derive(Component, Clone, Reflect, Default, Archive, Deserialize, Serialize)]
+#[reflect(Component)]
+pub struct ComponentA {
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Component, Clone, Reflect, Default, Archive, Deserialize, Serialize)]
+#[reflect(Component)]
+pub struct ComponentB {
+    pub value: String,
+}
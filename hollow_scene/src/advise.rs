@@ -0,0 +1,351 @@
+//! Recommend a storage for each proxy of a `Plugin!`, see [`Advice`].
+//!
+//! Whether `Inline`, `Table` or `DedupTable` is the smallest depends on how
+//! many entities have the component, and how many distinct values there are.
+//! [`Advice`] measures both on an actual scene, computes how many bytes each
+//! storage would take, and recommends a `Plugin!` invocation:
+//!
+//! ```text
+//! let plugin = Plugin!(Inline[proxy::Id<ComponentA>] Table[MyTransform]);
+//! print!("{}", plugin.advise(&scene.world));
+//! ```
+//!
+//! The scene conversion tool also prints the advice for each scene with
+//! `--advise`, see `convert::Converter::run`.
+
+use std::collections::HashSet;
+use std::{fmt, mem, num::NonZeroU16};
+
+use bevy::prelude::{Component, World};
+use bevy::utils::get_short_name;
+use rkyv::{Archived, Serialize};
+
+use crate::codec::Codec;
+use crate::dynamic::Serializer;
+use crate::entity::{ArchiveProxy, ColumnarProxy, TableKind};
+use crate::schema::{ComponentInfo, Storage};
+use crate::strings::Interner;
+use crate::{Auto, Columnar, DedupTable, Encoded, HCons, Inline, Table};
+
+/// How many bytes a proxy would take in each storage.
+///
+/// This counts the values, including their out-of-line data, and the keys
+/// of table storage, but not padding in the entity array.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StorageCosts {
+    pub inline: usize,
+    /// `None` when there are too many values for a table.
+    pub table: Option<usize>,
+    /// `None` when there are too many distinct values for a table.
+    pub dedup_table: Option<usize>,
+}
+impl StorageCosts {
+    /// The smallest storage, `Inline` then `Table` on ties, since they are
+    /// faster to spawn.
+    pub fn cheapest(&self) -> Storage {
+        let tables = [
+            (Storage::Table, self.table),
+            (Storage::DedupTable, self.dedup_table),
+        ];
+        let mut cheapest = (Storage::Inline, self.inline);
+        for (storage, cost) in tables {
+            if let Some(cost) = cost.filter(|cost| *cost < cheapest.1) {
+                cheapest = (storage, cost);
+            }
+        }
+        cheapest.0
+    }
+}
+
+/// Measurements of a single proxy in a scene, see [`Advice`].
+#[derive(Debug, Clone)]
+pub struct ProxyAdvice {
+    /// The proxy and its storage in the `Plugin!` the advice is for.
+    pub info: ComponentInfo,
+    /// How many entities have this component.
+    pub occupancy: usize,
+    /// How many distinct archived values there are.
+    pub distinct: usize,
+    pub costs: StorageCosts,
+}
+impl ProxyAdvice {
+    /// The recommended storage.
+    ///
+    /// `Columnar` proxies and tables with a [`Codec`] keep their storage,
+    /// they are not chosen for the size of their archived values. `Auto`
+    /// proxies also keep their storage, since they pick it for each file.
+    pub fn recommended(&self) -> Storage {
+        let kept = [Storage::Columnar, Storage::Auto];
+        if kept.contains(&self.info.storage) || self.info.codec.is_some() {
+            return self.info.storage;
+        }
+        self.costs.cheapest()
+    }
+}
+
+/// The storage recommended for each proxy of a `Plugin!`, given a scene.
+#[derive(Debug, Clone)]
+pub struct Advice {
+    pub entity_count: usize,
+    /// Table proxies first, then inline proxies, in schema order.
+    pub proxies: Vec<ProxyAdvice>,
+}
+impl Advice {
+    /// Measure the proxies of `Ts` and `Is` in `world`.
+    pub fn new<Ts: Advise, Is: Advise>(world: &World) -> Self {
+        let entity_count = world.iter_entities().count();
+        // The entity array also contains the synthetic scene root.
+        let slots = entity_count + 1;
+        let mut proxies = Vec::new();
+        Interner::default().scope(|| {
+            Ts::advise(world, slots, &mut proxies);
+            Is::advise(world, slots, &mut proxies);
+        });
+        Advice { entity_count, proxies }
+    }
+    /// The `Plugin!` invocation with the recommended storages.
+    pub fn plugin(&self) -> String {
+        let sections = [
+            Storage::Inline,
+            Storage::DedupTable,
+            Storage::Table,
+            Storage::Columnar,
+            Storage::Auto,
+        ];
+        let mut plugin = String::from("Plugin!(\n");
+        for section in sections {
+            let proxies = self.proxies.iter().filter(|p| p.recommended() == section);
+            let proxies: Vec<_> = proxies
+                .map(|p| match p.info.codec {
+                    Some(codec) => format!("{} => {codec}", get_short_name(p.info.proxy)),
+                    None => get_short_name(p.info.proxy),
+                })
+                .collect();
+            if !proxies.is_empty() {
+                let proxies = proxies.join(", ");
+                plugin.push_str(&format!("    {}[{proxies}]\n", section.name()));
+            }
+        }
+        plugin.push(')');
+        plugin
+    }
+}
+impl fmt::Display for Advice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let percent = |count: usize, total: usize| count as f64 * 100.0 / total.max(1) as f64;
+        let cost = |cost: Option<usize>| match cost {
+            Some(cost) => format!("{cost} bytes"),
+            None => "too many values".to_string(),
+        };
+
+        writeln!(f, "entities: {}", self.entity_count)?;
+        writeln!(f, "proxies:")?;
+        for advice in &self.proxies {
+            let ProxyAdvice { info, occupancy, distinct, costs } = advice;
+            let occupied = percent(*occupancy, self.entity_count);
+            let distinct_ratio = percent(*distinct, *occupancy);
+            writeln!(
+                f,
+                "\t{} {}:",
+                info.storage.name(),
+                get_short_name(info.proxy)
+            )?;
+            writeln!(f, "\t\toccupancy: {occupancy} ({occupied:.1}%)")?;
+            writeln!(f, "\t\tdistinct values: {distinct} ({distinct_ratio:.1}%)")?;
+            writeln!(f, "\t\tarchived size: {} bytes", info.archived_size)?;
+            writeln!(f, "\t\tInline: {} bytes", costs.inline)?;
+            writeln!(f, "\t\tTable: {}", cost(costs.table))?;
+            writeln!(f, "\t\tDedupTable: {}", cost(costs.dedup_table))?;
+            writeln!(f, "\t\trecommended: {}", advice.recommended().name())?;
+        }
+        writeln!(f, "recommended plugin:")?;
+        writeln!(f, "{}", self.plugin())
+    }
+}
+
+/// How many entities have a proxy, and the size of their archived values.
+///
+/// This is also how `Auto` storage picks a storage when saving.
+#[derive(Default)]
+pub(crate) struct Measure {
+    occupancy: usize,
+    distinct: usize,
+    /// Size of all values, including out-of-line data.
+    bytes: usize,
+    /// Size of the distinct values, including out-of-line data.
+    distinct_bytes: usize,
+    seen: HashSet<Vec<u8>>,
+}
+impl Measure {
+    /// Count a value archived as `bytes`.
+    pub(crate) fn add(&mut self, bytes: &[u8]) {
+        self.occupancy += 1;
+        self.bytes += bytes.len();
+        if self.seen.insert(bytes.to_vec()) {
+            self.distinct += 1;
+            self.distinct_bytes += bytes.len();
+        }
+    }
+    /// Compare the archived bytes of `P` for each entity of `world`.
+    fn of<P: ArchiveProxy + Serialize<Serializer>>(world: &World) -> Self {
+        let mut measure = Measure::default();
+        let targets = world.iter_entities().filter_map(|e| e.get::<P::Target>());
+        for target in targets {
+            // unwrap: serializing to an `AlignedVec` doesn't fail
+            let bytes = rkyv::to_bytes::<_, 1024>(&P::from_target(target)).unwrap();
+            measure.add(&bytes);
+        }
+        measure
+    }
+    /// Like [`Measure::of`], but assume all values are distinct, without
+    /// out-of-line data.
+    fn count<C: Component>(world: &World, archived_size: usize) -> Self {
+        let occupancy = world.iter_entities().filter(|e| e.contains::<C>()).count();
+        Measure {
+            occupancy,
+            distinct: occupancy,
+            bytes: occupancy * archived_size,
+            distinct_bytes: occupancy * archived_size,
+            seen: HashSet::new(),
+        }
+    }
+    /// The size of the measured `P` values in each storage, with `slots`
+    /// entities in the entity array.
+    pub(crate) fn costs<P: ArchiveProxy>(
+        &self,
+        archived_size: usize,
+        slots: usize,
+    ) -> StorageCosts {
+        let inline_slot = mem::size_of::<Archived<Option<P>>>();
+        let key = mem::size_of::<Archived<Option<NonZeroU16>>>();
+        let out_of_line = self.bytes.saturating_sub(self.occupancy * archived_size);
+        let max_values = usize::from(u16::MAX);
+        StorageCosts {
+            inline: slots * inline_slot + out_of_line,
+            table: (self.occupancy <= max_values).then_some(slots * key + self.bytes),
+            dedup_table: (self.distinct <= max_values).then_some(slots * key + self.distinct_bytes),
+        }
+    }
+    fn advice<P: ArchiveProxy>(self, info: ComponentInfo, slots: usize) -> ProxyAdvice {
+        let costs = self.costs::<P>(info.archived_size, slots);
+        ProxyAdvice {
+            info,
+            occupancy: self.occupancy,
+            distinct: self.distinct,
+            costs,
+        }
+    }
+}
+
+/// Type lists built by `Plugin!` whose proxies can be measured by [`Advice`].
+pub trait Advise {
+    /// Push the advice for each proxy of this list, with `slots` entities in
+    /// the entity array.
+    fn advise(world: &World, slots: usize, proxies: &mut Vec<ProxyAdvice>);
+}
+impl Advise for () {
+    fn advise(_: &World, _: usize, _: &mut Vec<ProxyAdvice>) {}
+}
+impl<P: ArchiveProxy + Serialize<Serializer>, T: Advise> Advise for HCons<Inline<P>, T> {
+    fn advise(world: &World, slots: usize, proxies: &mut Vec<ProxyAdvice>) {
+        let info = ComponentInfo::new::<P>(Storage::Inline);
+        proxies.push(Measure::of::<P>(world).advice::<P>(info, slots));
+        T::advise(world, slots, proxies);
+    }
+}
+impl<P: ArchiveProxy + Serialize<Serializer>, T: Advise> Advise for HCons<Table<P>, T> {
+    fn advise(world: &World, slots: usize, proxies: &mut Vec<ProxyAdvice>) {
+        let info = ComponentInfo::new::<P>(Storage::Table);
+        proxies.push(Measure::of::<P>(world).advice::<P>(info, slots));
+        T::advise(world, slots, proxies);
+    }
+}
+impl<P: ArchiveProxy + Serialize<Serializer>, T: Advise> Advise for HCons<DedupTable<P>, T> {
+    fn advise(world: &World, slots: usize, proxies: &mut Vec<ProxyAdvice>) {
+        let info = ComponentInfo::new::<P>(Storage::DedupTable);
+        proxies.push(Measure::of::<P>(world).advice::<P>(info, slots));
+        T::advise(world, slots, proxies);
+    }
+}
+impl<P: ColumnarProxy, T: Advise> Advise for HCons<Columnar<P>, T> {
+    fn advise(world: &World, slots: usize, proxies: &mut Vec<ProxyAdvice>) {
+        let info = ComponentInfo::new::<P>(Storage::Columnar);
+        let measure = Measure::count::<P::Target>(world, info.archived_size);
+        proxies.push(measure.advice::<P>(info, slots));
+        T::advise(world, slots, proxies);
+    }
+}
+impl<K, E, T> Advise for HCons<Encoded<K, E>, T>
+where
+    K: TableKind,
+    K::Proxy: Serialize<Serializer>,
+    E: Codec,
+    T: Advise,
+{
+    fn advise(world: &World, slots: usize, proxies: &mut Vec<ProxyAdvice>) {
+        let info = ComponentInfo::new::<K::Proxy>(K::STORAGE).with_codec(E::NAME);
+        let measure = Measure::of::<K::Proxy>(world);
+        proxies.push(measure.advice::<K::Proxy>(info, slots));
+        T::advise(world, slots, proxies);
+    }
+}
+impl<P: ArchiveProxy + Serialize<Serializer>, T: Advise> Advise for HCons<Auto<P>, T> {
+    fn advise(world: &World, slots: usize, proxies: &mut Vec<ProxyAdvice>) {
+        let info = ComponentInfo::new::<P>(Storage::Auto);
+        proxies.push(Measure::of::<P>(world).advice::<P>(info, slots));
+        T::advise(world, slots, proxies);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use super::Advice;
+    use crate::proxy::Id;
+    use crate::schema::Storage;
+    use crate::{Archive, DedupTable, Deserialize, HCons, Inline, Serialize, Table};
+
+    #[derive(Component, Clone, Copy, Archive, Deserialize, Serialize)]
+    struct Everywhere(u8);
+
+    #[derive(Component, Clone, Copy, Archive, Deserialize, Serialize)]
+    struct Rare([u64; 8]);
+
+    #[derive(Component, Clone, Copy, Archive, Deserialize, Serialize)]
+    struct Shared([u64; 8]);
+
+    type Tables = HCons<Table<Id<Everywhere>>, HCons<DedupTable<Id<Rare>>, ()>>;
+    type Inlines = HCons<Inline<Id<Shared>>, ()>;
+
+    #[test]
+    fn recommend() {
+        let mut world = World::new();
+        for i in 0..1000 {
+            let mut entity = world.spawn((Everywhere(1), Shared([i % 2; 8])));
+            if i % 100 == 0 {
+                entity.insert(Rare([i; 8]));
+            }
+        }
+        let advice = Advice::new::<Tables, Inlines>(&world);
+        assert_eq!(advice.entity_count, 1000);
+
+        assert_eq!(advice.proxies.len(), 3);
+        let (everywhere, rare, shared) =
+            (&advice.proxies[0], &advice.proxies[1], &advice.proxies[2]);
+        assert_eq!((everywhere.occupancy, everywhere.distinct), (1000, 1));
+        assert_eq!((rare.occupancy, rare.distinct), (10, 10));
+        assert_eq!((shared.occupancy, shared.distinct), (1000, 2));
+        // `u8` has no niche, so inline takes 2 bytes, but so does a key.
+        assert_eq!(everywhere.costs.inline, 1001 * 2);
+        assert_eq!(everywhere.costs.table, Some(1001 * 2 + 1000));
+        assert_eq!(everywhere.recommended(), Storage::Inline);
+        assert_eq!(rare.recommended(), Storage::Table);
+        assert_eq!(shared.recommended(), Storage::DedupTable);
+
+        let plugin = advice.plugin();
+        let expected = "Plugin!(\n    Inline[Id<Everywhere>]\n    DedupTable[Id<Shared>]\n    \
+            Table[Id<Rare>]\n)";
+        assert_eq!(plugin, expected);
+    }
+}
@@ -0,0 +1,456 @@
+//! Encode `Table` and `DedupTable` columns in the file, decode them at load.
+//!
+//! Add a codec to a proxy in `Plugin!` with `=>`:
+//!
+//! ```text
+//! Plugin!(
+//!     DedupTable[TeamProxy => Dictionary]
+//!     Table[Id<Position> => Delta, Id<AmmoCount> => BitPacked]
+//! )
+//! ```
+//!
+//! The proxy must implement [`Words`], codecs read and write values as a
+//! fixed number of `u32` words. All codecs here are lossless, for lossy
+//! quantization, use a quantized proxy such as `proxy::QuantizedTransform`.
+//!
+//! - [`Delta`]: Each word is stored as the difference with the same word of
+//!   the previous value, as a variable-length integer. Use this for sorted or
+//!   slowly changing values, such as positions on a grid or increasing ids.
+//! - [`BitPacked`]: Each word is stored in as many bits as the largest value
+//!   of the column needs. Use this for small integers.
+//! - [`Dictionary`]: Distinct values are stored once, values are bit-packed
+//!   indices in the distinct values. Use this for enums in `Table` storage,
+//!   values in `DedupTable` storage are already distinct.
+//!
+//! Encoded tables are decoded into their archived form each time the archived
+//! scene is spawned: once per `to_bevy` call (the hollow scene loader calls
+//! it once per load) and once per `ChunkedSpawn`. They are not zero-copy.
+//! Values of encoded tables can't be read with [`crate::query`].
+
+use std::collections::HashMap;
+
+use crate::proxy::Id;
+
+/// A value that can be stored as `WORDS` `u32` by a [`Codec`].
+///
+/// ```text
+/// impl Words for TeamProxy {
+///     const WORDS: usize = 1;
+///     fn to_words(&self, out: &mut Vec<u32>) {
+///         out.push(*self as u32);
+///     }
+///     fn from_words(words: &[u32]) -> Self {
+///         match words[0] {
+///             0 => TeamProxy::Red,
+///             _ => TeamProxy::Blue,
+///         }
+///     }
+/// }
+/// ```
+pub trait Words: Sized {
+    /// How many words a value takes.
+    const WORDS: usize;
+    /// Push the `WORDS` words of `self` to `out`.
+    fn to_words(&self, out: &mut Vec<u32>);
+    /// The value of `words`, as pushed by [`Words::to_words`].
+    fn from_words(words: &[u32]) -> Self;
+}
+
+macro_rules! unsigned_words {
+    ($( $ty:ty ),*) => {$(
+        impl Words for $ty {
+            const WORDS: usize = 1;
+            fn to_words(&self, out: &mut Vec<u32>) {
+                out.push(u32::from(*self));
+            }
+            fn from_words(words: &[u32]) -> Self {
+                words[0] as $ty
+            }
+        }
+    )*};
+}
+/// Signed integers are zigzag-encoded, so that small negative numbers are
+/// small words.
+macro_rules! signed_words {
+    ($( $ty:ty ),*) => {$(
+        impl Words for $ty {
+            const WORDS: usize = 1;
+            fn to_words(&self, out: &mut Vec<u32>) {
+                out.push(zigzag(i32::from(*self)));
+            }
+            fn from_words(words: &[u32]) -> Self {
+                unzigzag(words[0]) as $ty
+            }
+        }
+    )*};
+}
+unsigned_words!(u8, u16);
+signed_words!(i8, i16);
+
+impl Words for bool {
+    const WORDS: usize = 1;
+    fn to_words(&self, out: &mut Vec<u32>) {
+        out.push(u32::from(*self));
+    }
+    fn from_words(words: &[u32]) -> Self {
+        words[0] != 0
+    }
+}
+impl Words for u32 {
+    const WORDS: usize = 1;
+    fn to_words(&self, out: &mut Vec<u32>) {
+        out.push(*self);
+    }
+    fn from_words(words: &[u32]) -> Self {
+        words[0]
+    }
+}
+impl Words for i32 {
+    const WORDS: usize = 1;
+    fn to_words(&self, out: &mut Vec<u32>) {
+        out.push(zigzag(*self));
+    }
+    fn from_words(words: &[u32]) -> Self {
+        unzigzag(words[0])
+    }
+}
+
+impl Words for f32 {
+    const WORDS: usize = 1;
+    fn to_words(&self, out: &mut Vec<u32>) {
+        out.push(self.to_bits());
+    }
+    fn from_words(words: &[u32]) -> Self {
+        f32::from_bits(words[0])
+    }
+}
+impl Words for u64 {
+    const WORDS: usize = 2;
+    fn to_words(&self, out: &mut Vec<u32>) {
+        out.extend([*self as u32, (*self >> 32) as u32]);
+    }
+    fn from_words(words: &[u32]) -> Self {
+        u64::from(words[0]) | (u64::from(words[1]) << 32)
+    }
+}
+impl Words for f64 {
+    const WORDS: usize = 2;
+    fn to_words(&self, out: &mut Vec<u32>) {
+        self.to_bits().to_words(out);
+    }
+    fn from_words(words: &[u32]) -> Self {
+        f64::from_bits(u64::from_words(words))
+    }
+}
+impl Words for () {
+    const WORDS: usize = 0;
+    fn to_words(&self, _: &mut Vec<u32>) {}
+    fn from_words(_: &[u32]) {}
+}
+impl<T: Words, const N: usize> Words for [T; N] {
+    const WORDS: usize = N * T::WORDS;
+    fn to_words(&self, out: &mut Vec<u32>) {
+        self.iter().for_each(|value| value.to_words(out));
+    }
+    fn from_words(words: &[u32]) -> Self {
+        std::array::from_fn(|i| T::from_words(&words[i * T::WORDS..][..T::WORDS]))
+    }
+}
+impl<T: Words> Words for Id<T> {
+    const WORDS: usize = T::WORDS;
+    fn to_words(&self, out: &mut Vec<u32>) {
+        self.0.to_words(out);
+    }
+    fn from_words(words: &[u32]) -> Self {
+        Id(T::from_words(words))
+    }
+}
+
+/// How a table column is encoded in the file, see the [module docs](self).
+pub trait Codec: 'static {
+    /// The name of the codec, part of the schema fingerprint.
+    const NAME: &'static str;
+    /// Encode `words`, values of `lanes` words each, one after the other.
+    ///
+    /// `lanes` is never zero.
+    fn encode(words: &[u32], lanes: usize, out: &mut Vec<u8>);
+    /// Push the words of `len` values of `lanes` words each, encoded in
+    /// `bytes` by [`Codec::encode`], to `out`.
+    ///
+    /// Bytes come from the file: missing bytes are read as zero, and values
+    /// that can't be decoded are zero.
+    fn decode(bytes: &[u8], lanes: usize, len: usize, out: &mut Vec<u32>);
+}
+
+/// Store the difference with the previous value, see the [module docs](self).
+pub struct Delta;
+
+impl Codec for Delta {
+    const NAME: &'static str = "Delta";
+
+    fn encode(words: &[u32], lanes: usize, out: &mut Vec<u8>) {
+        let mut previous = vec![0_u32; lanes];
+        for (i, word) in words.iter().enumerate() {
+            let delta = word.wrapping_sub(previous[i % lanes]) as i32;
+            write_varint(zigzag(delta), out);
+            previous[i % lanes] = *word;
+        }
+    }
+    fn decode(mut bytes: &[u8], lanes: usize, len: usize, out: &mut Vec<u32>) {
+        let mut previous = vec![0_u32; lanes];
+        for i in 0..len * lanes {
+            let delta = unzigzag(read_varint(&mut bytes));
+            let word = previous[i % lanes].wrapping_add(delta as u32);
+            out.push(word);
+            previous[i % lanes] = word;
+        }
+    }
+}
+
+/// Store each word in as few bits as possible, see the [module docs](self).
+pub struct BitPacked;
+
+impl Codec for BitPacked {
+    const NAME: &'static str = "BitPacked";
+
+    fn encode(words: &[u32], lanes: usize, out: &mut Vec<u8>) {
+        let mut widths = vec![0_u8; lanes];
+        for (i, word) in words.iter().enumerate() {
+            widths[i % lanes] = widths[i % lanes].max(width(*word));
+        }
+        out.extend_from_slice(&widths);
+        let mut writer = BitWriter::new(out);
+        for (i, word) in words.iter().enumerate() {
+            writer.write(*word, widths[i % lanes]);
+        }
+        writer.finish();
+    }
+    fn decode(bytes: &[u8], lanes: usize, len: usize, out: &mut Vec<u32>) {
+        let (widths, bytes) = bytes.split_at(lanes.min(bytes.len()));
+        if widths.iter().any(|width| *width > MAX_WIDTH) {
+            out.extend((0..len * lanes).map(|_| 0));
+            return;
+        }
+        let width = |lane: usize| widths.get(lane).copied().unwrap_or(0);
+        let mut reader = BitReader::new(bytes);
+        out.extend((0..len * lanes).map(|i| reader.read(width(i % lanes))));
+    }
+}
+
+/// Store distinct values once, see the [module docs](self).
+pub struct Dictionary;
+
+impl Codec for Dictionary {
+    const NAME: &'static str = "Dictionary";
+
+    fn encode(words: &[u32], lanes: usize, out: &mut Vec<u8>) {
+        let mut entries: HashMap<&[u32], u32> = HashMap::new();
+        let mut dictionary: Vec<&[u32]> = Vec::new();
+        let indices: Vec<u32> = words
+            .chunks_exact(lanes)
+            .map(|value| {
+                *entries.entry(value).or_insert_with(|| {
+                    dictionary.push(value);
+                    dictionary.len() as u32 - 1
+                })
+            })
+            .collect();
+
+        write_varint(dictionary.len() as u32, out);
+        for word in dictionary.iter().flat_map(|value| value.iter()) {
+            out.extend_from_slice(&word.to_le_bytes());
+        }
+        let index_width = width(dictionary.len().saturating_sub(1) as u32);
+        out.push(index_width);
+        let mut writer = BitWriter::new(out);
+        for index in indices {
+            writer.write(index, index_width);
+        }
+        writer.finish();
+    }
+    fn decode(mut bytes: &[u8], lanes: usize, len: usize, out: &mut Vec<u32>) {
+        let count = read_varint(&mut bytes) as usize;
+        let (entries, bytes) = bytes.split_at((count * lanes * 4).min(bytes.len()));
+        let entries: Vec<u32> = entries
+            .chunks_exact(4)
+            .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+            .collect();
+
+        let (index_width, bytes) = bytes.split_first().map_or((0, &[][..]), |(w, b)| (*w, b));
+        if index_width > MAX_WIDTH {
+            out.extend((0..len * lanes).map(|_| 0));
+            return;
+        }
+        let mut reader = BitReader::new(bytes);
+        for _ in 0..len {
+            let index = reader.read(index_width) as usize;
+            match entries.get(index * lanes..(index + 1) * lanes) {
+                Some(value) => out.extend_from_slice(value),
+                None => out.extend((0..lanes).map(|_| 0)),
+            }
+        }
+    }
+}
+
+/// The largest bit width, what a `u32` needs.
+const MAX_WIDTH: u8 = u32::BITS as u8;
+
+/// How many bits are needed to store `word`.
+fn width(word: u32) -> u8 {
+    (u32::BITS - word.leading_zeros()) as u8
+}
+fn zigzag(value: i32) -> u32 {
+    ((value << 1) ^ (value >> 31)) as u32
+}
+fn unzigzag(value: u32) -> i32 {
+    (value >> 1) as i32 ^ -((value & 1) as i32)
+}
+/// Write `value` as a LEB128 variable-length integer.
+fn write_varint(mut value: u32, out: &mut Vec<u8>) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+/// Read a LEB128 variable-length integer at the start of `bytes`, and advance
+/// `bytes` past it. Missing bytes are read as zero.
+fn read_varint(bytes: &mut &[u8]) -> u32 {
+    let mut value = 0;
+    let mut shift = 0;
+    while let Some((&byte, rest)) = bytes.split_first() {
+        *bytes = rest;
+        if shift < u32::BITS {
+            value |= u32::from(byte & 0x7f) << shift;
+        }
+        shift += 7;
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    value
+}
+
+/// Write integers of arbitrary bit width, least significant bits first.
+struct BitWriter<'a> {
+    out: &'a mut Vec<u8>,
+    pending: u64,
+    pending_bits: u32,
+}
+impl<'a> BitWriter<'a> {
+    fn new(out: &'a mut Vec<u8>) -> Self {
+        BitWriter { out, pending: 0, pending_bits: 0 }
+    }
+    /// Write the `width` lowest bits of `value`.
+    fn write(&mut self, value: u32, width: u8) {
+        let width = u32::from(width);
+        let mask = (1_u64 << width) - 1;
+        self.pending |= (u64::from(value) & mask) << self.pending_bits;
+        self.pending_bits += width;
+        while self.pending_bits >= 8 {
+            self.out.push(self.pending as u8);
+            self.pending >>= 8;
+            self.pending_bits -= 8;
+        }
+    }
+    /// Write the last incomplete byte, if any.
+    fn finish(self) {
+        if self.pending_bits > 0 {
+            self.out.push(self.pending as u8);
+        }
+    }
+}
+/// Read integers written by a [`BitWriter`]. Missing bytes are read as zero.
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    pending: u64,
+    pending_bits: u32,
+}
+impl<'a> BitReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        BitReader { bytes, pending: 0, pending_bits: 0 }
+    }
+    /// Read a `width` bits integer, `width` is at most [`MAX_WIDTH`].
+    fn read(&mut self, width: u8) -> u32 {
+        let width = u32::from(width);
+        while self.pending_bits < width {
+            let byte = match self.bytes.split_first() {
+                Some((byte, rest)) => {
+                    self.bytes = rest;
+                    *byte
+                }
+                None => 0,
+            };
+            self.pending |= u64::from(byte) << self.pending_bits;
+            self.pending_bits += 8;
+        }
+        let value = self.pending & ((1_u64 << width) - 1);
+        self.pending >>= width;
+        self.pending_bits -= width;
+        value as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BitPacked, Codec, Delta, Dictionary, Words};
+
+    fn roundtrip<C: Codec, T: Words + PartialEq + std::fmt::Debug>(values: &[T]) -> usize {
+        let mut words = Vec::new();
+        values.iter().for_each(|value| value.to_words(&mut words));
+        let mut bytes = Vec::new();
+        C::encode(&words, T::WORDS, &mut bytes);
+
+        let mut decoded = Vec::new();
+        C::decode(&bytes, T::WORDS, values.len(), &mut decoded);
+        let decoded: Vec<_> = decoded.chunks_exact(T::WORDS).map(T::from_words).collect();
+        assert_eq!(decoded, values);
+        bytes.len()
+    }
+
+    #[test]
+    fn codecs() {
+        let ids: Vec<u32> = (0..1000).map(|i| 1_000_000 + 3 * i).collect();
+        let counts: Vec<u16> = (0..1000).map(|i| i % 13).collect();
+        let offsets: Vec<i8> = (0..1000).map(|i| (i % 7) as i8 - 3).collect();
+        let kinds: Vec<u8> = (0..1000).map(|i| [0, 4, 200][i % 3]).collect();
+        let positions: Vec<[f32; 3]> = (0..1000)
+            .map(|i| [(i % 10) as f32, (i / 10) as f32 * 0.5, -3.0])
+            .collect();
+
+        // The first value in 3 bytes, then 1 byte per value.
+        assert_eq!(roundtrip::<Delta, _>(&ids), 3 + 999);
+        // The width, then 4 bits per value.
+        assert_eq!(roundtrip::<BitPacked, _>(&counts), 1 + 500);
+        // -3..=3 is zigzag-encoded as 0..=6, 3 bits.
+        assert_eq!(roundtrip::<BitPacked, _>(&offsets), 1 + 375);
+        // 3 entries of 4 bytes, then 2 bits per value.
+        assert_eq!(roundtrip::<Dictionary, _>(&kinds), 1 + 12 + 1 + 250);
+
+        for codec in [
+            roundtrip::<Delta, [f32; 3]>,
+            roundtrip::<BitPacked, [f32; 3]>,
+            roundtrip::<Dictionary, [f32; 3]>,
+        ] {
+            assert!(codec(&positions) < 1000 * 12);
+        }
+        roundtrip::<Dictionary, _>(&ids);
+        roundtrip::<Delta, _>(&kinds);
+        roundtrip::<Delta, [u64; 1]>(&[]);
+        roundtrip::<Dictionary, f64>(&[f64::MAX, -0.0, f64::MIN_POSITIVE, -0.0]);
+        roundtrip::<BitPacked, bool>(&[true, false, false]);
+    }
+
+    #[test]
+    fn corrupt_widths() {
+        let mut decoded = Vec::new();
+        // Lane widths of 255 and 33 bits.
+        BitPacked::decode(&[255, 33, 1, 2, 3], 2, 2, &mut decoded);
+        assert_eq!(decoded, [0; 4]);
+
+        decoded.clear();
+        // 1 entry of 4 bytes, then indices of 64 bits.
+        Dictionary::decode(&[1, 7, 0, 0, 0, 64, 0xff, 0xff], 1, 3, &mut decoded);
+        assert_eq!(decoded, [0; 3]);
+    }
+}
@@ -0,0 +1,861 @@
+//! Runtime-configured schemas, an alternative to the `Plugin!` type lists.
+//!
+//! A [`DynamicSchema`] is a list of type-erased [`DynamicProxy`], built at
+//! runtime. This is useful for generic tools (such as editors or converters)
+//! and mods, that can't know all components at compile time.
+//!
+//! A `DynamicSchema` reads and writes the exact same bytes as the `FastScene`
+//! of the equivalent `Plugin!`, so each can read the other's files. Proxies
+//! must be added in the order `Plugin!` stores them: `Table`s, then
+//! `DedupTable`s, then `Inline`s, each in declaration order. The header
+//! fingerprint catches mismatches.
+//!
+//! ```text
+//! let schema: DynamicSchema = [
+//!     DynamicProxy::table::<Id<Mesh>>(),
+//!     DynamicProxy::inline::<Id<Transform>>(),
+//! ]
+//! .into_iter()
+//! .collect();
+//!
+//! // Reads and writes the same files as
+//! Plugin!(Table[Id<Mesh>], Inline[Id<Transform>])
+//! ```
+//!
+//! Unlike `Plugin!`, a `DynamicSchema` doesn't support entity filters,
+//! `Columnar` storage, `Auto` storage nor tables with a [`crate::codec::Codec`].
+
+use std::{alloc::Layout, any::Any, num::NonZeroU16};
+
+use ::bevy::ecs::world::{EntityMut, EntityRef};
+use ::bevy::prelude::BuildWorldChildren;
+use bevy::prelude as bevy;
+use rkyv::boxed::{ArchivedBox, BoxResolver};
+use rkyv::ser::{serializers::AllocSerializer, Serializer as _};
+use rkyv::string::{repr::ArchivedStringRepr, ArchivedString};
+use rkyv::{vec::ArchivedVec, AlignedVec, Archive, Fallible, FixedUsize, Serialize};
+
+use crate::header::{self, Header};
+use crate::hooks::SpawnHooks;
+use crate::names::{ArchivedNameIndex, NameIndex};
+use crate::schema::{ComponentInfo, Schema, Storage};
+use crate::strings::{ArchivedStringPool, Interner, SceneStrings};
+use crate::{ArchiveProxy, HollowSceneRoot, SaveOptions};
+
+/// The serializer of the hollow scene saver.
+pub type Serializer = AllocSerializer<1024>;
+type Error = <Serializer as Fallible>::Error;
+type Resolver = Box<dyn Any>;
+
+/// A type-erased [`ArchiveProxy`], to add to a [`DynamicSchema`].
+#[derive(Clone)]
+pub struct DynamicProxy {
+    info: ComponentInfo,
+    /// Layout of `P::Archived`.
+    layout: Layout,
+    /// `P::from_target` of the entity's component, if it has one.
+    extract: fn(EntityRef) -> Option<Box<dyn Any>>,
+    /// Whether a `P` is equal to the entity's component, for `DedupTable`s.
+    same_as: Option<fn(&dyn Any, EntityRef) -> bool>,
+    /// Serialize the out-of-line data of a `P`, returns its resolver.
+    serialize: fn(&dyn Any, &mut Serializer) -> Result<Resolver, Error>,
+    /// Write the archived `P` at `out`, `pos` is the position of `out` in
+    /// the archive.
+    resolve: unsafe fn(&dyn Any, usize, Resolver, *mut u8),
+    /// Insert the target of the archived `P` at the given address.
+    insert: unsafe fn(*const u8, &mut EntityMut),
+}
+impl DynamicProxy {
+    fn new<P>(storage: Storage) -> Self
+    where
+        P: ArchiveProxy + Serialize<Serializer> + 'static,
+        P::Resolver: 'static,
+    {
+        let layout = Layout::new::<P::Archived>();
+        assert!(
+            layout.align() <= AlignedVec::ALIGNMENT,
+            "Archived proxies aligned to more than {} bytes are not supported",
+            AlignedVec::ALIGNMENT,
+        );
+        DynamicProxy {
+            info: ComponentInfo::new::<P>(storage),
+            layout,
+            extract: extract::<P>,
+            same_as: None,
+            serialize: serialize::<P>,
+            resolve: resolve::<P>,
+            insert: insert::<P>,
+        }
+    }
+    /// Store `P` like `Plugin!(Inline[P])`.
+    pub fn inline<P>() -> Self
+    where
+        P: ArchiveProxy + Serialize<Serializer> + 'static,
+        P::Resolver: 'static,
+    {
+        Self::new::<P>(Storage::Inline)
+    }
+    /// Store `P` like `Plugin!(Table[P])`.
+    pub fn table<P>() -> Self
+    where
+        P: ArchiveProxy + Serialize<Serializer> + 'static,
+        P::Resolver: 'static,
+    {
+        Self::new::<P>(Storage::Table)
+    }
+    /// Store `P` like `Plugin!(DedupTable[P])`.
+    pub fn dedup_table<P>() -> Self
+    where
+        P: ArchiveProxy + PartialEq<P::Target> + Serialize<Serializer> + 'static,
+        P::Resolver: 'static,
+    {
+        DynamicProxy {
+            same_as: Some(same_as::<P>),
+            ..Self::new::<P>(Storage::DedupTable)
+        }
+    }
+    pub fn info(&self) -> &ComponentInfo {
+        &self.info
+    }
+}
+fn extract<P: ArchiveProxy + 'static>(entity: EntityRef) -> Option<Box<dyn Any>> {
+    let target = entity.get::<P::Target>()?;
+    Some(Box::new(P::from_target(target)))
+}
+fn same_as<P>(value: &dyn Any, entity: EntityRef) -> bool
+where
+    P: ArchiveProxy + PartialEq<P::Target> + 'static,
+{
+    // unwrap: all values of a proxy's table are created by its `extract`
+    let value = value.downcast_ref::<P>().unwrap();
+    entity
+        .get::<P::Target>()
+        .map_or(false, |target| value == target)
+}
+fn serialize<P>(value: &dyn Any, serializer: &mut Serializer) -> Result<Resolver, Error>
+where
+    P: Serialize<Serializer> + 'static,
+    P::Resolver: 'static,
+{
+    // unwrap: all values of a proxy are created by its `extract`
+    let value = value.downcast_ref::<P>().unwrap();
+    Ok(Box::new(value.serialize(serializer)?))
+}
+unsafe fn resolve<P>(value: &dyn Any, pos: usize, resolver: Resolver, out: *mut u8)
+where
+    P: Archive + 'static,
+    P::Resolver: 'static,
+{
+    // unwrap: `resolver` was returned by `serialize::<P>(value)`
+    let value = value.downcast_ref::<P>().unwrap();
+    let resolver = *resolver.downcast::<P::Resolver>().unwrap();
+    value.resolve(pos, resolver, out.cast());
+}
+unsafe fn insert<P: ArchiveProxy>(archived: *const u8, entity: &mut EntityMut) {
+    let archived = &*archived.cast::<P::Archived>();
+    let target = P::to_target_in(archived, Some(entity.world()));
+    entity.insert(target);
+    SpawnHooks::run_in_world::<P::Target>(entity);
+}
+
+/// A list of proxies to store in a hollow scene, see the [module docs](self).
+#[derive(Clone, Default)]
+pub struct DynamicSchema {
+    tables: Vec<DynamicProxy>,
+    inlines: Vec<DynamicProxy>,
+}
+impl FromIterator<DynamicProxy> for DynamicSchema {
+    fn from_iter<T: IntoIterator<Item = DynamicProxy>>(iter: T) -> Self {
+        let mut schema = DynamicSchema::default();
+        for proxy in iter {
+            schema.add(proxy);
+        }
+        schema
+    }
+}
+impl DynamicSchema {
+    /// Add `proxy` after the already added proxies with the same storage.
+    pub fn add(&mut self, proxy: DynamicProxy) -> &mut Self {
+        match proxy.info.storage {
+            Storage::Inline => self.inlines.push(proxy),
+            Storage::Table | Storage::DedupTable | Storage::Columnar | Storage::Auto => {
+                self.tables.push(proxy)
+            }
+        }
+        self
+    }
+    pub fn schema(&self) -> Schema {
+        let infos = |proxies: &[DynamicProxy]| proxies.iter().map(|p| p.info.clone()).collect();
+        Schema {
+            tables: infos(&self.tables),
+            inlines: infos(&self.inlines),
+        }
+    }
+    /// Serialize `scene`, header included, like the hollow scene saver.
+    ///
+    /// [`SaveOptions::deterministic`] and [`SaveOptions::name_index`] are
+    /// supported.
+    pub fn save(&self, scene: &bevy::Scene, options: SaveOptions) -> anyhow::Result<Vec<u8>> {
+        let payload = self.serialize(&scene.world, options)?;
+
+        let header = Header::new(&self.schema());
+        let mut bytes = Vec::with_capacity(header::LEN + payload.len());
+        bytes.extend_from_slice(&header.to_bytes());
+        bytes.extend_from_slice(&payload);
+        Ok(bytes)
+    }
+    /// Read a `.hollow_bvyfst` file saved with this schema, or the
+    /// equivalent `Plugin!`.
+    ///
+    /// Returns an error if the structure of the file is corrupted, such as
+    /// keys to missing table values or strings past the end of the file.
+    /// The archived proxy values themselves are not validated.
+    pub fn load(&self, bytes: &[u8]) -> anyhow::Result<bevy::Scene> {
+        let (header, payload) = Header::read(bytes)?;
+        header.check(&self.schema())?;
+        let payload = header::aligned(payload);
+
+        let layout = self.layout();
+        self.check(&payload, &layout)?;
+        let mut world = bevy::World::new();
+        // SAFETY: `check` verified that all entities, keys and strings are
+        // in `payload`. Proxy values are trusted, like with `Plugin!`.
+        unsafe { self.spawn(&payload, &layout, &mut world) };
+        Ok(bevy::Scene::new(world))
+    }
+    /// Check that the entities, tables, keys, hierarchy and strings of
+    /// `payload` are within `payload`, so that [`Self::spawn`] doesn't read
+    /// out of bounds.
+    ///
+    /// The values of the proxies are not checked, for example a `String` in
+    /// an archived proxy is read as is.
+    fn check(&self, payload: &[u8], layout: &SceneLayout) -> anyhow::Result<()> {
+        use anyhow::ensure;
+
+        ensure!(
+            payload.len() >= layout.root.size(),
+            "The scene is too short ({} bytes)",
+            payload.len()
+        );
+        let root_pos = payload.len() - layout.root.size();
+        ensure!(
+            root_pos % layout.root.align() == 0,
+            "The scene root is misaligned"
+        );
+        // SAFETY: the root is in `payload` and aligned. Slices of `()` are
+        // valid whatever their length, we only read their pointer and length.
+        let root = payload[root_pos..].as_ptr();
+        let entities = unsafe { (*root.add(layout.entities).cast::<ArchivedBox<[()]>>()).get() };
+        let first_entity = entities.as_ptr().cast::<u8>();
+        ensure!(
+            in_bytes(payload, first_entity, layout.entity, entities.len()),
+            "The {} entities are out of bounds",
+            entities.len()
+        );
+        let mut table_lens = Vec::with_capacity(self.tables.len());
+        for (proxy, offset) in self.tables.iter().zip(&layout.tables) {
+            let table = unsafe { &*root.add(*offset).cast::<ArchivedVec<()>>() };
+            ensure!(
+                in_bytes(payload, table.as_ptr().cast(), proxy.layout, table.len()),
+                "The {} table is out of bounds",
+                proxy.info.proxy
+            );
+            table_lens.push(table.len());
+        }
+
+        // One past the last descendant of the ancestors of the current entity.
+        let mut ancestors: Vec<usize> = Vec::new();
+        for index in 0..entities.len() {
+            while matches!(ancestors.last(), Some(&end) if end <= index) {
+                ancestors.pop();
+            }
+            // SAFETY: the entity is in `payload`, and so are its fields.
+            let archived = unsafe { first_entity.add(index * layout.entity.size()) };
+            let byte = |offset: usize| unsafe { *archived.add(offset) };
+            let fields = layout.keys.iter().chain(&layout.inlines);
+            ensure!(
+                fields.all(|field| byte(field.tag) <= 1),
+                "Entity {index} has an invalid component tag"
+            );
+            for ((proxy, field), len) in self.tables.iter().zip(&layout.keys).zip(&table_lens) {
+                if byte(field.tag) != 0 {
+                    let key = unsafe { archived.add(field.value).cast::<u16>().read() };
+                    ensure!(
+                        (1..=*len).contains(&usize::from(key)),
+                        "Entity {index} has key {key} in the {} table of {len} values",
+                        proxy.info.proxy
+                    );
+                }
+            }
+            let children = unsafe { archived.add(layout.children).cast::<u32>().read() };
+            let end = (index + 1).checked_add(children as usize);
+            let parent_end = ancestors.last().copied().unwrap_or(entities.len());
+            ensure!(
+                end.map_or(false, |end| end <= parent_end),
+                "Entity {index} has {children} descendants, more than its parent"
+            );
+            ancestors.push(index + 1 + children as usize);
+        }
+
+        let names = unsafe { &*root.add(layout.names).cast::<ArchivedNameIndex>() };
+        let strings = unsafe { &*root.add(layout.strings).cast::<ArchivedStringPool>() };
+        ensure!(
+            names.check(payload, entities.len()),
+            "The name index is out of bounds"
+        );
+        ensure!(strings.check(payload), "The string pool is out of bounds");
+        Ok(())
+    }
+
+    fn serialize(&self, world: &bevy::World, options: SaveOptions) -> Result<AlignedVec, Error> {
+        let layout = self.layout();
+        let (ids, children) = order(world, options.deterministic);
+
+        // Extract all proxies. Entities are flattened, the values of the
+        // `n`th entity are at `n * proxy_count..(n + 1) * proxy_count`.
+        let mut tables: Vec<Vec<Box<dyn Any>>> = self.tables.iter().map(|_| Vec::new()).collect();
+        let mut keys: Vec<Option<u16>> = Vec::with_capacity(ids.len() * self.tables.len());
+        let mut inlines = Vec::with_capacity(ids.len() * self.inlines.len());
+
+        // The first entity is the scene root, it doesn't exist in `world`.
+        keys.extend(self.tables.iter().map(|_| None));
+        inlines.extend(self.inlines.iter().map(|_| None));
+        let interner = Interner::default();
+        interner.scope(|| {
+            for &id in &ids[1..] {
+                let entity = world.entity(id);
+                for (proxy, table) in self.tables.iter().zip(&mut tables) {
+                    let index = (proxy.extract)(entity).map(|value| {
+                        let same = |stored: &dyn Any| {
+                            proxy.same_as.map_or(false, |same| same(stored, entity))
+                        };
+                        table
+                            .iter()
+                            .position(|stored| same(stored.as_ref()))
+                            .unwrap_or_else(|| {
+                                table.push(value);
+                                table.len() - 1
+                            })
+                    });
+                    keys.push(index.map(|i| u16::try_from(i + 1).unwrap()));
+                }
+                inlines.extend(self.inlines.iter().map(|proxy| (proxy.extract)(entity)));
+            }
+        });
+        let strings = interner.to_pool();
+        let names = if options.name_index {
+            NameIndex::new(world, &children, &ids)
+        } else {
+            NameIndex::default()
+        };
+
+        // We write values in the same order as `FastScene::serialize`, so that
+        // both produce the same bytes.
+        let mut serializer = Serializer::default();
+        let s = &mut serializer;
+
+        // Entities
+        let mut inline_resolvers = Vec::with_capacity(inlines.len());
+        for (proxy, value) in self.inlines.iter().cycle().zip(&inlines) {
+            let serialize = |value: &dyn Any| (proxy.serialize)(value, s);
+            inline_resolvers.push(value.as_deref().map(serialize).transpose()?);
+        }
+        let mut inlines = inlines.iter().zip(inline_resolvers);
+        let mut keys = keys.into_iter();
+
+        let entities_pos = s.align(layout.entity.align())?;
+        for &entity_children in &children {
+            write_with(s, layout.entity, |pos, out| {
+                let children_bytes = entity_children.to_ne_bytes();
+                out[layout.children..][..children_bytes.len()].copy_from_slice(&children_bytes);
+
+                for (proxy, field) in self.inlines.iter().zip(&layout.inlines) {
+                    // unwrap: there is one value per entity and inline proxy
+                    if let (Some(value), Some(resolver)) = inlines.next().unwrap() {
+                        out[field.tag] = 1;
+                        let out = out[field.value..].as_mut_ptr();
+                        unsafe {
+                            (proxy.resolve)(value.as_ref(), pos + field.value, resolver, out)
+                        };
+                    }
+                }
+                for field in &layout.keys {
+                    // unwrap: there is one key per entity and table
+                    if let Some(key) = keys.next().unwrap() {
+                        let key_bytes = key.to_ne_bytes();
+                        out[field.tag] = 1;
+                        out[field.value..][..key_bytes.len()].copy_from_slice(&key_bytes);
+                    }
+                }
+            })?;
+        }
+        // Tables
+        let mut table_resolvers = Vec::with_capacity(tables.len());
+        for (proxy, values) in self.tables.iter().zip(&tables) {
+            let resolvers = values
+                .iter()
+                .map(|value| (proxy.serialize)(value.as_ref(), s));
+            let resolvers: Vec<_> = resolvers.collect::<Result<_, _>>()?;
+
+            s.align(proxy.layout.align())?;
+            // SAFETY: there is nothing to copy in an empty slice. This is only
+            // to get a `VecResolver` pointing to the first value.
+            let empty: &[()] = &[];
+            table_resolvers.push(unsafe { ArchivedVec::serialize_copy_from_slice(empty, s)? });
+            for (value, resolver) in values.iter().zip(resolvers) {
+                write_with(s, proxy.layout, |pos, out| unsafe {
+                    (proxy.resolve)(value.as_ref(), pos, resolver, out.as_mut_ptr());
+                })?;
+            }
+        }
+        let names_resolver = names.serialize(s)?;
+        let strings_resolver = strings.serialize(s)?;
+
+        // The `FastScene`, at the end of the archive, like `serialize_value`.
+        write_with(s, layout.root, |pos, out| unsafe {
+            let len = ids.len() as FixedUsize;
+            let entities = out[layout.entities..]
+                .as_mut_ptr()
+                .cast::<ArchivedBox<[()]>>();
+            let resolver = BoxResolver::from_raw_parts(entities_pos, len);
+            ArchivedBox::resolve_from_raw_parts(pos + layout.entities, resolver, entities);
+
+            let resolvers = tables.iter().zip(table_resolvers);
+            for ((values, resolver), offset) in resolvers.zip(&layout.tables) {
+                let table = out[*offset..].as_mut_ptr().cast::<ArchivedVec<()>>();
+                ArchivedVec::resolve_from_len(values.len(), pos + offset, resolver, table);
+            }
+            let out_names = out[layout.names..].as_mut_ptr().cast::<ArchivedNameIndex>();
+            names.resolve(pos + layout.names, names_resolver, out_names);
+            let out_strings = out[layout.strings..]
+                .as_mut_ptr()
+                .cast::<ArchivedStringPool>();
+            strings.resolve(pos + layout.strings, strings_resolver, out_strings);
+        })?;
+        Ok(serializer.into_serializer().into_inner())
+    }
+
+    /// Spawn the scene in `payload` as children of a new entity of `world`.
+    ///
+    /// # Safety
+    ///
+    /// `payload` must be a `FastScene` serialized with this schema.
+    unsafe fn spawn(&self, payload: &[u8], layout: &SceneLayout, world: &mut bevy::World) {
+        let root = payload[payload.len() - layout.root.size()..].as_ptr();
+        let entities = &*root.add(layout.entities).cast::<ArchivedBox<[()]>>();
+        let entities = entities.get();
+        let first_entity = entities.as_ptr().cast::<u8>();
+
+        let table = |offset: &usize| {
+            let table = &*root.add(*offset).cast::<ArchivedVec<()>>();
+            table.as_ptr().cast::<u8>()
+        };
+        let tables: Vec<_> = layout.tables.iter().map(table).collect();
+        let names = &*root.add(layout.names).cast::<ArchivedNameIndex>();
+        let strings = &*root.add(layout.strings).cast::<ArchivedStringPool>();
+        if !strings.is_empty() {
+            world.insert_resource(strings.to_resource());
+        }
+
+        let fingerprint = self.schema().fingerprint();
+        let root_entity = world.spawn(HollowSceneRoot { fingerprint }).id();
+        let mut ids = Vec::with_capacity(entities.len());
+        // `(entity, one past last descendant)` of the ancestors of current entity.
+        let mut ancestors: Vec<(bevy::Entity, usize)> = Vec::new();
+
+        for index in 0..entities.len() {
+            while matches!(ancestors.last(), Some(&(_, end)) if end <= index) {
+                ancestors.pop();
+            }
+            let parent = ancestors.last().map_or(root_entity, |&(parent, _)| parent);
+            let archived = first_entity.add(index * layout.entity.size());
+
+            let id = world.spawn_empty().id();
+            world.entity_mut(parent).add_child(id);
+            let mut entity = world.entity_mut(id);
+
+            let keys = self.tables.iter().zip(&layout.keys).zip(&tables);
+            for ((proxy, field), table) in keys {
+                if *archived.add(field.tag) != 0 {
+                    let key = archived.add(field.value).cast::<NonZeroU16>().read();
+                    let value = table.add(usize::from(key.get() - 1) * proxy.layout.size());
+                    (proxy.insert)(value, &mut entity);
+                }
+            }
+            for (proxy, field) in self.inlines.iter().zip(&layout.inlines) {
+                if *archived.add(field.tag) != 0 {
+                    (proxy.insert)(archived.add(field.value), &mut entity);
+                }
+            }
+            let children = archived.add(layout.children).cast::<u32>().read();
+            ids.push(id);
+            ancestors.push((id, index + 1 + children as usize));
+        }
+        if !names.is_empty() {
+            world
+                .entity_mut(root_entity)
+                .insert(names.to_component(&ids));
+        }
+        world.remove_resource::<SceneStrings>();
+    }
+
+    /// The layout of the archived `Entity` and `FastScene` of this schema.
+    ///
+    /// All archived types in a `FastScene` are `repr(C)`, except for the
+    /// `rkyv` pointers, so we can compute it here.
+    fn layout(&self) -> SceneLayout {
+        let option = |layout: Layout| repr_c(&[Layout::new::<u8>(), layout]);
+        let key = option(Layout::new::<NonZeroU16>());
+        let inlines: Vec<_> = self.inlines.iter().map(|p| option(p.layout)).collect();
+
+        let (inline_list, inline_offsets) = hlist(inlines.iter().map(|(layout, _)| *layout));
+        let (key_list, key_offsets) = hlist(self.tables.iter().map(|_| key.0));
+        // `InlineStorage` and `KeyStorage` have the layout of their only field.
+        let (entity, entity_offsets) = repr_c(&[Layout::new::<u32>(), inline_list, key_list]);
+
+        let field = |start: usize, (_, offsets): &(Layout, Vec<usize>)| OptionField {
+            tag: start + offsets[0],
+            value: start + offsets[1],
+        };
+        let inline_fields = inline_offsets.iter().zip(&inlines);
+        let inline_fields =
+            inline_fields.map(|(offset, inline)| field(entity_offsets[1] + offset, inline));
+        let key_fields = key_offsets
+            .iter()
+            .map(|offset| field(entity_offsets[2] + offset, &key));
+
+        let vec = Layout::new::<ArchivedVec<()>>();
+        let (table_list, table_offsets) = hlist(self.tables.iter().map(|_| vec));
+        let root = [
+            Layout::new::<ArchivedBox<[()]>>(),
+            table_list,
+            Layout::new::<ArchivedNameIndex>(),
+            Layout::new::<ArchivedStringPool>(),
+        ];
+        let (root, root_offsets) = repr_c(&root);
+
+        SceneLayout {
+            entity,
+            children: entity_offsets[0],
+            inlines: inline_fields.collect(),
+            keys: key_fields.collect(),
+            root,
+            entities: root_offsets[0],
+            tables: table_offsets.iter().map(|o| root_offsets[1] + o).collect(),
+            names: root_offsets[2],
+            strings: root_offsets[3],
+        }
+    }
+}
+
+/// Entities of `world` in entity slice order, and how many descendants they
+/// have, see [`crate::hierarchy::build`].
+///
+/// The first entity is the scene root, a placeholder.
+fn order(world: &bevy::World, deterministic: bool) -> (Vec<bevy::Entity>, Vec<u32>) {
+    let is_root = |entity: &EntityRef| !entity.contains::<bevy::Parent>();
+    let root_key = |entity: EntityRef<'_>| {
+        let name = entity
+            .get::<bevy::Name>()
+            .map(|name| name.as_str().to_string());
+        (name, entity.id())
+    };
+    let mut roots: Vec<_> = world
+        .iter_entities()
+        .filter(is_root)
+        .map(root_key)
+        .collect();
+    if deterministic {
+        roots.sort_by(|(l_name, l), (r_name, r)| (l_name, l.index()).cmp(&(r_name, r.index())));
+    }
+    let mut ids = vec![bevy::Entity::PLACEHOLDER];
+    let mut children = vec![0];
+    for (_, root) in roots {
+        push_tree(world, root, &mut ids, &mut children);
+    }
+    children[0] = (ids.len() - 1) as u32;
+    (ids, children)
+}
+/// Push `root` and its descendants to `ids` in depth-first order, and their
+/// descendant count to `children`.
+///
+/// This uses an explicit stack rather than recursion, so that very deep
+/// hierarchies do not overflow the call stack.
+fn push_tree(
+    world: &bevy::World,
+    root: bevy::Entity,
+    ids: &mut Vec<bevy::Entity>,
+    children: &mut Vec<u32>,
+) {
+    let children_of =
+        |id: bevy::Entity| world.get::<bevy::Children>(id).map_or(&[][..], |c| &c[..]);
+
+    // The entities whose children are being pushed, with their index in
+    // `ids`, and the children yet to push.
+    let mut ancestors = vec![(ids.len(), children_of(root).iter())];
+    ids.push(root);
+    children.push(0);
+    while let Some((_, entity_children)) = ancestors.last_mut() {
+        if let Some(&child) = entity_children.next() {
+            ancestors.push((ids.len(), children_of(child).iter()));
+            ids.push(child);
+            children.push(0);
+            continue;
+        }
+        // unwrap: we just checked `ancestors.last_mut()`
+        let (index, _) = ancestors.pop().unwrap();
+        children[index] = (ids.len() - index - 1) as u32;
+    }
+}
+
+/// Write a `layout`-sized value, initialized by `init`, at the next position
+/// aligned for `layout`.
+///
+/// `init` is called with the position of the value in the archive and zeroed
+/// bytes to initialize.
+fn write_with(
+    serializer: &mut Serializer,
+    layout: Layout,
+    init: impl FnOnce(usize, &mut [u8]),
+) -> Result<(), Error> {
+    let pos = serializer.align(layout.align())?;
+    let mut bytes = AlignedVec::with_capacity(layout.size());
+    bytes.resize(layout.size(), 0);
+    init(pos, &mut bytes);
+    serializer.write(&bytes)
+}
+
+/// Whether `len` values of `layout` starting at `ptr` are within `bytes`,
+/// and `ptr` is aligned for `layout`.
+pub(crate) fn in_bytes(bytes: &[u8], ptr: *const u8, layout: Layout, len: usize) -> bool {
+    let start = (ptr as usize).wrapping_sub(bytes.as_ptr() as usize);
+    let size = layout.size().checked_mul(len);
+    let end = size.and_then(|size| start.checked_add(size));
+    end.map_or(false, |end| end <= bytes.len()) && ptr as usize % layout.align() == 0
+}
+/// Whether `string`, which is within `bytes`, points to UTF-8 within `bytes`.
+pub(crate) fn str_in_bytes(bytes: &[u8], string: &ArchivedString) -> bool {
+    // SAFETY: `ArchivedString` is a `repr(transparent)` `ArchivedStringRepr`
+    let repr = unsafe { &*(string as *const ArchivedString).cast::<ArchivedStringRepr>() };
+    let layout = Layout::new::<u8>();
+    // `bytes` is only called once we know the string is within `bytes`.
+    in_bytes(bytes, repr.as_ptr(), layout, repr.len()) && std::str::from_utf8(repr.bytes()).is_ok()
+}
+
+/// Offsets in an `Entity` of an `ArchivedOption`'s tag and value.
+struct OptionField {
+    tag: usize,
+    value: usize,
+}
+struct SceneLayout {
+    entity: Layout,
+    children: usize,
+    inlines: Vec<OptionField>,
+    keys: Vec<OptionField>,
+    root: Layout,
+    entities: usize,
+    tables: Vec<usize>,
+    names: usize,
+    strings: usize,
+}
+
+/// The layout of a `repr(C)` struct with the given fields, and their offset.
+fn repr_c(fields: &[Layout]) -> (Layout, Vec<usize>) {
+    let mut layout = Layout::new::<()>();
+    let mut offsets = Vec::with_capacity(fields.len());
+    for field in fields {
+        // unwrap: only fails on overflow, archived types are much smaller
+        let (extended, offset) = layout.extend(*field).unwrap();
+        layout = extended;
+        offsets.push(offset);
+    }
+    (layout.pad_to_align(), offsets)
+}
+/// The layout of a `HCons` list with the given items, and their offset.
+fn hlist(items: impl DoubleEndedIterator<Item = Layout>) -> (Layout, Vec<usize>) {
+    let mut layout = Layout::new::<()>();
+    let mut offsets = Vec::new();
+    for item in items.rev() {
+        let (cons, cons_offsets) = repr_c(&[item, layout]);
+        offsets
+            .iter_mut()
+            .for_each(|offset| *offset += cons_offsets[1]);
+        offsets.insert(0, cons_offsets[0]);
+        layout = cons;
+    }
+    (layout, offsets)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::alloc::Layout;
+
+    use bevy::prelude::*;
+    use rkyv::Archived;
+
+    use super::{DynamicProxy, DynamicSchema};
+    use crate::header::{self, Header};
+    use crate::schema::Schema;
+    use crate::{entity, proxy::Id, Archive, Deserialize, Serialize};
+    use crate::{ArchivedFastScene, FastScene, SaveOptions};
+    use crate::{DedupTable, HCons, Inline, Table};
+
+    #[derive(Component, Debug, Clone, PartialEq, Archive, Serialize, Deserialize)]
+    struct Label(String);
+
+    #[derive(Component, Debug, Clone, PartialEq, Archive, Serialize, Deserialize)]
+    struct Team(u8);
+
+    #[derive(Component, Debug, Clone, PartialEq, Archive, Serialize, Deserialize)]
+    struct Health(u32);
+
+    #[derive(Component, Debug, Clone, PartialEq, Archive, Serialize, Deserialize)]
+    struct Tag;
+
+    type Tables = HCons<Table<Id<Label>>, HCons<DedupTable<Id<Team>>, ()>>;
+    type Inlines = HCons<Inline<Id<Health>>, HCons<Inline<Id<Tag>>, ()>>;
+    type Keys = <Tables as entity::Tables>::Keys;
+
+    const OPTIONS: SaveOptions = SaveOptions {
+        deterministic: true,
+        name_index: true,
+        parallel: false,
+        strict: false,
+        chunked: false,
+    };
+
+    fn schema() -> DynamicSchema {
+        [
+            DynamicProxy::inline::<Id<Health>>(),
+            DynamicProxy::table::<Id<Label>>(),
+            DynamicProxy::dedup_table::<Id<Team>>(),
+            DynamicProxy::inline::<Id<Tag>>(),
+        ]
+        .into_iter()
+        .collect()
+    }
+    fn scene() -> Scene {
+        let mut world = World::new();
+        let label = |label: &str| Label(label.to_string());
+        world
+            .spawn((Name::new("a"), label("a label"), Team(1), Health(10)))
+            .with_children(|parent| {
+                parent
+                    .spawn((Name::new("b"), Team(1), Tag))
+                    .with_children(|parent| {
+                        parent.spawn((Name::new("c"), Health(3), label("c")));
+                    });
+                parent.spawn((Name::new("d"), Team(2)));
+            });
+        world.spawn((Name::new("e"), Tag, Team(2)));
+        Scene::new(world)
+    }
+    /// Description of all named entities in `world`, sorted.
+    fn entities(world: &mut World) -> Vec<String> {
+        type Components = (
+            &'static Name,
+            Option<&'static Parent>,
+            Option<&'static Label>,
+            Option<&'static Team>,
+            Option<&'static Health>,
+            Option<&'static Tag>,
+        );
+        let mut query = world.query::<Components>();
+        let mut entities: Vec<_> = query
+            .iter(world)
+            .map(|(name, parent, label, team, health, tag)| {
+                let parent = parent.and_then(|p| world.get::<Name>(p.get()));
+                format!("{name} {parent:?} {label:?} {team:?} {health:?} {tag:?}")
+            })
+            .collect();
+        entities.sort();
+        entities
+    }
+    fn static_save(scene: &mut Scene) -> Vec<u8> {
+        let fast_scene = FastScene::<Tables, Inlines>::from_bevy_filtered::<()>(scene, OPTIONS);
+        let mut bytes = Header::new(&Schema::of::<Tables, Inlines>())
+            .to_bytes()
+            .to_vec();
+        bytes.extend_from_slice(&rkyv::to_bytes::<_, 1024>(&fast_scene).unwrap());
+        bytes
+    }
+    fn static_load(bytes: &[u8]) -> Scene {
+        let (header, payload) = Header::read(bytes).unwrap();
+        header.check(&Schema::of::<Tables, Inlines>()).unwrap();
+        let payload = header::aligned(payload);
+        let scene = unsafe { rkyv::archived_root::<FastScene<Tables, Inlines>>(&payload) };
+        scene.to_bevy()
+    }
+
+    #[test]
+    fn same_layout() {
+        let layout = schema().layout();
+        let entity = Layout::new::<Archived<entity::Entity<Keys, Inlines>>>();
+        assert_eq!(layout.entity, entity);
+        assert_eq!(
+            layout.root,
+            Layout::new::<ArchivedFastScene<Tables, Inlines>>()
+        );
+    }
+    #[test]
+    fn same_bytes() {
+        let static_bytes = static_save(&mut scene());
+        let dynamic_bytes = schema().save(&scene(), OPTIONS).unwrap();
+        assert_eq!(static_bytes, dynamic_bytes);
+    }
+    #[test]
+    fn read_static_files() {
+        let mut scene = scene();
+        let bytes = static_save(&mut scene);
+        let mut loaded = schema().load(&bytes).unwrap();
+        assert_eq!(entities(&mut loaded.world), entities(&mut scene.world));
+
+        let mut paths = loaded.world.query::<&crate::EntityPaths>();
+        let paths = paths.single(&loaded.world);
+        let c = paths.get("a/b/c").unwrap();
+        assert_eq!(loaded.world.get::<Health>(c), Some(&Health(3)));
+    }
+    #[test]
+    fn corrupted_files() {
+        let schema = schema();
+        let layout = schema.layout();
+        let bytes = schema.save(&scene(), OPTIONS).unwrap();
+        let root = bytes.len() - layout.root.size();
+        assert!(schema.load(&bytes).is_ok());
+
+        // Entities and tables past the end of the file.
+        let vec_size = std::mem::size_of::<rkyv::vec::ArchivedVec<()>>();
+        for offset in [layout.entities].iter().chain(&layout.tables) {
+            let mut corrupted = bytes.clone();
+            corrupted[root + offset..][..vec_size].fill(0x7f);
+            assert!(schema.load(&corrupted).is_err());
+        }
+        // Out of range keys, invalid tags and too many descendants.
+        let payload = header::aligned(&bytes[header::LEN..]);
+        let scene = unsafe { rkyv::archived_root::<FastScene<Tables, Inlines>>(&payload) };
+        let entities = scene.entities.as_ptr() as usize - payload.as_ptr() as usize;
+        let first = header::LEN + entities + layout.entity.size();
+        let fields = [
+            (layout.keys[0].value, 100u32),
+            (layout.inlines[0].tag, 2),
+            (layout.children, 100),
+        ];
+        for (offset, value) in fields {
+            let mut corrupted = bytes.clone();
+            corrupted[first + offset] = value as u8;
+            assert!(schema.load(&corrupted).is_err());
+        }
+        // Entity paths past the end of the file.
+        assert!(!scene.names.is_empty());
+        let mut corrupted = bytes.clone();
+        corrupted[root + layout.names..][..vec_size].fill(0x7f);
+        assert!(schema.load(&corrupted).is_err());
+    }
+    #[test]
+    fn static_reads_dynamic_files() {
+        let mut scene = scene();
+        let bytes = schema().save(&scene, OPTIONS).unwrap();
+        let mut loaded = static_load(&bytes);
+        assert_eq!(entities(&mut loaded.world), entities(&mut scene.world));
+    }
+}
@@ -3,9 +3,14 @@ use rkyv::{Archive, Deserialize, Serialize};
 
 pub mod storage;
 
+pub(crate) use storage::auto;
+
 pub use storage::{
-    inline::{InlineStorage, Inlines},
-    ref_table::{KeyStorage, Keys, TableStorage, Tables},
+    inline::{FindInline, InlineStorage, Inlines},
+    ref_table::{
+        DecodedTables, FindTable, KeyStorage, Keys, ParInsert, ParTables, TableKind, TableStorage,
+        Tables,
+    },
 };
 
 pub trait ArchiveProxy: Archive {
@@ -16,9 +21,82 @@ pub trait ArchiveProxy: Archive {
 
     fn to_target(archive: &Self::Archived) -> Self::Target;
     fn from_target(target: &Self::Target) -> Self;
+
+    /// Like [`ArchiveProxy::to_target`], with access to the world the scene is
+    /// spawned in, when spawning directly in a `World`.
+    ///
+    /// Override this when the component depends on resources, such as
+    /// `Handle`s that need the `AssetServer`, see [`crate::proxy::AssetPath`].
+    fn to_target_in(archive: &Self::Archived, _world: Option<&bevy::World>) -> Self::Target {
+        Self::to_target(archive)
+    }
+}
+
+/// An [`ArchiveProxy`] that can be stored in `Columnar` storage.
+///
+/// `Columnar` stores each field of the proxy in a separate array, rather than
+/// an array of whole proxies like `Table`. Arrays of a single field compress
+/// better. All the values of the table are converted at once, each time the
+/// scene is spawned, with [`ColumnarProxy::to_targets`]. The targets are then
+/// cloned into their entity, so `Target` must be `Clone`.
+///
+/// ```text
+/// #[derive(Default, Archive, Deserialize, Serialize)]
+/// #[archive_attr(repr(C))]
+/// struct HealthColumns { current: Vec<u32>, max: Vec<u32> }
+///
+/// impl ColumnarProxy for HealthProxy {
+///     type Columns = HealthColumns;
+///     fn push(self, columns: &mut HealthColumns) {
+///         columns.current.push(self.current);
+///         columns.max.push(self.max);
+///     }
+///     fn len(columns: &ArchivedHealthColumns) -> usize {
+///         columns.current.len().min(columns.max.len())
+///     }
+///     fn to_target_at(columns: &ArchivedHealthColumns, index: usize, _: Option<&World>) -> Health {
+///         Health { current: columns.current[index], max: columns.max[index] }
+///     }
+/// }
+/// ```
+pub trait ColumnarProxy: ArchiveProxy {
+    /// One `Vec` per field of the proxy.
+    type Columns: Archive + Default;
+
+    /// Push each field of `self` at the end of its column.
+    fn push(self, columns: &mut Self::Columns);
+    /// How many proxies `columns` holds, the length of its shortest column.
+    ///
+    /// Columns are read from the file, they could be shorter than the table
+    /// length in a corrupted file. Indices past this length are not read.
+    fn len(columns: &rkyv::Archived<Self::Columns>) -> usize;
+    /// The target of the `index`th proxy pushed to `columns`.
+    ///
+    /// `index` is less than [`ColumnarProxy::len`]. `world` is the same as in
+    /// [`ArchiveProxy::to_target_in`].
+    fn to_target_at(
+        columns: &rkyv::Archived<Self::Columns>,
+        index: usize,
+        world: Option<&bevy::World>,
+    ) -> Self::Target;
+    /// Push the targets of the first `len` proxies pushed to `columns` to
+    /// `out`, in order.
+    ///
+    /// `len` is at most [`ColumnarProxy::len`]. The default calls
+    /// [`ColumnarProxy::to_target_at`] for each index, override it to convert
+    /// whole columns at once.
+    fn to_targets(
+        columns: &rkyv::Archived<Self::Columns>,
+        len: usize,
+        world: Option<&bevy::World>,
+        out: &mut Vec<Self::Target>,
+    ) {
+        out.extend((0..len).map(|index| Self::to_target_at(columns, index, world)));
+    }
 }
 
 #[derive(Clone, Copy, Archive, Deserialize, Serialize)]
+#[archive_attr(repr(C))]
 pub struct Entity<Keys, Inlines> {
     // How many entities following this one are its children.
     pub children: u32,
@@ -8,14 +8,22 @@
 //!    The index is stored in the `Entity` as a `Option<NonZeroU32>`.
 //!    Use this if the archived format occupies a lot of memory (something like
 //!    several thousand bits or more), or if the same value is shared by many
-//!    different entites.
+//!    different entites. `Columnar` is a variant that stores one array per
+//!    field of the value, see [`crate::ColumnarProxy`]. `Auto` is a variant
+//!    that stores values inline or in a table, whichever is the smallest
+//!    for the saved scene, see [`auto`].
 //! 3. `extra::Extra`: In the `extra` table of the entity-specific extras table.
 //!    The `extra` table is a magic thing that is not implemented yet, so don't use it.
 
-use bevy::{ecs::system::EntityCommands, ecs::world::EntityMut, prelude::Bundle};
+use std::marker::PhantomData;
+
+use bevy::ecs::{system::EntityCommands, world::EntityMut};
+use bevy::prelude::{Bundle, Component, World};
 
 use super::ArchiveProxy;
+use crate::hooks::SpawnHooks;
 
+pub mod auto;
 pub mod inline;
 pub mod ref_table;
 
@@ -26,18 +34,51 @@ pub mod ref_table;
 /// Enables spawning entites from tables with `EntityCommands` and `EntityMut`
 pub trait EntitySpawner {
     fn insert<B: Bundle>(&mut self, bundle: B);
+    fn world(&self) -> Option<&World>;
+    /// Run the [`SpawnHooks`] of `C`, called right after inserting `C`.
+    fn run_hooks<C: Component>(&mut self);
 }
 impl EntitySpawner for &'_ mut EntityCommands<'_, '_, '_> {
     fn insert<B: Bundle>(&mut self, bundle: B) {
         EntityCommands::insert(self, bundle);
     }
+    fn world(&self) -> Option<&World> {
+        None
+    }
+    fn run_hooks<C: Component>(&mut self) {
+        self.add(|entity, world: &mut World| {
+            if let Some(mut entity) = world.get_entity_mut(entity) {
+                SpawnHooks::run_in_world::<C>(&mut entity);
+            }
+        });
+    }
 }
 impl EntitySpawner for &'_ mut EntityMut<'_> {
     fn insert<B: Bundle>(&mut self, bundle: B) {
         EntityMut::insert(self, bundle);
     }
+    fn world(&self) -> Option<&World> {
+        Some(EntityMut::world(self))
+    }
+    fn run_hooks<C: Component>(&mut self) {
+        SpawnHooks::run_in_world::<C>(self);
+    }
 }
 
+// -------------------------------------
+//               LOOKUP
+// -------------------------------------
+
+/// Type-level index of a proxy that is the head of a hlist.
+pub struct Here;
+
+/// Type-level index of a proxy that is in the tail of a hlist.
+///
+/// This, and [`Here`], exist so that finding a proxy in a hlist doesn't
+/// require overlapping trait implementations. You should never have to name
+/// them, let rustc infer them with `_`.
+pub struct There<I>(PhantomData<I>);
+
 // In the `extra` table of the entity-specific extras table.
 // The `extra` table is a magic thing that is not implemented yet, so don't use it.
 // It's an "heterogenous list of erased values"
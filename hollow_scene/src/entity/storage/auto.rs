@@ -0,0 +1,298 @@
+//! `Auto` storage, that picks `Inline`, `Table` or `DedupTable` for each file.
+//!
+//! While saving, `Auto` keeps the proxy of each entity of the entity slice.
+//! Once all entities are inserted, [`Tables::encode`] measures how many
+//! entities have the component and how many distinct archived values there
+//! are, like [`crate::advise::Advice`] does, then stores the values in the
+//! smallest storage.
+//!
+//! Unlike `Inline` and `Table`, the values are never in the entity array,
+//! entities only have a zero-sized [`AutoKey`], and the values are looked up
+//! by the entity's index in the entity slice. The picked storage is recorded
+//! both in the archived `Auto` and in [`crate::header::Header::auto`].
+
+use core::fmt;
+use std::{collections::HashMap, marker::PhantomData, mem, num::NonZeroU16};
+
+use bevy::prelude::{Entity, World};
+use rkyv::{Archive, Archived, Deserialize, Serialize};
+
+use super::ref_table::{ComponentsOf, Decoded, Fill, FindTable, Keys, ParTables, Tables};
+use super::{ArchiveProxy, EntitySpawner, There};
+use crate::advise::Measure;
+use crate::schema::{ComponentInfo, Storage};
+use crate::{dynamic::Serializer, header};
+use crate::{ArchivedAuto, ArchivedHCons, Auto, HCons};
+
+/// The storages `Auto` can pick, indexed by their code in
+/// [`crate::header::Header::auto`].
+const STORAGES: [Storage; 3] = [Storage::Inline, Storage::Table, Storage::DedupTable];
+
+/// The code of `storage` in [`crate::header::Header::auto`].
+///
+/// # Panics
+///
+/// If `storage` is not one of the storages `Auto` picks.
+pub(crate) fn code(storage: Storage) -> u8 {
+    let code = STORAGES.iter().position(|s| *s == storage);
+    let code = code.unwrap_or_else(|| panic!("Auto storage can't be {}", storage.name()));
+    code as u8
+}
+/// The storage of `code` in [`crate::header::Header::auto`].
+pub(crate) fn storage(code: u8) -> Result<Storage, header::Error> {
+    STORAGES
+        .get(usize::from(code))
+        .copied()
+        .ok_or(header::Error::UnknownAutoStorage(code))
+}
+
+impl<C: ArchiveProxy + Serialize<Serializer>> Auto<C> {
+    fn new() -> Self {
+        Auto {
+            // The synthetic scene root, first entity of the entity slice.
+            pending: vec![None],
+            storage: code(Storage::Inline),
+            inline: Vec::new(),
+            keys: Vec::new(),
+            values: Vec::new(),
+        }
+    }
+    fn len(&self) -> usize {
+        let inline = self.pending.iter().chain(&self.inline);
+        inline.filter(|value| value.is_some()).count() + self.values.len()
+    }
+    /// Move the pending values to the smallest storage.
+    fn choose(&mut self) {
+        // unwrap: serializing to an `AlignedVec` doesn't fail
+        let to_bytes = |value: &C| rkyv::to_bytes::<_, 1024>(value).unwrap();
+        let archived: Vec<_> = self
+            .pending
+            .iter()
+            .map(|v| v.as_ref().map(to_bytes))
+            .collect();
+
+        let mut measure = Measure::default();
+        archived
+            .iter()
+            .flatten()
+            .for_each(|bytes| measure.add(bytes));
+        let archived_size = mem::size_of::<C::Archived>();
+        let storage = measure
+            .costs::<C>(archived_size, self.pending.len())
+            .cheapest();
+
+        let pending = mem::take(&mut self.pending);
+        let key = |len: usize| NonZeroU16::new(u16::try_from(len).unwrap()).unwrap();
+        match storage {
+            Storage::Table => {
+                for value in pending {
+                    let index = value.map(|value| {
+                        self.values.push(value);
+                        key(self.values.len())
+                    });
+                    self.keys.push(index);
+                }
+            }
+            Storage::DedupTable => {
+                let mut indices = HashMap::new();
+                for (value, bytes) in pending.into_iter().zip(archived) {
+                    let index = value.zip(bytes).map(|(value, bytes)| {
+                        *indices.entry(bytes.to_vec()).or_insert_with(|| {
+                            self.values.push(value);
+                            key(self.values.len())
+                        })
+                    });
+                    self.keys.push(index);
+                }
+            }
+            _ => self.inline = pending,
+        }
+        self.storage = code(storage);
+    }
+}
+impl<C: ArchiveProxy> ArchivedAuto<C> {
+    /// The storage picked when saving.
+    pub fn storage(&self) -> Result<Storage, header::Error> {
+        storage(self.storage)
+    }
+    /// The value of the `index`th entity of the entity slice, if it has one.
+    fn get(&self, index: usize) -> Option<&C::Archived> {
+        if self.storage == code(Storage::Inline) {
+            return self.inline.get(index)?.as_ref();
+        }
+        let key = self.keys.get(index)?.as_ref()?;
+        self.values.get(usize::from(key.get() - 1))
+    }
+    fn len(&self) -> usize {
+        let inline = self.inline.iter().filter(|value| value.is_some()).count();
+        inline + self.values.len()
+    }
+    /// The size of the values and keys, not counting out-of-line data.
+    fn size(&self) -> usize {
+        let inline = self.inline.len() * mem::size_of::<Archived<Option<C>>>();
+        let keys = self.keys.len() * mem::size_of::<Archived<Option<NonZeroU16>>>();
+        inline + keys + self.values.len() * mem::size_of::<C::Archived>()
+    }
+}
+
+impl<C, Tk: Keys, Tt: Tables<Keys = Tk>> Tables for HCons<Auto<C>, Tt>
+where
+    C: ArchiveProxy + Serialize<Serializer>,
+{
+    type Keys = HCons<AutoKey<C>, Tk>;
+
+    #[inline]
+    fn insert_archived_keys<S: EntitySpawner>(
+        ArchivedHCons(head, tail): &ArchivedHCons<Auto<C>, Tt>,
+        ArchivedHCons(_, key_tail): &ArchivedHCons<AutoKey<C>, Tk>,
+        decoded: &[Decoded],
+        index: usize,
+        mut cmds: S,
+    ) {
+        if let Some(component) = head.get(index) {
+            let target = C::to_target_in(component, cmds.world());
+            cmds.insert(target);
+            cmds.run_hooks::<C::Target>();
+        }
+        Tt::insert_archived_keys(tail, key_tail, &decoded[1..], index, cmds);
+    }
+    #[inline]
+    fn new() -> Self {
+        HCons(Auto::new(), Tt::new())
+    }
+    #[inline]
+    fn insert_entity_components(
+        &mut self,
+        (head, tail): ComponentsOf<HCons<AutoKey<C>, Tk>>,
+    ) -> HCons<AutoKey<C>, Tk> {
+        self.0.pending.push(head.map(C::from_target));
+        let tail = self.1.insert_entity_components(tail);
+        HCons(AutoKey(PhantomData), tail)
+    }
+    fn encode(&mut self) {
+        self.0.choose();
+        self.1.encode();
+    }
+    fn decode(
+        ArchivedHCons(_, tail): &Self::Archived,
+        world: Option<&World>,
+        decoded: &mut Vec<Decoded>,
+    ) {
+        decoded.push(Decoded::InPlace);
+        Tt::decode(tail, world, decoded);
+    }
+    fn component_count(&self, index: usize) -> usize {
+        (index == 0)
+            .then_some(self.0.len())
+            .unwrap_or_else(|| self.1.component_count(index - 1))
+    }
+    fn component_name(&self, index: usize) -> &'static str {
+        (index == 0)
+            .then_some(std::any::type_name::<C::Target>())
+            .unwrap_or_else(|| self.1.component_name(index - 1))
+    }
+    fn describe(components: &mut Vec<ComponentInfo>) {
+        components.push(ComponentInfo::new::<C>(Storage::Auto));
+        Tt::describe(components);
+    }
+    fn archived_lens(ArchivedHCons(head, tail): &Self::Archived, lens: &mut [usize]) {
+        lens[0] = head.len();
+        Tt::archived_lens(tail, &mut lens[1..]);
+    }
+    fn archived_sizes(ArchivedHCons(head, tail): &Self::Archived, sizes: &mut [usize]) {
+        sizes[0] = head.size();
+        Tt::archived_sizes(tail, &mut sizes[1..]);
+    }
+    fn count_archived(
+        ArchivedHCons(head, tail): &Self::Archived,
+        index: usize,
+        counts: &mut [usize],
+    ) {
+        if head.get(index).is_some() {
+            counts[0] += 1;
+        }
+        Tt::count_archived(tail, index, &mut counts[1..]);
+    }
+    fn auto_storages(&self, storages: &mut Vec<Storage>) {
+        // unwrap: the storage is always set with `code`
+        storages.push(storage(self.0.storage).unwrap());
+        self.1.auto_storages(storages);
+    }
+    fn archived_auto_storages(
+        ArchivedHCons(head, tail): &Self::Archived,
+        storages: &mut Vec<Storage>,
+    ) -> Result<(), header::Error> {
+        storages.push(head.storage()?);
+        Tt::archived_auto_storages(tail, storages)
+    }
+    const COMPONENT_COUNT: usize = 1 + Tt::COMPONENT_COUNT;
+}
+impl<C, Tt: ParTables> ParTables for HCons<Auto<C>, Tt>
+where
+    C: ArchiveProxy + Serialize<Serializer> + Send,
+{
+    fn fills<'a>(
+        &'a mut self,
+        world: &'a World,
+        ids: &'a [Entity],
+        columns: &'a mut [Vec<Option<usize>>],
+        fills: &mut Vec<Fill<'a>>,
+    ) {
+        let HCons(auto, tail) = self;
+        // Values are not in the keys, so the column stays empty.
+        // unwrap: there is one column per table
+        let (_, columns) = columns.split_first_mut().unwrap();
+        fills.push(Box::new(move || {
+            auto.pending.clear();
+            let values = ids.iter().map(|id| world.get::<C::Target>(*id));
+            auto.pending
+                .extend(values.map(|value| value.map(C::from_target)));
+        }));
+        tail.fills(world, ids, columns, fills);
+    }
+}
+impl<P, C, Tt, Idx> FindTable<P, There<Idx>> for HCons<Auto<C>, Tt>
+where
+    P: ArchiveProxy,
+    C: ArchiveProxy + Serialize<Serializer>,
+    Tt: FindTable<P, Idx>,
+{
+    #[inline]
+    fn find<'a>(
+        ArchivedHCons(_, tail): &'a ArchivedHCons<Auto<C>, Tt>,
+        ArchivedHCons(_, key_tail): &ArchivedHCons<AutoKey<C>, Tt::Keys>,
+    ) -> Option<&'a P::Archived> {
+        Tt::find(tail, key_tail)
+    }
+}
+
+/// The key of an `Auto` proxy, empty since values are looked up by the
+/// entity's index in the entity slice.
+#[derive(Clone, Copy, Archive, Deserialize, Serialize)]
+#[archive_attr(repr(C))]
+pub struct AutoKey<C: ArchiveProxy>(PhantomData<fn(C)>);
+
+impl<C: ArchiveProxy> fmt::Debug for AutoKey<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "A__")
+    }
+}
+impl<C: ArchiveProxy, Tl: Keys> Keys for HCons<AutoKey<C>, Tl> {
+    type Query = (Option<&'static C::Target>, Tl::Query);
+
+    #[inline]
+    fn empty() -> Self {
+        HCons(AutoKey(PhantomData), Tl::empty())
+    }
+    #[inline]
+    fn from_columns(columns: &[Vec<Option<usize>>], index: usize) -> Self {
+        HCons(AutoKey(PhantomData), Tl::from_columns(&columns[1..], index))
+    }
+    fn occupancy(&self) -> String {
+        format!("{:?}{}", &self.0, self.1.occupancy())
+    }
+    /// `Auto` values are counted with [`Tables::count_archived`].
+    fn count_archived(ArchivedHCons(_, tail): &Self::Archived, counts: &mut [usize]) {
+        Tl::count_archived(tail, &mut counts[1..]);
+    }
+}
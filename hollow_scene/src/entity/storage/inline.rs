@@ -1,8 +1,9 @@
 use bevy::ecs::query::{ROQueryItem, WorldQuery};
 use rkyv::{Archive, Deserialize, Serialize};
 
-use super::EntitySpawner;
-use crate::{entity::ArchiveProxy, Inline};
+use super::{EntitySpawner, Here, There};
+use crate::schema::{ComponentInfo, Storage};
+use crate::{entity::ArchiveProxy, ArchivedHCons, HCons, Inline};
 
 pub type ComponentsOf<'w, I> = ROQueryItem<'w, <I as Inlines>::Query>;
 
@@ -17,14 +18,31 @@ pub trait Inlines: Archive {
     fn insert_entity_components<S: EntitySpawner>(archive: &Self::Archived, cmds: &mut S);
     fn new() -> Self;
     fn occupancy(&self) -> String;
+
+    /// Push a description of each component in this list to `components`.
+    fn describe(components: &mut Vec<ComponentInfo>);
+    /// Increment `counts[i]` if the `i`th component exists in `archive`.
+    fn count_archived(archive: &Self::Archived, counts: &mut [usize]);
 }
 
 #[derive(Clone, Copy, Archive, Deserialize, Serialize)]
+#[archive_attr(repr(C))]
 pub struct InlineStorage<I>(I);
 impl<I: Inlines> ArchivedInlineStorage<I> {
     pub fn spawn(&self, mut cmds: impl EntitySpawner) {
         I::insert_entity_components(&self.0, &mut cmds);
     }
+    /// The archived value of proxy `P`, if this entity has it.
+    pub fn get<P: ArchiveProxy, Idx>(&self) -> Option<&P::Archived>
+    where
+        I: FindInline<P, Idx>,
+    {
+        I::find(&self.0)
+    }
+    /// Increment `counts[i]` if this entity has the `i`th inline component.
+    pub fn count(&self, counts: &mut [usize]) {
+        I::count_archived(&self.0, counts);
+    }
 }
 impl<I: Inlines> InlineStorage<I> {
     pub fn new() -> Self {
@@ -52,28 +70,67 @@ impl Inlines for () {
     fn occupancy(&self) -> String {
         String::new()
     }
+    fn describe(_: &mut Vec<ComponentInfo>) {}
+    fn count_archived((): &(), _: &mut [usize]) {}
 }
-impl<H: ArchiveProxy, T: Inlines> Inlines for (Inline<H>, T) {
+impl<H: ArchiveProxy, T: Inlines> Inlines for HCons<Inline<H>, T> {
     type Query = (Option<&'static H::Target>, T::Query);
 
     #[inline]
     fn from_query_items((head, tail): (Option<&H::Target>, ComponentsOf<T>)) -> Self {
         let head = Inline(head.map(H::from_target));
-        (head, T::from_query_items(tail))
+        HCons(head, T::from_query_items(tail))
     }
     #[inline]
-    fn insert_entity_components<S: EntitySpawner>((head, tail): &Self::Archived, cmds: &mut S) {
+    fn insert_entity_components<S: EntitySpawner>(
+        ArchivedHCons(head, tail): &Self::Archived,
+        cmds: &mut S,
+    ) {
         if let Some(value) = head.0.as_ref() {
-            cmds.insert(H::to_target(value));
+            let target = H::to_target_in(value, cmds.world());
+            cmds.insert(target);
+            cmds.run_hooks::<H::Target>();
         }
         T::insert_entity_components(tail, cmds);
     }
     #[inline]
     fn new() -> Self {
-        (Inline(None), T::new())
+        HCons(Inline(None), T::new())
     }
     fn occupancy(&self) -> String {
         let head = if self.0 .0.is_some() { '#' } else { '_' };
         format!("{head}{}", self.1.occupancy())
     }
+    fn describe(components: &mut Vec<ComponentInfo>) {
+        components.push(ComponentInfo::new::<H>(Storage::Inline));
+        T::describe(components);
+    }
+    fn count_archived(ArchivedHCons(head, tail): &Self::Archived, counts: &mut [usize]) {
+        if head.0.is_some() {
+            counts[0] += 1;
+        }
+        T::count_archived(tail, &mut counts[1..]);
+    }
+}
+
+/// [`Inlines`] that contain the `P` proxy at `Idx`.
+pub trait FindInline<P: ArchiveProxy, Idx>: Inlines {
+    fn find(archive: &Self::Archived) -> Option<&P::Archived>;
+}
+impl<P: ArchiveProxy, T: Inlines> FindInline<P, Here> for HCons<Inline<P>, T> {
+    #[inline]
+    fn find(ArchivedHCons(head, _): &Self::Archived) -> Option<&P::Archived> {
+        head.0.as_ref()
+    }
+}
+impl<P, H, T, Idx> FindInline<P, There<Idx>> for HCons<Inline<H>, T>
+where
+    P: ArchiveProxy,
+    H: ArchiveProxy,
+    T: FindInline<P, Idx>,
+{
+    #[inline]
+    fn find(ArchivedHCons(_, tail): &Self::Archived) -> Option<&P::Archived> {
+        T::find(tail)
+    }
 }
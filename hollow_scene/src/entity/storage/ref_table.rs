@@ -1,11 +1,17 @@
 use core::fmt;
-use std::{marker::PhantomData, num::NonZeroU16};
+use std::{any::Any, marker::PhantomData, num::NonZeroU16, sync::Arc};
 
 use bevy::ecs::query::{ROQueryItem, WorldQuery};
-use rkyv::{Archive, Archived, Deserialize, Serialize};
+use bevy::prelude::{Entity, World};
+use bevy::tasks::ComputeTaskPool;
+use rkyv::{AlignedVec, Archive, Archived, Deserialize, Serialize};
 
-use super::{ArchiveProxy, EntitySpawner};
-use crate::{ArchivedDedupTable, ArchivedTable, DedupTable, Table};
+use super::{ArchiveProxy, ColumnarProxy, EntitySpawner, Here, There};
+use crate::codec::{Codec, Words};
+use crate::schema::{ComponentInfo, Storage};
+use crate::{dynamic::Serializer, header, strings::Interner};
+use crate::{ArchivedColumnar, ArchivedEncoded, ArchivedHCons, ArchivedTable};
+use crate::{Columnar, DedupTable, Encoded, HCons, Table};
 
 // -------------------------------------
 //               TABLES
@@ -22,17 +28,70 @@ pub trait Tables: Archive {
     type Keys: Keys;
 
     /// Deserialize a collection of components to directly insert into the ECS.
+    ///
+    /// `decoded` is the result of [`Tables::decode`] on `archive`, `index`
+    /// is the index of the entity in the entity slice.
     fn insert_archived_keys<S: EntitySpawner>(
         archive: &Self::Archived,
         keys: &Archived<Self::Keys>,
+        decoded: &[Decoded],
+        index: usize,
         cmds: S,
     );
     fn new() -> Self;
     fn insert_entity_components(&mut self, components: ComponentsOf<Self::Keys>) -> Self::Keys;
+    /// Encode the tables with a [`Codec`] and pick the storage of `Auto`
+    /// tables, once all entities are inserted.
+    fn encode(&mut self);
+    /// Push to `decoded` the archived values of each table with a [`Codec`],
+    /// the targets of each `Columnar` table, and [`Decoded::InPlace`] for
+    /// other tables.
+    ///
+    /// `world` is the same as in [`ArchiveProxy::to_target_in`].
+    fn decode(archive: &Self::Archived, world: Option<&World>, decoded: &mut Vec<Decoded>);
 
     const COMPONENT_COUNT: usize;
     fn component_count(&self, index: usize) -> usize;
     fn component_name(&self, index: usize) -> &'static str;
+
+    /// Push a description of each component in this list to `components`.
+    fn describe(components: &mut Vec<ComponentInfo>);
+    /// Set `lens[i]` to the number of values in the `i`th table of `archive`.
+    fn archived_lens(archive: &Self::Archived, lens: &mut [usize]);
+    /// Set `sizes[i]` to the size in bytes of the values in the `i`th table
+    /// of `archive`, not counting out-of-line data.
+    fn archived_sizes(archive: &Self::Archived, sizes: &mut [usize]);
+    /// Increment `counts[i]` if the `i`th table is an `Auto` table with a
+    /// value for the `index`th entity of the entity slice. Other tables are
+    /// counted with [`Keys::count_archived`].
+    fn count_archived(archive: &Self::Archived, index: usize, counts: &mut [usize]);
+    /// Push the storage picked for each `Auto` table.
+    fn auto_storages(&self, storages: &mut Vec<Storage>);
+    /// Push the storage recorded in `archive` for each `Auto` table.
+    fn archived_auto_storages(
+        archive: &Self::Archived,
+        storages: &mut Vec<Storage>,
+    ) -> Result<(), header::Error>;
+}
+
+/// [`Tables`] that can be filled in parallel, with
+/// [`TableStorage::par_insert_values`].
+///
+/// This is the case when the proxies in `Table`, `DedupTable` and `Auto`
+/// storage, and the columns of `Columnar` proxies, are `Send`.
+pub trait ParTables: Tables {
+    /// Push to `fills` a [`Fill`] per table, which empties the table, then
+    /// stores the values of the `ids` entities of `world`.
+    ///
+    /// `columns[i][j]` is set to the index in the `i`th table of the value of
+    /// entity `ids[j]`, in the same way as [`Tables::insert_entity_components`].
+    fn fills<'a>(
+        &'a mut self,
+        world: &'a World,
+        ids: &'a [Entity],
+        columns: &'a mut [Vec<Option<usize>>],
+        fills: &mut Vec<Fill<'a>>,
+    );
 }
 
 impl<C: ArchiveProxy> Table<C> {
@@ -40,68 +99,140 @@ impl<C: ArchiveProxy> Table<C> {
         self.table.push(C::from_target(c));
         self.table.len()
     }
+    fn clear(&mut self) {
+        self.table.clear();
+    }
+}
+impl<C: ArchiveProxy + PartialEq<C::Target>> Table<C> {
+    /// Like [`Table::store`], but reuse the index of an equal value if any.
+    fn store_dedup(&mut self, c: &C::Target) -> usize {
+        let position = self.table.iter().position(|elem| elem == c);
+        position.map_or_else(|| self.store(c), |position| position + 1)
+    }
 }
 impl<C: ArchiveProxy> ArchivedTable<C> {
+    fn get(&self, key: &ArchivedKey<C>) -> Option<&C::Archived> {
+        let index = key.index.as_ref()?;
+        let index = usize::try_from(index.get() - 1).unwrap();
+        // SAFETY: by construction, all keys are compatible with tables of given type.
+        Some(unsafe { self.table.get_unchecked(index) })
+    }
     fn insert_at(&self, key: &ArchivedKey<C>, cmds: &mut impl EntitySpawner) {
-        if let Some(index) = key.index.as_ref() {
-            let index = usize::try_from(index.get() - 1).unwrap();
-            // SAFETY: by construction, all keys are compatible with tables of given type.
-            let component = unsafe { self.table.get_unchecked(index) };
-            cmds.insert(C::to_target(component));
+        if let Some(component) = self.get(key) {
+            let target = C::to_target_in(component, cmds.world());
+            cmds.insert(target);
+            cmds.run_hooks::<C::Target>();
+        }
+    }
+}
+impl<C: ColumnarProxy> Columnar<C> {
+    fn store(&mut self, c: &C::Target) -> usize {
+        C::from_target(c).push(&mut self.columns);
+        self.len += 1;
+        self.len as usize
+    }
+    fn clear(&mut self) {
+        self.columns = Default::default();
+        self.len = 0;
+    }
+}
+impl<C: ColumnarProxy> ArchivedColumnar<C>
+where
+    C::Target: Clone,
+{
+    /// The target of each value of this table.
+    ///
+    /// Stops at the shortest column if the columns hold less than `len`
+    /// values.
+    fn decode(&self, world: Option<&World>) -> Vec<C::Target> {
+        let len = (self.len as usize).min(C::len(&self.columns));
+        let mut targets = Vec::with_capacity(len);
+        C::to_targets(&self.columns, len, world, &mut targets);
+        targets.truncate(len);
+        targets
+    }
+    fn insert_at(&self, key: &ArchivedKey<C>, decoded: &Decoded, cmds: &mut impl EntitySpawner) {
+        let (Some(index), Decoded::Targets(targets)) = (key.index.as_ref(), decoded) else {
+            return;
+        };
+        // unwrap: `decoded` is the result of `ArchivedColumnar::decode`
+        let targets = targets.downcast_ref::<Vec<C::Target>>().unwrap();
+        if let Some(target) = targets.get(usize::from(index.get() - 1)) {
+            cmds.insert(target.clone());
+            cmds.run_hooks::<C::Target>();
         }
     }
 }
 impl Tables for () {
     type Keys = ();
     #[inline]
-    fn insert_archived_keys<S: EntitySpawner>(&(): &(), &(): &(), _: S) {}
+    fn insert_archived_keys<S: EntitySpawner>(&(): &(), &(): &(), _: &[Decoded], _: usize, _: S) {}
     #[inline]
     fn new() {}
     #[inline]
     fn insert_entity_components(&mut self, (): ()) {}
+    fn encode(&mut self) {}
+    fn decode((): &(), _: Option<&World>, _: &mut Vec<Decoded>) {}
     fn component_count(&self, _: usize) -> usize {
         panic!("Out of bound, terminal node isn't a component table")
     }
     fn component_name(&self, _: usize) -> &'static str {
         panic!("Out of bound, terminal node isn't a component table")
     }
+    fn describe(_: &mut Vec<ComponentInfo>) {}
+    fn archived_lens((): &(), _: &mut [usize]) {}
+    fn archived_sizes((): &(), _: &mut [usize]) {}
+    fn count_archived((): &(), _: usize, _: &mut [usize]) {}
+    fn auto_storages(&self, _: &mut Vec<Storage>) {}
+    fn archived_auto_storages((): &(), _: &mut Vec<Storage>) -> Result<(), header::Error> {
+        Ok(())
+    }
 
     const COMPONENT_COUNT: usize = 0;
 }
-impl<Hk, Tk: Keys, Tt: Tables<Keys = Tk>> Tables for (DedupTable<Hk>, Tt)
+impl ParTables for () {
+    fn fills(&mut self, _: &World, _: &[Entity], _: &mut [Vec<Option<usize>>], _: &mut Vec<Fill>) {}
+}
+impl<Hk, Tk: Keys, Tt: Tables<Keys = Tk>> Tables for HCons<DedupTable<Hk>, Tt>
 where
     Hk: ArchiveProxy + PartialEq<Hk::Target>,
 {
-    type Keys = (Key<Hk>, Tk);
+    type Keys = HCons<Key<Hk>, Tk>;
 
     #[inline]
     fn insert_archived_keys<S: EntitySpawner>(
-        (head, tail): &(ArchivedDedupTable<Hk>, Tt::Archived),
-        (key_head, key_tail): &(ArchivedKey<Hk>, Archived<Tk>),
+        ArchivedHCons(head, tail): &ArchivedHCons<DedupTable<Hk>, Tt>,
+        ArchivedHCons(key_head, key_tail): &ArchivedHCons<Key<Hk>, Tk>,
+        decoded: &[Decoded],
+        index: usize,
         mut cmds: S,
     ) {
         head.0.insert_at(key_head, &mut cmds);
-        Tt::insert_archived_keys(tail, key_tail, cmds);
+        Tt::insert_archived_keys(tail, key_tail, &decoded[1..], index, cmds);
     }
     #[inline]
     fn new() -> Self {
-        (DedupTable(Table { table: Vec::new() }), Tt::new())
+        HCons(DedupTable(Table { table: Vec::new() }), Tt::new())
     }
     #[inline]
     fn insert_entity_components(
         &mut self,
-        (head, tail): ComponentsOf<(Key<Hk>, Tk)>,
-    ) -> (Key<Hk>, Tk) {
-        let index = head.map(|head| {
-            let table = &mut self.0 .0;
-            table
-                .table
-                .iter()
-                .position(|elem| elem == head)
-                .unwrap_or_else(|| table.store(head))
-        });
+        (head, tail): ComponentsOf<HCons<Key<Hk>, Tk>>,
+    ) -> HCons<Key<Hk>, Tk> {
+        let index = head.map(|head| self.0 .0.store_dedup(head));
         let tail = self.1.insert_entity_components(tail);
-        (Key::from_index(index), tail)
+        HCons(Key::from_index(index), tail)
+    }
+    fn encode(&mut self) {
+        self.1.encode();
+    }
+    fn decode(
+        ArchivedHCons(_, tail): &Self::Archived,
+        world: Option<&World>,
+        decoded: &mut Vec<Decoded>,
+    ) {
+        decoded.push(Decoded::InPlace);
+        Tt::decode(tail, world, decoded);
     }
     fn component_count(&self, index: usize) -> usize {
         (index == 0)
@@ -113,32 +244,92 @@ where
             .then_some(std::any::type_name::<Hk::Target>())
             .unwrap_or_else(|| self.1.component_name(index - 1))
     }
+    fn describe(components: &mut Vec<ComponentInfo>) {
+        components.push(ComponentInfo::new::<Hk>(Storage::DedupTable));
+        Tt::describe(components);
+    }
+    fn archived_lens(ArchivedHCons(head, tail): &Self::Archived, lens: &mut [usize]) {
+        lens[0] = head.0.table.len();
+        Tt::archived_lens(tail, &mut lens[1..]);
+    }
+    fn archived_sizes(ArchivedHCons(head, tail): &Self::Archived, sizes: &mut [usize]) {
+        sizes[0] = head.0.table.len() * std::mem::size_of::<Hk::Archived>();
+        Tt::archived_sizes(tail, &mut sizes[1..]);
+    }
+    fn count_archived(ArchivedHCons(_, tail): &Self::Archived, index: usize, counts: &mut [usize]) {
+        Tt::count_archived(tail, index, &mut counts[1..]);
+    }
+    fn auto_storages(&self, storages: &mut Vec<Storage>) {
+        self.1.auto_storages(storages);
+    }
+    fn archived_auto_storages(
+        ArchivedHCons(_, tail): &Self::Archived,
+        storages: &mut Vec<Storage>,
+    ) -> Result<(), header::Error> {
+        Tt::archived_auto_storages(tail, storages)
+    }
     const COMPONENT_COUNT: usize = 1 + Tt::COMPONENT_COUNT;
 }
-impl<Hk: ArchiveProxy, Tk: Keys, Tt: Tables<Keys = Tk>> Tables for (Table<Hk>, Tt) {
-    type Keys = (Key<Hk>, Tk);
+impl<Hk, Tt: ParTables> ParTables for HCons<DedupTable<Hk>, Tt>
+where
+    Hk: ArchiveProxy + PartialEq<Hk::Target> + Send,
+{
+    fn fills<'a>(
+        &'a mut self,
+        world: &'a World,
+        ids: &'a [Entity],
+        columns: &'a mut [Vec<Option<usize>>],
+        fills: &mut Vec<Fill<'a>>,
+    ) {
+        let HCons(DedupTable(table), tail) = self;
+        // unwrap: there is one column per table
+        let (column, columns) = columns.split_first_mut().unwrap();
+        fills.push(Box::new(move || {
+            table.clear();
+            column.clear();
+            let values = ids.iter().map(|id| world.get::<Hk::Target>(*id));
+            column.extend(values.map(|value| value.map(|v| table.store_dedup(v))));
+        }));
+        tail.fills(world, ids, columns, fills);
+    }
+}
+impl<Hk: ArchiveProxy, Tk: Keys, Tt: Tables<Keys = Tk>> Tables for HCons<Table<Hk>, Tt> {
+    type Keys = HCons<Key<Hk>, Tk>;
 
     #[inline]
     fn insert_archived_keys<S: EntitySpawner>(
-        (head, tail): &(ArchivedTable<Hk>, Tt::Archived),
-        (key_head, key_tail): &(ArchivedKey<Hk>, Archived<Tk>),
+        ArchivedHCons(head, tail): &ArchivedHCons<Table<Hk>, Tt>,
+        ArchivedHCons(key_head, key_tail): &ArchivedHCons<Key<Hk>, Tk>,
+        decoded: &[Decoded],
+        index: usize,
         mut cmds: S,
     ) {
         head.insert_at(key_head, &mut cmds);
-        Tt::insert_archived_keys(tail, key_tail, cmds);
+        Tt::insert_archived_keys(tail, key_tail, &decoded[1..], index, cmds);
     }
     #[inline]
     fn new() -> Self {
-        (Table { table: Vec::new() }, Tt::new())
+        HCons(Table { table: Vec::new() }, Tt::new())
     }
     #[inline]
     fn insert_entity_components(
         &mut self,
-        (head, tail): ComponentsOf<(Key<Hk>, Tk)>,
-    ) -> (Key<Hk>, Tk) {
+        (head, tail): ComponentsOf<HCons<Key<Hk>, Tk>>,
+    ) -> HCons<Key<Hk>, Tk> {
         let index = head.map(|c| self.0.store(c));
         let tail = self.1.insert_entity_components(tail);
-        (Key::from_index(index), tail)
+        HCons(Key::from_index(index), tail)
+    }
+    fn encode(&mut self) {
+        self.1.encode();
+    }
+    fn decode(
+        ArchivedHCons(_, tail): &Self::Archived,
+        world: Option<&World>,
+        decoded: &mut Vec<Decoded>,
+    ) {
+        decoded.push(Decoded::InPlace);
+        Tt::decode(tail, world, decoded);
     }
     fn component_count(&self, index: usize) -> usize {
         (index == 0)
@@ -150,10 +341,382 @@ impl<Hk: ArchiveProxy, Tk: Keys, Tt: Tables<Keys = Tk>> Tables for (Table<Hk>, T
             .then_some(std::any::type_name::<Hk::Target>())
             .unwrap_or_else(|| self.1.component_name(index - 1))
     }
+    fn describe(components: &mut Vec<ComponentInfo>) {
+        components.push(ComponentInfo::new::<Hk>(Storage::Table));
+        Tt::describe(components);
+    }
+    fn archived_lens(ArchivedHCons(head, tail): &Self::Archived, lens: &mut [usize]) {
+        lens[0] = head.table.len();
+        Tt::archived_lens(tail, &mut lens[1..]);
+    }
+    fn archived_sizes(ArchivedHCons(head, tail): &Self::Archived, sizes: &mut [usize]) {
+        sizes[0] = head.table.len() * std::mem::size_of::<Hk::Archived>();
+        Tt::archived_sizes(tail, &mut sizes[1..]);
+    }
+    fn count_archived(ArchivedHCons(_, tail): &Self::Archived, index: usize, counts: &mut [usize]) {
+        Tt::count_archived(tail, index, &mut counts[1..]);
+    }
+    fn auto_storages(&self, storages: &mut Vec<Storage>) {
+        self.1.auto_storages(storages);
+    }
+    fn archived_auto_storages(
+        ArchivedHCons(_, tail): &Self::Archived,
+        storages: &mut Vec<Storage>,
+    ) -> Result<(), header::Error> {
+        Tt::archived_auto_storages(tail, storages)
+    }
     const COMPONENT_COUNT: usize = 1 + Tt::COMPONENT_COUNT;
 }
+impl<Hk: ArchiveProxy + Send, Tt: ParTables> ParTables for HCons<Table<Hk>, Tt> {
+    fn fills<'a>(
+        &'a mut self,
+        world: &'a World,
+        ids: &'a [Entity],
+        columns: &'a mut [Vec<Option<usize>>],
+        fills: &mut Vec<Fill<'a>>,
+    ) {
+        let HCons(table, tail) = self;
+        // unwrap: there is one column per table
+        let (column, columns) = columns.split_first_mut().unwrap();
+        fills.push(Box::new(move || {
+            table.clear();
+            column.clear();
+            let values = ids.iter().map(|id| world.get::<Hk::Target>(*id));
+            column.extend(values.map(|value| value.map(|v| table.store(v))));
+        }));
+        tail.fills(world, ids, columns, fills);
+    }
+}
+
+impl<Hk: ColumnarProxy, Tk: Keys, Tt: Tables<Keys = Tk>> Tables for HCons<Columnar<Hk>, Tt>
+where
+    Hk::Target: Clone,
+{
+    type Keys = HCons<Key<Hk>, Tk>;
+
+    #[inline]
+    fn insert_archived_keys<S: EntitySpawner>(
+        ArchivedHCons(head, tail): &ArchivedHCons<Columnar<Hk>, Tt>,
+        ArchivedHCons(key_head, key_tail): &ArchivedHCons<Key<Hk>, Tk>,
+        decoded: &[Decoded],
+        index: usize,
+        mut cmds: S,
+    ) {
+        head.insert_at(key_head, &decoded[0], &mut cmds);
+        Tt::insert_archived_keys(tail, key_tail, &decoded[1..], index, cmds);
+    }
+    #[inline]
+    fn new() -> Self {
+        HCons(Columnar { columns: Default::default(), len: 0 }, Tt::new())
+    }
+    #[inline]
+    fn insert_entity_components(
+        &mut self,
+        (head, tail): ComponentsOf<HCons<Key<Hk>, Tk>>,
+    ) -> HCons<Key<Hk>, Tk> {
+        let index = head.map(|c| self.0.store(c));
+        let tail = self.1.insert_entity_components(tail);
+        HCons(Key::from_index(index), tail)
+    }
+    fn encode(&mut self) {
+        self.1.encode();
+    }
+    fn decode(
+        ArchivedHCons(head, tail): &Self::Archived,
+        world: Option<&World>,
+        decoded: &mut Vec<Decoded>,
+    ) {
+        decoded.push(Decoded::Targets(Box::new(head.decode(world))));
+        Tt::decode(tail, world, decoded);
+    }
+    fn component_count(&self, index: usize) -> usize {
+        (index == 0)
+            .then_some(self.0.len as usize)
+            .unwrap_or_else(|| self.1.component_count(index - 1))
+    }
+    fn component_name(&self, index: usize) -> &'static str {
+        (index == 0)
+            .then_some(std::any::type_name::<Hk::Target>())
+            .unwrap_or_else(|| self.1.component_name(index - 1))
+    }
+    fn describe(components: &mut Vec<ComponentInfo>) {
+        components.push(ComponentInfo::new::<Hk>(Storage::Columnar));
+        Tt::describe(components);
+    }
+    fn archived_lens(ArchivedHCons(head, tail): &Self::Archived, lens: &mut [usize]) {
+        lens[0] = head.len as usize;
+        Tt::archived_lens(tail, &mut lens[1..]);
+    }
+    fn archived_sizes(ArchivedHCons(head, tail): &Self::Archived, sizes: &mut [usize]) {
+        // An upper bound, there is no padding between the fields in columns.
+        sizes[0] = head.len as usize * std::mem::size_of::<Hk::Archived>();
+        Tt::archived_sizes(tail, &mut sizes[1..]);
+    }
+    fn count_archived(ArchivedHCons(_, tail): &Self::Archived, index: usize, counts: &mut [usize]) {
+        Tt::count_archived(tail, index, &mut counts[1..]);
+    }
+    fn auto_storages(&self, storages: &mut Vec<Storage>) {
+        self.1.auto_storages(storages);
+    }
+    fn archived_auto_storages(
+        ArchivedHCons(_, tail): &Self::Archived,
+        storages: &mut Vec<Storage>,
+    ) -> Result<(), header::Error> {
+        Tt::archived_auto_storages(tail, storages)
+    }
+    const COMPONENT_COUNT: usize = 1 + Tt::COMPONENT_COUNT;
+}
+impl<Hk: ColumnarProxy, Tt: ParTables> ParTables for HCons<Columnar<Hk>, Tt>
+where
+    Hk::Target: Clone,
+    Hk::Columns: Send,
+{
+    fn fills<'a>(
+        &'a mut self,
+        world: &'a World,
+        ids: &'a [Entity],
+        columns: &'a mut [Vec<Option<usize>>],
+        fills: &mut Vec<Fill<'a>>,
+    ) {
+        let HCons(table, tail) = self;
+        // unwrap: there is one column per table
+        let (column, columns) = columns.split_first_mut().unwrap();
+        fills.push(Box::new(move || {
+            table.clear();
+            column.clear();
+            let values = ids.iter().map(|id| world.get::<Hk::Target>(*id));
+            column.extend(values.map(|value| value.map(|v| table.store(v))));
+        }));
+        tail.fills(world, ids, columns, fills);
+    }
+}
+
+/// A `Table` or `DedupTable`, to store with a [`Codec`] in [`Encoded`].
+pub trait TableKind {
+    type Proxy: ArchiveProxy;
+    const STORAGE: Storage;
+
+    fn new() -> Self;
+    /// Store `c`, returns its index + 1 in [`TableKind::values`].
+    fn store(&mut self, c: &<Self::Proxy as ArchiveProxy>::Target) -> usize;
+    fn values(&self) -> &[Self::Proxy];
+}
+impl<C: ArchiveProxy> TableKind for Table<C> {
+    type Proxy = C;
+    const STORAGE: Storage = Storage::Table;
+
+    fn new() -> Self {
+        Table { table: Vec::new() }
+    }
+    fn store(&mut self, c: &C::Target) -> usize {
+        Table::store(self, c)
+    }
+    fn values(&self) -> &[C] {
+        &self.table
+    }
+}
+impl<C: ArchiveProxy + PartialEq<C::Target>> TableKind for DedupTable<C> {
+    type Proxy = C;
+    const STORAGE: Storage = Storage::DedupTable;
+
+    fn new() -> Self {
+        DedupTable(Table { table: Vec::new() })
+    }
+    fn store(&mut self, c: &C::Target) -> usize {
+        self.0.store_dedup(c)
+    }
+    fn values(&self) -> &[C] {
+        &self.0.table
+    }
+}
+impl<T: TableKind, E: Codec> Encoded<T, E>
+where
+    T::Proxy: Words,
+{
+    fn encode(&mut self) {
+        let values = self.table.values();
+        let lanes = T::Proxy::WORDS;
+        let mut words = Vec::with_capacity(values.len() * lanes);
+        values.iter().for_each(|value| value.to_words(&mut words));
+
+        self.bytes.clear();
+        if lanes != 0 {
+            E::encode(&words, lanes, &mut self.bytes);
+        }
+        self.len = u32::try_from(values.len()).unwrap();
+    }
+}
+impl<T: TableKind, E: Codec> ArchivedEncoded<T, E>
+where
+    T::Proxy: Words + Serialize<Serializer>,
+{
+    /// The archived `Vec<T::Proxy>` of the values encoded in this table.
+    fn decode(&self) -> AlignedVec {
+        let (lanes, len) = (T::Proxy::WORDS, self.len as usize);
+        let mut words = Vec::with_capacity(len * lanes);
+        if lanes != 0 {
+            E::decode(&self.bytes, lanes, len, &mut words);
+        }
+        // Codecs are trusted to decode `len` values, but let's not read out
+        // of bounds if they don't.
+        words.resize(len * lanes, 0);
+
+        let value = |i: usize| T::Proxy::from_words(&words[i * lanes..][..lanes]);
+        let values: Vec<T::Proxy> = (0..len).map(value).collect();
+        // unwrap: serializing to an `AlignedVec` doesn't fail
+        rkyv::to_bytes::<_, 1024>(&values).unwrap()
+    }
+}
+impl<T, E, Tk, Tt> Tables for HCons<Encoded<T, E>, Tt>
+where
+    T: TableKind,
+    T::Proxy: Words + Serialize<Serializer>,
+    E: Codec,
+    Tk: Keys,
+    Tt: Tables<Keys = Tk>,
+{
+    type Keys = HCons<Key<T::Proxy>, Tk>;
+
+    #[inline]
+    fn insert_archived_keys<S: EntitySpawner>(
+        ArchivedHCons(_, tail): &ArchivedHCons<Encoded<T, E>, Tt>,
+        ArchivedHCons(key_head, key_tail): &ArchivedHCons<Key<T::Proxy>, Tk>,
+        decoded: &[Decoded],
+        index: usize,
+        mut cmds: S,
+    ) {
+        if let (Some(index), Decoded::Archived(bytes)) = (key_head.index.as_ref(), &decoded[0]) {
+            let index = usize::from(index.get() - 1);
+            // SAFETY: `bytes` is an archived `Vec<T::Proxy>`, see `Tables::decode`.
+            let table = unsafe { rkyv::archived_root::<Vec<T::Proxy>>(bytes) };
+            let target = T::Proxy::to_target_in(&table[index], cmds.world());
+            cmds.insert(target);
+            cmds.run_hooks::<<T::Proxy as ArchiveProxy>::Target>();
+        }
+        Tt::insert_archived_keys(tail, key_tail, &decoded[1..], index, cmds);
+    }
+    #[inline]
+    fn new() -> Self {
+        let head = Encoded {
+            table: T::new(),
+            bytes: Vec::new(),
+            len: 0,
+            _codec: PhantomData,
+        };
+        HCons(head, Tt::new())
+    }
+    #[inline]
+    fn insert_entity_components(
+        &mut self,
+        (head, tail): ComponentsOf<HCons<Key<T::Proxy>, Tk>>,
+    ) -> HCons<Key<T::Proxy>, Tk> {
+        let index = head.map(|c| self.0.table.store(c));
+        let tail = self.1.insert_entity_components(tail);
+        HCons(Key::from_index(index), tail)
+    }
+    fn encode(&mut self) {
+        self.0.encode();
+        self.1.encode();
+    }
+    fn decode(
+        ArchivedHCons(head, tail): &Self::Archived,
+        world: Option<&World>,
+        decoded: &mut Vec<Decoded>,
+    ) {
+        decoded.push(Decoded::Archived(head.decode()));
+        Tt::decode(tail, world, decoded);
+    }
+    fn component_count(&self, index: usize) -> usize {
+        (index == 0)
+            .then_some(self.0.table.values().len())
+            .unwrap_or_else(|| self.1.component_count(index - 1))
+    }
+    fn component_name(&self, index: usize) -> &'static str {
+        (index == 0)
+            .then_some(std::any::type_name::<<T::Proxy as ArchiveProxy>::Target>())
+            .unwrap_or_else(|| self.1.component_name(index - 1))
+    }
+    fn describe(components: &mut Vec<ComponentInfo>) {
+        let info = ComponentInfo::new::<T::Proxy>(T::STORAGE);
+        components.push(info.with_codec(E::NAME));
+        Tt::describe(components);
+    }
+    fn archived_lens(ArchivedHCons(head, tail): &Self::Archived, lens: &mut [usize]) {
+        lens[0] = head.len as usize;
+        Tt::archived_lens(tail, &mut lens[1..]);
+    }
+    fn archived_sizes(ArchivedHCons(head, tail): &Self::Archived, sizes: &mut [usize]) {
+        sizes[0] = head.bytes.len();
+        Tt::archived_sizes(tail, &mut sizes[1..]);
+    }
+    fn count_archived(ArchivedHCons(_, tail): &Self::Archived, index: usize, counts: &mut [usize]) {
+        Tt::count_archived(tail, index, &mut counts[1..]);
+    }
+    fn auto_storages(&self, storages: &mut Vec<Storage>) {
+        self.1.auto_storages(storages);
+    }
+    fn archived_auto_storages(
+        ArchivedHCons(_, tail): &Self::Archived,
+        storages: &mut Vec<Storage>,
+    ) -> Result<(), header::Error> {
+        Tt::archived_auto_storages(tail, storages)
+    }
+    const COMPONENT_COUNT: usize = 1 + Tt::COMPONENT_COUNT;
+}
+impl<T, E, Tt> ParTables for HCons<Encoded<T, E>, Tt>
+where
+    T: TableKind + Send,
+    T::Proxy: Words + Serialize<Serializer>,
+    E: Codec,
+    Tt: ParTables,
+{
+    fn fills<'a>(
+        &'a mut self,
+        world: &'a World,
+        ids: &'a [Entity],
+        columns: &'a mut [Vec<Option<usize>>],
+        fills: &mut Vec<Fill<'a>>,
+    ) {
+        let HCons(Encoded { table, .. }, tail) = self;
+        // unwrap: there is one column per table
+        let (column, columns) = columns.split_first_mut().unwrap();
+        fills.push(Box::new(move || {
+            *table = T::new();
+            column.clear();
+            let values = ids
+                .iter()
+                .map(|id| world.get::<<T::Proxy as ArchiveProxy>::Target>(*id));
+            column.extend(values.map(|value| value.map(|v| table.store(v))));
+        }));
+        tail.fills(world, ids, columns, fills);
+    }
+}
+
+/// Fill a table with the values of entities, see [`ParTables::fills`].
+pub type Fill<'a> = Box<dyn FnOnce() + Send + 'a>;
+
+/// Fill the tables of `Ts` in parallel, this is
+/// [`TableStorage::par_insert_values`] when `Ts` is [`ParTables`].
+pub type ParInsert<Ts> =
+    fn(&mut TableStorage<Ts>, &World, &[Entity]) -> Vec<KeyStorage<<Ts as Tables>::Keys>>;
+
+/// A table of a scene, decoded once per spawn by [`Tables::decode`].
+#[derive(Debug)]
+pub enum Decoded {
+    /// The values are read from the archived table.
+    InPlace,
+    /// The archived `Vec` of the values of a table with a [`Codec`].
+    Archived(AlignedVec),
+    /// The `Vec` of the targets of a `Columnar` table.
+    Targets(Box<dyn Any + Send + Sync>),
+}
+
+/// The decoded tables of a scene, see [`ArchivedTableStorage::decode`].
+#[derive(Clone, Debug, Default)]
+pub struct DecodedTables(Arc<[Decoded]>);
 
 #[derive(Clone, Archive, Deserialize, Serialize)]
+#[archive_attr(repr(C))]
 pub struct TableStorage<Ts> {
     tables: Ts,
 }
@@ -167,6 +730,17 @@ impl<Ts: Tables> TableStorage<Ts> {
     pub fn insert_values(&mut self, values: ComponentsOf<Ts::Keys>) -> KeyStorage<Ts::Keys> {
         KeyStorage(self.tables.insert_entity_components(values))
     }
+    /// Encode the tables with a [`Codec`] and pick the storage of `Auto`
+    /// tables, call this once all entities are inserted.
+    pub(crate) fn encode(&mut self) {
+        self.tables.encode();
+    }
+    /// The storage picked for each `Auto` table, see [`crate::header::Header::auto`].
+    pub fn auto_storages(&self) -> Vec<Storage> {
+        let mut storages = Vec::new();
+        self.tables.auto_storages(&mut storages);
+        storages
+    }
     pub const fn component_count(&self) -> usize {
         Ts::COMPONENT_COUNT
     }
@@ -177,10 +751,209 @@ impl<Ts: Tables> TableStorage<Ts> {
         self.tables.component_name(index)
     }
 }
+impl<Ts: ParTables> TableStorage<Ts> {
+    /// Like calling [`TableStorage::insert_values`] for each of the `ids`
+    /// entities, but gathering each table in a separate task of the
+    /// `ComputeTaskPool`.
+    ///
+    /// The tables and keys are the same as with `insert_values`. Each task
+    /// interns strings in its own copy of the current [`Interner`], the
+    /// strings of each copy are then added to the current interner in table
+    /// order. Tables whose strings end up at other indices than in their copy
+    /// are filled again on this thread.
+    ///
+    /// # Panics
+    ///
+    /// When the `ComputeTaskPool` is not initialized, this is the case outside
+    /// of a bevy `App`. Check it with `ComputeTaskPool::try_get`.
+    pub fn par_insert_values(
+        &mut self,
+        world: &World,
+        ids: &[Entity],
+    ) -> Vec<KeyStorage<Ts::Keys>> {
+        let mut columns: Vec<_> = (0..Ts::COMPONENT_COUNT)
+            .map(|_| Vec::with_capacity(ids.len()))
+            .collect();
+        let interner = Interner::current();
+        let forks: Vec<_> = (0..Ts::COMPONENT_COUNT)
+            .map(|_| interner.as_ref().map(Interner::fork))
+            .collect();
+
+        let mut fills = Vec::with_capacity(Ts::COMPONENT_COUNT);
+        self.tables.fills(world, ids, &mut columns, &mut fills);
+        let pool = ComputeTaskPool::get();
+        pool.scope(|scope| {
+            for (fill, fork) in fills.into_iter().zip(&forks) {
+                scope.spawn(async move {
+                    match fork {
+                        Some(fork) => fork.scope(fill),
+                        None => fill(),
+                    }
+                });
+            }
+        });
+
+        if let Some(interner) = interner {
+            let moved: Vec<_> = forks.iter().flatten().map(|f| !interner.merge(f)).collect();
+            if moved.contains(&true) {
+                let mut fills = Vec::with_capacity(Ts::COMPONENT_COUNT);
+                self.tables.fills(world, ids, &mut columns, &mut fills);
+                let refills = fills.into_iter().zip(moved).filter(|(_, moved)| *moved);
+                refills.for_each(|(fill, _)| interner.scope(fill));
+            }
+        }
+
+        let keys = (0..ids.len()).map(|i| Ts::Keys::from_columns(&columns, i));
+        keys.map(KeyStorage).collect()
+    }
+}
 impl<Ts: Tables> ArchivedTableStorage<Ts> {
     #[inline]
-    pub fn spawn_keys(&self, keys: &ArchivedKeyStorage<Ts::Keys>, cmds: impl EntitySpawner) {
-        Ts::insert_archived_keys(&self.tables, &keys.0, cmds);
+    pub fn spawn_keys(
+        &self,
+        keys: &ArchivedKeyStorage<Ts::Keys>,
+        decoded: &DecodedTables,
+        index: usize,
+        cmds: impl EntitySpawner,
+    ) {
+        Ts::insert_archived_keys(&self.tables, &keys.0, &decoded.0, index, cmds);
+    }
+    /// Decode the tables with a [`Codec`] and the `Columnar` tables, to pass
+    /// to [`ArchivedTableStorage::spawn_keys`].
+    ///
+    /// `world` is the world the scene is spawned in, if any.
+    pub fn decode(&self, world: Option<&World>) -> DecodedTables {
+        let mut decoded = Vec::with_capacity(Ts::COMPONENT_COUNT);
+        Ts::decode(&self.tables, world, &mut decoded);
+        DecodedTables(decoded.into())
+    }
+    /// The storage recorded for each `Auto` table, see
+    /// [`crate::header::Header::check_auto`].
+    pub fn auto_storages(&self) -> Result<Vec<Storage>, header::Error> {
+        let mut storages = Vec::new();
+        Ts::archived_auto_storages(&self.tables, &mut storages)?;
+        Ok(storages)
+    }
+    /// The number of values in each table.
+    pub fn lens(&self) -> Vec<usize> {
+        let mut lens = vec![0; Ts::COMPONENT_COUNT];
+        Ts::archived_lens(&self.tables, &mut lens);
+        lens
+    }
+    /// The size in bytes of the values of each table, encoded if the table
+    /// has a [`Codec`].
+    pub fn sizes(&self) -> Vec<usize> {
+        let mut sizes = vec![0; Ts::COMPONENT_COUNT];
+        Ts::archived_sizes(&self.tables, &mut sizes);
+        sizes
+    }
+    /// Increment `counts[i]` if the `index`th entity of the entity slice has
+    /// the `i`th table component in an `Auto` table.
+    ///
+    /// Use this with [`ArchivedKeyStorage::count`], which counts the other tables.
+    pub fn count(&self, index: usize, counts: &mut [usize]) {
+        Ts::count_archived(&self.tables, index, counts);
+    }
+    /// The archived value of proxy `P` that `keys` points to, if any.
+    pub fn get<P: ArchiveProxy, Idx>(
+        &self,
+        keys: &ArchivedKeyStorage<Ts::Keys>,
+    ) -> Option<&P::Archived>
+    where
+        Ts: FindTable<P, Idx>,
+    {
+        Ts::find(&self.tables, &keys.0)
+    }
+}
+
+/// [`Tables`] that contain the `P` proxy at `Idx`.
+///
+/// `Columnar` proxies and tables with a [`Codec`] are not stored as archived
+/// proxies, so they can't be found. `Auto` proxies are not found through
+/// keys, so they can't be found either.
+pub trait FindTable<P: ArchiveProxy, Idx>: Tables {
+    fn find<'a>(
+        archive: &'a Self::Archived,
+        keys: &Archived<Self::Keys>,
+    ) -> Option<&'a P::Archived>;
+}
+impl<P: ArchiveProxy, Tt: Tables> FindTable<P, Here> for HCons<Table<P>, Tt> {
+    #[inline]
+    fn find<'a>(
+        ArchivedHCons(head, _): &'a ArchivedHCons<Table<P>, Tt>,
+        ArchivedHCons(key_head, _): &ArchivedHCons<Key<P>, Tt::Keys>,
+    ) -> Option<&'a P::Archived> {
+        head.get(key_head)
+    }
+}
+impl<P, Tt: Tables> FindTable<P, Here> for HCons<DedupTable<P>, Tt>
+where
+    P: ArchiveProxy + PartialEq<P::Target>,
+{
+    #[inline]
+    fn find<'a>(
+        ArchivedHCons(head, _): &'a ArchivedHCons<DedupTable<P>, Tt>,
+        ArchivedHCons(key_head, _): &ArchivedHCons<Key<P>, Tt::Keys>,
+    ) -> Option<&'a P::Archived> {
+        head.0.get(key_head)
+    }
+}
+impl<P, H, Tt, Idx> FindTable<P, There<Idx>> for HCons<Table<H>, Tt>
+where
+    P: ArchiveProxy,
+    H: ArchiveProxy,
+    Tt: FindTable<P, Idx>,
+{
+    #[inline]
+    fn find<'a>(
+        ArchivedHCons(_, tail): &'a ArchivedHCons<Table<H>, Tt>,
+        ArchivedHCons(_, key_tail): &ArchivedHCons<Key<H>, Tt::Keys>,
+    ) -> Option<&'a P::Archived> {
+        Tt::find(tail, key_tail)
+    }
+}
+impl<P, H, Tt, Idx> FindTable<P, There<Idx>> for HCons<DedupTable<H>, Tt>
+where
+    P: ArchiveProxy,
+    H: ArchiveProxy + PartialEq<H::Target>,
+    Tt: FindTable<P, Idx>,
+{
+    #[inline]
+    fn find<'a>(
+        ArchivedHCons(_, tail): &'a ArchivedHCons<DedupTable<H>, Tt>,
+        ArchivedHCons(_, key_tail): &ArchivedHCons<Key<H>, Tt::Keys>,
+    ) -> Option<&'a P::Archived> {
+        Tt::find(tail, key_tail)
+    }
+}
+impl<P, H, Tt, Idx> FindTable<P, There<Idx>> for HCons<Columnar<H>, Tt>
+where
+    P: ArchiveProxy,
+    H: ColumnarProxy,
+    Tt: FindTable<P, Idx>,
+{
+    #[inline]
+    fn find<'a>(
+        ArchivedHCons(_, tail): &'a ArchivedHCons<Columnar<H>, Tt>,
+        ArchivedHCons(_, key_tail): &ArchivedHCons<Key<H>, Tt::Keys>,
+    ) -> Option<&'a P::Archived> {
+        Tt::find(tail, key_tail)
+    }
+}
+impl<P, T, E, Tt, Idx> FindTable<P, There<Idx>> for HCons<Encoded<T, E>, Tt>
+where
+    P: ArchiveProxy,
+    T: TableKind,
+    T::Proxy: Words + Serialize<Serializer>,
+    E: Codec,
+    Tt: FindTable<P, Idx>,
+{
+    #[inline]
+    fn find<'a>(
+        ArchivedHCons(_, tail): &'a ArchivedHCons<Encoded<T, E>, Tt>,
+        ArchivedHCons(_, key_tail): &ArchivedHCons<Key<T::Proxy>, Tt::Keys>,
+    ) -> Option<&'a P::Archived> {
+        Tt::find(tail, key_tail)
     }
 }
 
@@ -194,10 +967,15 @@ pub type ComponentsOf<'w, K> = ROQueryItem<'w, <K as Keys>::Query>;
 pub trait Keys: Archive {
     type Query: WorldQuery;
     fn empty() -> Self;
+    /// The keys of the `index`th entity in `columns`, see [`Tables::gather`].
+    fn from_columns(columns: &[Vec<Option<usize>>], index: usize) -> Self;
     fn occupancy(&self) -> String;
+    /// Increment `counts[i]` if the `i`th key in `archive` points to a value.
+    fn count_archived(archive: &Self::Archived, counts: &mut [usize]);
 }
 
 #[derive(Clone, Copy, Archive, Deserialize, Serialize)]
+#[archive_attr(repr(C))]
 pub struct Key<C: ArchiveProxy> {
     index: Option<NonZeroU16>,
     _value_ty: PhantomData<fn(C)>,
@@ -223,23 +1001,37 @@ impl<C: ArchiveProxy> Key<C> {
 impl Keys for () {
     type Query = ();
     fn empty() {}
+    fn from_columns(_: &[Vec<Option<usize>>], _: usize) {}
     fn occupancy(&self) -> String {
         String::new()
     }
+    fn count_archived((): &(), _: &mut [usize]) {}
 }
-impl<C: ArchiveProxy, Tl: Keys> Keys for (Key<C>, Tl) {
+impl<C: ArchiveProxy, Tl: Keys> Keys for HCons<Key<C>, Tl> {
     type Query = (Option<&'static C::Target>, Tl::Query);
 
     #[inline]
     fn empty() -> Self {
-        (Key { index: None, _value_ty: PhantomData }, Tl::empty())
+        HCons(Key { index: None, _value_ty: PhantomData }, Tl::empty())
+    }
+    #[inline]
+    fn from_columns(columns: &[Vec<Option<usize>>], index: usize) -> Self {
+        let head = Key::from_index(columns[0][index]);
+        HCons(head, Tl::from_columns(&columns[1..], index))
     }
     fn occupancy(&self) -> String {
         format!("{:?}{}", &self.0, self.1.occupancy())
     }
+    fn count_archived(ArchivedHCons(head, tail): &Self::Archived, counts: &mut [usize]) {
+        if head.index.is_some() {
+            counts[0] += 1;
+        }
+        Tl::count_archived(tail, &mut counts[1..]);
+    }
 }
 
 #[derive(Clone, Copy, Archive, Deserialize, Serialize)]
+#[archive_attr(repr(C))]
 pub struct KeyStorage<Ks>(Ks);
 
 impl<Ks: Keys> Default for KeyStorage<Ks> {
@@ -249,6 +1041,12 @@ impl<Ks: Keys> Default for KeyStorage<Ks> {
     }
 }
 
+impl<Ks: Keys> ArchivedKeyStorage<Ks> {
+    /// Increment `counts[i]` if this entity has the `i`th table component.
+    pub fn count(&self, counts: &mut [usize]) {
+        Ks::count_archived(&self.0, counts);
+    }
+}
 impl<Ks: Keys> KeyStorage<Ks> {
     pub fn no_component() -> Self {
         KeyStorage(Ks::empty())
@@ -0,0 +1,193 @@
+//! The header of `.hollow_bvyfst` files.
+//!
+//! It is 32 bytes long, so that the `rkyv` archive that follows it stays
+//! aligned:
+//!
+//! ```text
+//! 0              13 14      20     24            32
+//! | hollow_bvyfst |_| v00006 | auto | fingerprint |
+//! ```
+//!
+//! `auto` is a little endian [`Header::auto`], `fingerprint` is a little
+//! endian [`Schema::fingerprint`]. Readers check that `auto` matches the
+//! storages recorded in the archive, see [`Header::check_auto`].
+
+use thiserror::Error;
+
+use crate::entity::auto;
+use crate::schema::{Schema, Storage};
+use crate::version::{self, DIGIT_COUNT, VERSION};
+
+pub const LEN: usize = 32;
+const MAGIC: &[u8; 13] = b"hollow_bvyfst";
+/// How many `Auto` proxies fit in [`Header::auto`].
+pub const MAX_AUTO: usize = 16;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("The file is too short to be a hollow_bvyfst scene ({0} bytes)")]
+    TooShort(usize),
+    #[error("The file is not a hollow_bvyfst scene, it doesn't start with 'hollow_bvyfst'")]
+    NotHollow,
+    #[error("Can't parse version from hollow_bvyfst header: {0}")]
+    InvalidVersion(#[from] version::Error),
+    #[error("The scene isn't compatible with the current version: (file: {0}, us: {VERSION})")]
+    IncompatibleVersion(u16),
+    #[error(
+        "The scene was saved with a different schema than the one used to load it: \
+        (file: {file:016x}, us: {schema:016x})"
+    )]
+    SchemaMismatch { file: u64, schema: u64 },
+    #[error("The scene has {0} Auto proxies, but at most {MAX_AUTO} are supported")]
+    TooManyAuto(usize),
+    #[error("Unknown Auto storage code {0}, the file is likely corrupted")]
+    UnknownAutoStorage(u8),
+    #[error(
+        "The Auto storages of the header don't match the ones of the scene: \
+        (header: {header:08x}, scene: {scene:08x})"
+    )]
+    AutoMismatch { header: u32, scene: u32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header {
+    pub version: u16,
+    /// The storage picked for each `Auto` proxy when saving, 2 bits per
+    /// proxy in schema order, see [`Header::auto_storage`].
+    pub auto: u32,
+    pub fingerprint: u64,
+}
+impl Header {
+    /// The header of a file saved with `schema` by this version of the crate.
+    pub fn new(schema: &Schema) -> Self {
+        Header {
+            version: VERSION.get(),
+            auto: 0,
+            fingerprint: schema.fingerprint(),
+        }
+    }
+    /// Record the storage picked for each `Auto` proxy, in schema order.
+    pub fn with_auto(self, storages: &[Storage]) -> Result<Self, Error> {
+        if storages.len() > MAX_AUTO {
+            return Err(Error::TooManyAuto(storages.len()));
+        }
+        let codes = storages.iter().enumerate();
+        let auto = codes.fold(0, |acc, (i, s)| {
+            acc | (u32::from(auto::code(*s)) << (2 * i))
+        });
+        Ok(Header { auto, ..self })
+    }
+    /// The storage picked for the `i`th `Auto` proxy of the schema.
+    pub fn auto_storage(&self, i: usize) -> Result<Storage, Error> {
+        auto::storage(((self.auto >> (2 * i)) & 0b11) as u8)
+    }
+    /// Check that [`Header::auto`] records `storages`, the storages picked
+    /// for each `Auto` proxy of the archived scene.
+    pub fn check_auto(&self, storages: &[Storage]) -> Result<(), Error> {
+        let scene = self.with_auto(storages)?.auto;
+        if self.auto != scene {
+            return Err(Error::AutoMismatch { header: self.auto, scene });
+        }
+        Ok(())
+    }
+    pub fn to_bytes(self) -> [u8; LEN] {
+        let version = format!("v{:0digi$}", self.version, digi = DIGIT_COUNT);
+        let mut bytes = [0; LEN];
+        bytes[..13].copy_from_slice(MAGIC);
+        bytes[13] = b' ';
+        bytes[14..20].copy_from_slice(version.as_bytes());
+        bytes[20..24].copy_from_slice(&self.auto.to_le_bytes());
+        bytes[24..].copy_from_slice(&self.fingerprint.to_le_bytes());
+        bytes
+    }
+    /// Read the header at the start of `bytes`, returns it with the bytes
+    /// following it.
+    ///
+    /// This doesn't check the file is compatible, use [`Header::check`].
+    pub fn read(bytes: &[u8]) -> Result<(Self, &[u8]), Error> {
+        if bytes.len() < LEN {
+            return Err(Error::TooShort(bytes.len()));
+        }
+        let (header, payload) = bytes.split_at(LEN);
+        if &header[..13] != MAGIC {
+            return Err(Error::NotHollow);
+        }
+        let version = version::Version::get_version_slice(&header[14..20])?;
+        // unwrap: `header[20..24]` is exactly 4 bytes long
+        let auto = u32::from_le_bytes(header[20..24].try_into().unwrap());
+        // unwrap: `header[24..]` is exactly 8 bytes long
+        let fingerprint = u64::from_le_bytes(header[24..].try_into().unwrap());
+        Ok((Header { version, auto, fingerprint }, payload))
+    }
+    /// Check that a file with this header can be read with `schema`.
+    pub fn check(&self, schema: &Schema) -> Result<(), Error> {
+        self.check_version()?;
+        let schema = schema.fingerprint();
+        if self.fingerprint != schema {
+            return Err(Error::SchemaMismatch { file: self.fingerprint, schema });
+        }
+        Ok(())
+    }
+    /// Check that a file with this header was saved by a compatible version
+    /// of this crate, whatever its schema.
+    pub fn check_version(&self) -> Result<(), Error> {
+        if self.version != VERSION.get() {
+            return Err(Error::IncompatibleVersion(self.version));
+        }
+        Ok(())
+    }
+}
+
+/// Copy `payload` in a buffer aligned for `rkyv`.
+pub fn aligned(payload: &[u8]) -> rkyv::AlignedVec {
+    let mut aligned = rkyv::AlignedVec::with_capacity(payload.len());
+    aligned.extend_from_slice(payload);
+    aligned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Error, Header, LEN, MAX_AUTO};
+    use crate::schema::{Schema, Storage};
+
+    #[test]
+    fn roundtrip() {
+        let schema = Schema::of::<(), ()>();
+        let auto = [Storage::DedupTable, Storage::Inline, Storage::Table];
+        let header = Header::new(&schema).with_auto(&auto).unwrap();
+        let mut bytes = header.to_bytes().to_vec();
+        bytes.extend_from_slice(b"payload");
+
+        assert_eq!(&bytes[..20], b"hollow_bvyfst v00006");
+        let (read, payload) = Header::read(&bytes).unwrap();
+        assert_eq!(read, header);
+        assert_eq!(payload, b"payload");
+        let read_auto: Vec<_> = (0..3).map(|i| read.auto_storage(i).unwrap()).collect();
+        assert_eq!(read_auto, auto);
+        assert!(read.check_auto(&auto).is_ok());
+        assert!(matches!(
+            read.check_auto(&auto[..2]),
+            Err(Error::AutoMismatch { .. })
+        ));
+        let unknown = Header { auto: 0b11, ..header };
+        assert!(matches!(
+            unknown.auto_storage(0),
+            Err(Error::UnknownAutoStorage(3))
+        ));
+        assert!(matches!(
+            header.with_auto(&[Storage::Table; MAX_AUTO + 1]),
+            Err(Error::TooManyAuto(17))
+        ));
+        assert!(read.check(&schema).is_ok());
+
+        let other = Header { fingerprint: !header.fingerprint, ..header };
+        assert!(matches!(
+            other.check(&schema),
+            Err(Error::SchemaMismatch { .. })
+        ));
+        assert!(matches!(
+            Header::read(&bytes[..LEN - 1]),
+            Err(Error::TooShort(_))
+        ));
+    }
+}
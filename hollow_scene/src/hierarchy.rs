@@ -1,14 +1,18 @@
 //! Write/Read a bevy hierarchy into a slice
 
 use std::marker::PhantomData;
+use std::time::{Duration, Instant};
 
-use ::bevy::ecs::query::{ROQueryItem, WorldQuery};
-use ::bevy::ecs::world::EntityMut;
+use ::bevy::ecs::query::{ROQueryItem, ReadOnlyWorldQuery, WorldQuery};
 use ::bevy::prelude::{BuildWorldChildren, QueryState};
+use ::bevy::tasks::ComputeTaskPool;
 use bevy::prelude as bevy;
 use rkyv::Archived;
 
-use crate::entity::{Entity, InlineStorage, Inlines, Keys, TableStorage, Tables};
+use crate::entity::{DecodedTables, Entity, InlineStorage, Inlines, KeyStorage, Keys};
+use crate::entity::{ParInsert, TableStorage, Tables};
+use crate::strings::SceneStrings;
+use crate::{required::RequiredBundles, SaveOptions};
 
 pub struct Spawn<'ett, 'b: 'ett + 't, 't, Ts: Tables + 'b, Is: Inlines + 'b> {
     scene: &'ett [Archived<Entity<Ts::Keys, Is>>],
@@ -22,100 +26,266 @@ impl<'ett, 'b: 'ett, 't, Ts: Tables, Is: Inlines> Spawn<'ett, 'b, 't, Ts, Is> {
     ) -> Self {
         Spawn { scene, _b: PhantomData, tables }
     }
-    /// Split this `Spawn` in two.
+    /// Spawn the next entities of `state` in `world`, within `budget`.
     ///
-    /// Assuming this is a list of entities and `descendants` is how many
-    /// children the current entity has (accumulated recursively), we have:
-    ///
-    /// - `&mut self` becomes all remaining entities to spawn.
-    /// - the return value contains all children entities to spawn.
-    fn extract_children(&mut self, descendants: u32) -> Self {
-        let (childr, scene) = self.scene.split_at(descendants as usize);
-        self.scene = scene;
-        Spawn { scene: childr, ..*self }
-    }
-    fn next(&mut self) -> Option<&'ett Archived<Entity<Ts::Keys, Is>>> {
-        let (entity, scene) = self.scene.split_first()?;
-        self.scene = scene;
-        Some(entity)
-    }
-    pub fn children_of(mut self, mut spawner: EntityMut) {
-        spawner.with_children(|spawner| loop {
-            let Some(entity) = self.next() else { return; };
+    /// Since the entity slice is in depth-first order, parents are always
+    /// spawned before their children.
+    pub fn chunk(&self, world: &mut bevy::World, state: &mut ChunkedSpawn, budget: SpawnBudget) {
+        let start = Instant::now();
+        let max_index = state.spawned().saturating_add(budget.max_entities);
+        let out_of_time = || budget.max_time.map_or(false, |max| start.elapsed() >= max);
+        let required = world.get_resource::<RequiredBundles>().cloned();
+        let decoded = state
+            .decoded
+            .get_or_insert_with(|| self.tables.decode(Some(world)))
+            .clone();
 
-            let mut bevy_entity = spawner.spawn_empty();
+        let first = state.ids.len();
+        while let Some(entity) = self.scene.get(state.ids.len()) {
+            let index = state.ids.len();
+            // Always spawn at least one entity, so that every budget progresses.
+            if index > first && (index >= max_index || out_of_time()) {
+                return;
+            }
+            while matches!(state.ancestors.last(), Some(&(_, end)) if end <= index) {
+                state.ancestors.pop();
+            }
+            // unwrap: the root ancestor ends at the end of the slice
+            let (parent, _) = *state.ancestors.last().unwrap();
 
+            let mut bevy_entity = world.spawn_empty();
+            bevy_entity.set_parent(parent);
             self.tables
-                .spawn_keys(&entity.ref_table_keys, &mut bevy_entity);
+                .spawn_keys(&entity.ref_table_keys, &decoded, index, &mut bevy_entity);
             entity.inline_items.spawn(&mut bevy_entity);
+            if let Some(required) = &required {
+                // The synthetic scene root, parent of the scene's root entities.
+                if index == 0 {
+                    required.apply_root(&mut bevy_entity);
+                }
+                required.apply(&mut bevy_entity);
+            }
 
-            let descendant_count = entity.children;
+            let id = bevy_entity.id();
+            state.ids.push(id);
+            state
+                .ancestors
+                .push((id, index + 1 + entity.children as usize));
+        }
+    }
+}
+
+/// How many entities to spawn in a single [`ArchivedFastScene::spawn_chunk`].
+///
+/// The chunk ends as soon as one of the limits is reached, but always spawns
+/// at least one entity.
+///
+/// [`ArchivedFastScene::spawn_chunk`]: crate::ArchivedFastScene::spawn_chunk
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SpawnBudget {
+    /// The maximum number of entities to spawn.
+    pub max_entities: usize,
+    /// The maximum time to spend spawning entities.
+    pub max_time: Option<Duration>,
+}
+impl SpawnBudget {
+    /// Spawn everything in a single chunk.
+    pub const UNLIMITED: Self = SpawnBudget { max_entities: usize::MAX, max_time: None };
+}
+impl Default for SpawnBudget {
+    /// 4 milliseconds, a quarter of a frame at 60 FPS.
+    fn default() -> Self {
+        SpawnBudget {
+            max_entities: usize::MAX,
+            max_time: Some(Duration::from_millis(4)),
+        }
+    }
+}
 
-            let spawn = self.extract_children(descendant_count);
-            spawn.children_of(bevy_entity);
-        });
+/// The state of a scene spawned a chunk at a time.
+///
+/// Create it with [`ArchivedFastScene::chunked_spawn`], then call
+/// [`ArchivedFastScene::spawn_chunk`] until it returns `true`.
+///
+/// [`ArchivedFastScene::chunked_spawn`]: crate::ArchivedFastScene::chunked_spawn
+/// [`ArchivedFastScene::spawn_chunk`]: crate::ArchivedFastScene::spawn_chunk
+#[derive(Clone, Debug)]
+pub struct ChunkedSpawn {
+    /// The spawned entities, in entity slice order.
+    ids: Vec<bevy::Entity>,
+    /// The ancestors of the next entity to spawn, with the index in the
+    /// entity slice following their last descendant.
+    ancestors: Vec<(bevy::Entity, usize)>,
+    total: usize,
+    /// The strings of the scene, inserted as a resource while spawning.
+    pub(crate) strings: Option<SceneStrings>,
+    /// The tables with a `Codec` and the `Columnar` tables, decoded on the
+    /// first chunk.
+    decoded: Option<DecodedTables>,
+}
+impl ChunkedSpawn {
+    pub(crate) fn new(root: bevy::Entity, total: usize) -> Self {
+        ChunkedSpawn {
+            ids: Vec::with_capacity(total),
+            ancestors: vec![(root, total)],
+            total,
+            strings: None,
+            decoded: None,
+        }
+    }
+    /// The entity the scene is spawned under.
+    pub fn root(&self) -> bevy::Entity {
+        self.ancestors[0].0
+    }
+    /// How many entities are spawned so far.
+    pub fn spawned(&self) -> usize {
+        self.ids.len()
+    }
+    /// How many entities there is to spawn in total.
+    pub fn total(&self) -> usize {
+        self.total
+    }
+    /// Whether all the entities of the scene are spawned.
+    pub fn is_done(&self) -> bool {
+        self.ids.len() == self.total
+    }
+    /// The `Entity` of each element of the entity slice spawned so far.
+    pub fn ids(&self) -> &[bevy::Entity] {
+        &self.ids
+    }
+    pub(crate) fn into_ids(self) -> Vec<bevy::Entity> {
+        self.ids
     }
 }
+
 type BuildQuery<Ks, Is> = (
+    bevy::Entity,
     Option<&'static bevy::Children>,
     <Ks as Keys>::Query,
     <Is as Inlines>::Query,
 );
 
-pub fn build<Ts: Tables, Is: Inlines>(
+type RootQuery = (bevy::Entity, Option<&'static bevy::Name>);
+
+/// Build the entity slice of a [`crate::FastScene`] from `world`.
+///
+/// Only entities matching `F` are stored. When an entity doesn't match `F`,
+/// it is skipped together with all its descendants.
+///
+/// When `options.deterministic` is set, root entities are sorted by `Name`,
+/// then by entity index, so that identical scenes produce identical slices and
+/// tables. Otherwise, the root order is the query iteration order, which
+/// depends on archetype and table insertion order.
+///
+/// When `options.parallel` is set and `par_insert` is `Some`, tables are
+/// filled with `par_insert` after walking the hierarchy, each table in a
+/// separate task. The result is the same, except for the order of strings
+/// interned by several tables, see [`TableStorage::par_insert_values`].
+/// Without a `ComputeTaskPool`, `parallel` is ignored.
+///
+/// Also returns the bevy `Entity` of each element of the entity slice, the
+/// first element (the scene root) doesn't exist in `world`, and is a
+/// placeholder.
+pub fn build<Ts: Tables, Is: Inlines, F: ReadOnlyWorldQuery>(
     world: &mut bevy::World,
     tables: &mut TableStorage<Ts>,
-) -> Box<[Entity<Ts::Keys, Is>]> {
-    let mut root_query =
-        world.query_filtered::<BuildQuery<Ts::Keys, Is>, bevy::Without<bevy::Parent>>();
+    options: SaveOptions,
+    par_insert: Option<ParInsert<Ts>>,
+) -> (Box<[Entity<Ts::Keys, Is>]>, Box<[bevy::Entity]>) {
+    let mut root_query = world.query_filtered::<RootQuery, (bevy::Without<bevy::Parent>, F)>();
     root_query.update_archetypes(world);
-    let mut child_query = world.query::<BuildQuery<Ts::Keys, Is>>();
+    let mut child_query = world.query_filtered::<BuildQuery<Ts::Keys, Is>, F>();
     child_query.update_archetypes(world);
 
-    let entity_count = child_query.iter_manual(world).len();
+    let entity_count = world.entities().len() as usize;
     let mut entities: Vec<_> = Vec::with_capacity(entity_count + 1);
-    entities.push(Entity::with_children(entity_count as u32));
-
-    let grand_children = root_query
-        .iter_manual(world)
-        .map(|item| child(item, &child_query, &mut entities, tables, world))
-        .sum::<u32>();
+    entities.push((Entity::with_children(0), bevy::Entity::PLACEHOLDER));
 
-    entities[0].children += grand_children;
-    entities.into_boxed_slice()
+    let mut roots: Vec<_> = root_query.iter_manual(world).collect();
+    if options.deterministic {
+        roots.sort_by_key(|&(entity, name)| (name.map(bevy::Name::as_str), entity.index()));
+    }
+    let parallel = options.parallel && ComputeTaskPool::try_get().is_some();
+    let par_insert = par_insert.filter(|_| parallel);
+    for (root, _) in roots {
+        // unwrap: `root_query`'s filter is stricter than `child_query`'s
+        let item = child_query.get_manual(world, root).unwrap();
+        let tables = par_insert.is_none().then_some(&mut *tables);
+        subtree(item, &child_query, &mut entities, tables, world);
+    }
+    entities[0].0.children = (entities.len() - 1) as u32;
+    if let Some(par_insert) = par_insert {
+        let ids: Vec<_> = entities[1..].iter().map(|&(_, id)| id).collect();
+        let keys = par_insert(tables, world, &ids);
+        for ((entity, _), keys) in entities[1..].iter_mut().zip(keys) {
+            entity.ref_table_keys = keys;
+        }
+    }
+    let (entities, ids): (Vec<_>, Vec<_>) = entities.into_iter().unzip();
+    (entities.into_boxed_slice(), ids.into_boxed_slice())
 }
-// TODO(clean) there is too many arguments to this function
-fn child<Ts: Tables, Is: Inlines>(
-    (children, table_query, inline_query): ROQueryItem<BuildQuery<Ts::Keys, Is>>,
-    query: &QueryState<BuildQuery<Ts::Keys, Is>>,
-    uninit: &mut Vec<Entity<Ts::Keys, Is>>,
-    tables: &mut TableStorage<Ts>,
+/// Push the entity of `item` and all its descendants to `uninit`, in
+/// depth-first order.
+///
+/// This uses an explicit stack rather than recursion, so that very deep
+/// hierarchies do not overflow the call stack.
+fn subtree<Ts: Tables, Is: Inlines, F: ReadOnlyWorldQuery>(
+    item: ROQueryItem<BuildQuery<Ts::Keys, Is>>,
+    query: &QueryState<BuildQuery<Ts::Keys, Is>, F>,
+    uninit: &mut Vec<(Entity<Ts::Keys, Is>, bevy::Entity)>,
+    mut tables: Option<&mut TableStorage<Ts>>,
     world: &bevy::World,
-) -> u32 {
-    let child_count = children.map_or(0, |c| c.len());
-
-    let inserted_index = uninit.len();
-    uninit.push(Entity {
-        children: child_count as u32,
+) {
+    // The entities whose children are being pushed, with their index in
+    // `uninit`, and the children yet to push.
+    let mut ancestors = vec![child(item, query, uninit, tables.as_deref_mut(), world)];
+    while let Some((_, children)) = ancestors.last_mut() {
+        if let Some(item) = children.next() {
+            let entry = child(item, query, uninit, tables.as_deref_mut(), world);
+            ancestors.push(entry);
+            continue;
+        }
+        // unwrap: we just checked `ancestors.last_mut()`
+        let (inserted_index, _) = ancestors.pop().unwrap();
+        // Filtered-out children are not in `uninit`, so we count what was
+        // actually pushed rather than using `children.len()`.
+        uninit[inserted_index].0.children = (uninit.len() - inserted_index - 1) as u32;
+    }
+}
+// TODO(clean) there is too many arguments to this function
+/// Push the entity of `item` to `uninit`, returns its index in `uninit` and
+/// its children.
+///
+/// Table components are only stored when `tables` is set, otherwise the
+/// entity has no keys.
+fn child<'w, 'q, Ts: Tables, Is: Inlines, F: ReadOnlyWorldQuery>(
+    (id, children, table_query, inline_query): ROQueryItem<'w, BuildQuery<Ts::Keys, Is>>,
+    query: &'q QueryState<BuildQuery<Ts::Keys, Is>, F>,
+    uninit: &mut Vec<(Entity<Ts::Keys, Is>, bevy::Entity)>,
+    tables: Option<&mut TableStorage<Ts>>,
+    world: &'w bevy::World,
+) -> (usize, IterChildren<'w, 'q, 'w, BuildQuery<Ts::Keys, Is>, F>) {
+    let entity = Entity {
+        children: 0,
         inline_items: InlineStorage::query(inline_query),
-        ref_table_keys: tables.insert_values(table_query),
-    });
-    let grand_children = IterChildren::new(children, query, world)
-        .map(|item| child(item, query, uninit, tables, world))
-        .sum::<u32>();
-
-    uninit[inserted_index].children += grand_children;
-    uninit[inserted_index].children
+        ref_table_keys: match tables {
+            Some(tables) => tables.insert_values(table_query),
+            None => KeyStorage::no_component(),
+        },
+    };
+    uninit.push((entity, id));
+    (uninit.len() - 1, IterChildren::new(children, query, world))
 }
-struct IterChildren<'chld, 'q, 'w, Q: WorldQuery> {
+/// Iterate over the children of an entity matching the query filter `F`.
+struct IterChildren<'chld, 'q, 'w, Q: WorldQuery, F: ReadOnlyWorldQuery> {
     entities: &'chld [bevy::Entity],
-    query: &'q QueryState<Q>,
+    query: &'q QueryState<Q, F>,
     world: &'w bevy::World,
 }
-impl<'chld, 'q, 'w, Q: WorldQuery> IterChildren<'chld, 'q, 'w, Q> {
+impl<'chld, 'q, 'w, Q: WorldQuery, F: ReadOnlyWorldQuery> IterChildren<'chld, 'q, 'w, Q, F> {
     fn new(
         children: Option<&'chld bevy::Children>,
-        query: &'q QueryState<Q>,
+        query: &'q QueryState<Q, F>,
         world: &'w bevy::World,
     ) -> Self {
         IterChildren {
@@ -125,13 +295,19 @@ impl<'chld, 'q, 'w, Q: WorldQuery> IterChildren<'chld, 'q, 'w, Q> {
         }
     }
 }
-impl<'chld, 'q, 'w, Q: WorldQuery> Iterator for IterChildren<'chld, 'q, 'w, Q> {
+impl<'chld, 'q, 'w, Q: WorldQuery, F: ReadOnlyWorldQuery> Iterator
+    for IterChildren<'chld, 'q, 'w, Q, F>
+{
     type Item = ROQueryItem<'w, Q>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let (entity, tail) = self.entities.split_first()?;
-        self.entities = tail;
+        loop {
+            let (entity, tail) = self.entities.split_first()?;
+            self.entities = tail;
 
-        Some(self.query.get_manual(self.world, *entity).unwrap())
+            if let Ok(item) = self.query.get_manual(self.world, *entity) {
+                return Some(item);
+            }
+        }
     }
 }
@@ -0,0 +1,173 @@
+//! Run code when hollow scenes are spawned.
+//!
+//! Spawn hooks add runtime-only components, such as physics bodies or AI
+//! state, to spawned entities that have a given proxied component:
+//!
+//! ```text
+//! Plugin!(Inline[MyTransform, ColliderProxy]).on_spawn::<Collider>(|entity| {
+//!     entity.insert(RigidBody::Fixed);
+//! })
+//! ```
+//!
+//! Hooks run as soon as their component is inserted on the entity. Table
+//! components are inserted before inline components, each in the order of the
+//! `Plugin!` list, so a hook may not see the components that follow its own.
+//!
+//! The hooks of a plugin only run on the scenes saved with its schema, when
+//! they are spawned with:
+//!
+//! - A [`crate::chunked::ChunkedScene`] or [`crate::reload::ReloadableScene`],
+//!   hooks run as soon as their component is inserted.
+//! - Bevy's `SceneBundle`, hooks run once bevy spawned the whole instance, on
+//!   each entity with their component, in the order they were added.
+//!
+//! Spawning an [`crate::ArchivedFastScene`] directly only runs the hooks of
+//! the [`SpawnHooks`] resource of the world, if any.
+//!
+//! Once all the entities of a scene instance are spawned,
+//! [`HollowSceneSpawned`] is sent.
+
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use bevy::ecs::world::EntityMut;
+use bevy::prelude::{Component, Entity, Event, Resource};
+
+/// A function called on spawned entities with a given component.
+pub type Hook = dyn Fn(&mut EntityMut) + Send + Sync;
+
+/// The spawn hooks of each component, see the [module docs](self).
+#[derive(Resource, Clone, Default)]
+pub struct SpawnHooks {
+    /// The hooks in the order they were added, with their component.
+    hooks: Arc<Vec<(TypeId, Arc<Hook>)>>,
+}
+impl SpawnHooks {
+    /// Call `hook` on spawned entities when `C` is inserted.
+    ///
+    /// Several hooks can be added for the same component, they run in the
+    /// order they were added.
+    pub fn add<C: Component>(&mut self, hook: impl Fn(&mut EntityMut) + Send + Sync + 'static) {
+        let hooks = Arc::make_mut(&mut self.hooks);
+        hooks.push((TypeId::of::<C>(), Arc::new(hook)));
+    }
+    /// Add all the hooks of `other`.
+    pub fn extend(&mut self, other: &SpawnHooks) {
+        let hooks = Arc::make_mut(&mut self.hooks);
+        hooks.extend(other.hooks.iter().cloned());
+    }
+    /// Run the hooks of the component with the given `type_id` on `entity`.
+    pub fn run(&self, type_id: TypeId, entity: &mut EntityMut) {
+        let hooks = self.hooks.iter().filter(|(id, _)| *id == type_id);
+        for (_, hook) in hooks {
+            hook(entity);
+        }
+    }
+    /// Run the hooks of all the components of `entity`, in the order they
+    /// were added.
+    pub fn run_all(&self, entity: &mut EntityMut) {
+        for (type_id, hook) in self.hooks.iter() {
+            if entity.contains_type_id(*type_id) {
+                hook(entity);
+            }
+        }
+    }
+    /// Run the hooks of `C` on `entity`, if its world has [`SpawnHooks`].
+    pub(crate) fn run_in_world<C: Component>(entity: &mut EntityMut) {
+        let Some(hooks) = entity.world().get_resource::<SpawnHooks>() else {
+            return;
+        };
+        let hooks = hooks.clone();
+        hooks.run(TypeId::of::<C>(), entity);
+    }
+}
+
+/// The [`SpawnHooks`] of each plugin, by the fingerprint of its schema.
+#[derive(Resource, Clone, Default)]
+pub(crate) struct PluginHooks(HashMap<u64, SpawnHooks>);
+impl PluginHooks {
+    pub(crate) fn add(&mut self, fingerprint: u64, hooks: &SpawnHooks) {
+        self.0.entry(fingerprint).or_default().extend(hooks);
+    }
+    /// The hooks of the plugin of scenes saved with the `fingerprint` schema.
+    pub(crate) fn get(&self, fingerprint: u64) -> Option<&SpawnHooks> {
+        self.0.get(&fingerprint)
+    }
+}
+
+/// Sent once all the entities of a hollow scene instance are spawned.
+#[derive(Event, Clone, Debug)]
+pub struct HollowSceneSpawned {
+    /// The entity the scene is spawned under.
+    pub root: Entity,
+    /// The spawned entity of each element of the entity slice.
+    ///
+    /// Use it with the indices of [`crate::ArchivedFastScene::index_of_path`]
+    /// or [`crate::query`].
+    pub entity_map: Vec<Entity>,
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use super::SpawnHooks;
+
+    #[derive(Component)]
+    struct Collider;
+
+    #[derive(Component, Debug, PartialEq)]
+    struct Body(u32);
+
+    #[test]
+    fn run_hooks() {
+        let mut hooks = SpawnHooks::default();
+        hooks.add::<Collider>(|entity| {
+            entity.insert(Body(1));
+        });
+        let mut other = SpawnHooks::default();
+        other.add::<Collider>(|entity| {
+            let body = entity.get::<Body>().map_or(0, |b| b.0);
+            entity.insert(Body(body + 1));
+        });
+        hooks.extend(&other);
+
+        let mut world = World::new();
+        let entity = world.spawn(Collider).id();
+        // Not run without the resource.
+        SpawnHooks::run_in_world::<Collider>(&mut world.entity_mut(entity));
+        assert_eq!(world.get::<Body>(entity), None);
+
+        world.insert_resource(hooks);
+        SpawnHooks::run_in_world::<Collider>(&mut world.entity_mut(entity));
+        assert_eq!(world.get::<Body>(entity), Some(&Body(2)));
+
+        let without = world.spawn_empty().id();
+        SpawnHooks::run_in_world::<Body>(&mut world.entity_mut(without));
+        assert_eq!(world.get::<Body>(without), None);
+    }
+
+    #[test]
+    fn run_all_hooks() {
+        let mut hooks = SpawnHooks::default();
+        hooks.add::<Body>(|entity| {
+            let body = entity.get::<Body>().map_or(0, |b| b.0);
+            entity.insert(Body(body * 10));
+        });
+        hooks.add::<Collider>(|entity| {
+            entity.insert(Body(1));
+        });
+        let mut world = World::new();
+        let collider = world.spawn(Collider).id();
+        let body = world.spawn(Body(2)).id();
+
+        hooks.run_all(&mut world.entity_mut(collider));
+        hooks.run_all(&mut world.entity_mut(body));
+
+        // The `Body` hook was added first, so it doesn't see the `Body` of
+        // the `Collider` hook.
+        assert_eq!(world.get::<Body>(collider), Some(&Body(1)));
+        assert_eq!(world.get::<Body>(body), Some(&Body(20)));
+    }
+}
@@ -0,0 +1,372 @@
+//! Print statistics and content of `.hollow_bvyfst` files.
+//!
+//! Since a `.hollow_bvyfst` file can only be read when knowing which
+//! components it stores, there is no general-purpose `bvyfst` binary.
+//! Instead, define your own with the same `Plugin!` as your game:
+//!
+//! ```text
+//! // src/bin/bvyfst.rs
+//! fn main() -> anyhow::Result<()> {
+//!     Plugin!(
+//!         Inline[proxy::Id<ComponentA>]
+//!         Table[proxy::Id<ComponentB>]
+//!     )
+//!     .inspect_cli()
+//! }
+//! ```
+//!
+//! See `examples/bvyfst.rs` for a complete example.
+
+use std::{fmt, io, mem, path::Path};
+
+use rkyv::Archived;
+
+use crate::entity::{Entity, Inlines, Tables};
+use crate::header::{self, Header};
+use crate::schema::{ComponentInfo, Schema, Storage};
+use crate::ArchivedFastScene;
+
+/// Statistics about a single component in a scene file.
+#[derive(Debug, Clone)]
+pub struct ComponentReport {
+    pub info: ComponentInfo,
+    /// How many entities have this component.
+    pub occupancy: usize,
+    /// How many values are stored in the table, `None` for `Inline` components.
+    pub table_len: Option<usize>,
+    /// The storage picked when saving, for `Auto` components.
+    pub auto: Option<Storage>,
+}
+
+/// How many bytes each part of a scene file occupies.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ByteReport {
+    pub header: usize,
+    /// Hierarchy information (child count) in the entity array.
+    pub hierarchy: usize,
+    /// Components in `Inline` storage, stored in the entity array.
+    pub inline: usize,
+    /// Keys to `Table`, `DedupTable` and `Columnar` values, stored in the
+    /// entity array.
+    pub keys: usize,
+    /// Values in `Table`, `DedupTable`, `Columnar` and `Auto` storage,
+    /// encoded for tables with a codec.
+    pub tables: usize,
+    /// Anything else: out-of-line data such as strings, the name index etc.
+    pub other: usize,
+}
+
+/// Statistics about a scene file, see [`Report::new`].
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub header: Header,
+    pub file_size: usize,
+    pub entity_count: usize,
+    /// The largest number of ancestors + 1 of an entity in the scene.
+    pub hierarchy_depth: u32,
+    pub components: Vec<ComponentReport>,
+    pub bytes: ByteReport,
+}
+impl Report {
+    /// Compute statistics for `scene`, the archived content of a file of
+    /// `file_size` bytes with the given `header`.
+    pub fn new<Ts: Tables, Is: Inlines>(
+        header: Header,
+        file_size: usize,
+        scene: &ArchivedFastScene<Ts, Is>,
+    ) -> Self {
+        let schema = Schema::of::<Ts, Is>();
+        let mut table_counts = vec![0; schema.tables.len()];
+        let mut inline_counts = vec![0; schema.inlines.len()];
+        let mut hierarchy_depth = 0;
+        for entity in scene.iter() {
+            let archived = &scene.entities[entity.index()];
+            archived.ref_table_keys.count(&mut table_counts);
+            scene.tables.count(entity.index(), &mut table_counts);
+            archived.inline_items.count(&mut inline_counts);
+            hierarchy_depth = hierarchy_depth.max(entity.depth() + 1);
+        }
+        let table_lens = scene.tables.lens();
+
+        let mut auto_index = 0;
+        let mut auto = |info: &ComponentInfo| {
+            let is_auto = info.storage == Storage::Auto;
+            let storage = is_auto
+                .then(|| header.auto_storage(auto_index).ok())
+                .flatten();
+            auto_index += usize::from(is_auto);
+            storage
+        };
+        let tables = schema.tables.into_iter().zip(table_counts).zip(&table_lens);
+        let tables = tables.map(|((info, occupancy), len)| ComponentReport {
+            auto: auto(&info),
+            info,
+            occupancy,
+            table_len: Some(*len),
+        });
+        let inlines = schema.inlines.into_iter().zip(inline_counts);
+        let inlines = inlines.map(|(info, occupancy)| ComponentReport {
+            info,
+            occupancy,
+            table_len: None,
+            auto: None,
+        });
+        let components: Vec<_> = tables.chain(inlines).collect();
+
+        let entity_array_len = scene.entities.len();
+        let entity_array = entity_array_len * mem::size_of::<Archived<Entity<Ts::Keys, Is>>>();
+        let inline = entity_array_len * mem::size_of::<Is::Archived>();
+        let keys = entity_array_len * mem::size_of::<Archived<Ts::Keys>>();
+        let tables = scene.tables.sizes().iter().sum::<usize>();
+        let payload = file_size - header::LEN;
+        let bytes = ByteReport {
+            header: header::LEN,
+            hierarchy: entity_array - inline - keys,
+            inline,
+            keys,
+            tables,
+            other: payload.saturating_sub(entity_array + tables),
+        };
+        Report {
+            header,
+            file_size,
+            entity_count: scene.entity_count(),
+            hierarchy_depth,
+            components,
+            bytes,
+        }
+    }
+}
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Report {
+            header, file_size, entity_count, hierarchy_depth, ..
+        } = self;
+        let percent = |count: usize| count as f64 * 100.0 / (*entity_count).max(1) as f64;
+
+        writeln!(f, "version: v{:05}", header.version)?;
+        writeln!(f, "schema fingerprint: {:016x}", header.fingerprint)?;
+        writeln!(f, "file size: {file_size} bytes")?;
+        writeln!(f, "entities: {entity_count}")?;
+        writeln!(f, "hierarchy depth: {hierarchy_depth}")?;
+        writeln!(f, "components:")?;
+        for ComponentReport { info, occupancy, table_len, auto } in &self.components {
+            let storage = info.storage.name();
+            let occupied = percent(*occupancy);
+            write!(f, "\t{storage}")?;
+            if let Some(auto) = auto {
+                write!(f, " ({})", auto.name())?;
+            }
+            if let Some(codec) = info.codec {
+                write!(f, " => {codec}")?;
+            }
+            write!(f, " {}: {occupancy} ({occupied:.1}%)", info.target)?;
+            if let Some(len) = table_len {
+                write!(f, ", table size: {len}")?;
+            }
+            writeln!(f)?;
+        }
+        let ByteReport { header, hierarchy, inline, keys, tables, other } = self.bytes;
+        writeln!(f, "bytes:")?;
+        writeln!(f, "\theader: {header}")?;
+        writeln!(f, "\thierarchy: {hierarchy}")?;
+        writeln!(f, "\tinline: {inline}")?;
+        writeln!(f, "\ttable keys: {keys}")?;
+        writeln!(f, "\ttables: {tables}")?;
+        writeln!(f, "\tother: {other}")
+    }
+}
+
+/// Write each entity of `scene` as a line of text, with the name of its
+/// components, indented by its depth in the hierarchy.
+pub fn dump<Ts: Tables, Is: Inlines>(
+    scene: &ArchivedFastScene<Ts, Is>,
+    mut out: impl io::Write,
+) -> io::Result<()> {
+    let schema = Schema::of::<Ts, Is>();
+    for entity in scene.iter() {
+        let archived = &scene.entities[entity.index()];
+        let mut table_counts = vec![0; schema.tables.len()];
+        let mut inline_counts = vec![0; schema.inlines.len()];
+        archived.ref_table_keys.count(&mut table_counts);
+        scene.tables.count(entity.index(), &mut table_counts);
+        archived.inline_items.count(&mut inline_counts);
+
+        let tables = schema.tables.iter().zip(table_counts);
+        let inlines = schema.inlines.iter().zip(inline_counts);
+        let components: Vec<_> = tables
+            .chain(inlines)
+            .filter(|(_, count)| *count != 0)
+            .map(|(info, _)| info.target)
+            .collect();
+
+        let indent = "  ".repeat(entity.depth() as usize);
+        let index = entity.index();
+        writeln!(out, "{indent}#{index}: {}", components.join(", "))?;
+    }
+    Ok(())
+}
+
+/// Read the `.hollow_bvyfst` file at `path`, and print its [`Report`].
+///
+/// If `dump_entities` is set, also print the entities, see [`dump`].
+pub fn inspect<Ts: Tables, Is: Inlines>(path: &Path, dump_entities: bool) -> anyhow::Result<()> {
+    let bytes = std::fs::read(path)?;
+    let (header, payload) = Header::read(&bytes)?;
+    println!("{}:", path.display());
+    header.check(&Schema::of::<Ts, Is>())?;
+
+    let payload = header::aligned(payload);
+    // SAFETY: the header was checked with this schema, and the file is
+    // trusted, see `ArchivedFastScene::from_payload`.
+    let scene = unsafe { ArchivedFastScene::<Ts, Is>::from_payload(&payload)? };
+    header.check_auto(&scene.tables.auto_storages()?)?;
+
+    print!("{}", Report::new(header, bytes.len(), scene));
+    if dump_entities {
+        println!("entities:");
+        dump(scene, io::stdout().lock())?;
+    }
+    Ok(())
+}
+
+/// Run the `bvyfst` command line tool for the given schema.
+///
+/// Usage: `bvyfst [--dump] <FILE>...`
+pub fn run<Ts: Tables, Is: Inlines>() -> anyhow::Result<()> {
+    let mut dump_entities = false;
+    let mut files = Vec::new();
+    for arg in std::env::args_os().skip(1) {
+        match arg.to_str() {
+            Some("--dump") => dump_entities = true,
+            Some("-h" | "--help") => {
+                println!("Usage: bvyfst [--dump] <FILE>...");
+                println!("Print statistics about .hollow_bvyfst files.");
+                println!("\t--dump: also print every entity and its components");
+                return Ok(());
+            }
+            _ => files.push(arg),
+        }
+    }
+    if files.is_empty() {
+        anyhow::bail!("No file provided. Usage: bvyfst [--dump] <FILE>...");
+    }
+    for file in &files {
+        inspect::<Ts, Is>(Path::new(file), dump_entities)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{any::type_name, mem, num::NonZeroU16};
+
+    use bevy::prelude::*;
+    use rkyv::Archived;
+
+    use super::{dump, Report};
+    use crate::header::{self, Header};
+    use crate::proxy::Id;
+    use crate::schema::{Schema, Storage};
+    use crate::{Archive, DedupTable, Deserialize, HCons, Inline, Serialize, Table};
+    use crate::{ArchivedFastScene, FastScene, SaveOptions};
+
+    #[derive(Component, Clone, Copy, Archive, Deserialize, Serialize)]
+    struct Health(u32);
+
+    #[derive(Component, Clone, Copy, PartialEq, Archive, Deserialize, Serialize)]
+    struct Team(u32);
+
+    #[derive(Component, Clone, Copy, Archive, Deserialize, Serialize)]
+    struct Level(u32);
+
+    type Tables = HCons<Table<Id<Health>>, HCons<DedupTable<Id<Team>>, ()>>;
+    type Inlines = HCons<Inline<Id<Level>>, ()>;
+
+    /// A root with a child and a grand child, and a second root.
+    fn save() -> Vec<u8> {
+        let mut world = World::new();
+        world
+            .spawn((Level(1), Health(10), Team(1)))
+            .with_children(|parent| {
+                parent.spawn((Health(20), Team(1))).with_children(|parent| {
+                    parent.spawn(Team(2));
+                });
+            });
+        world.spawn(Level(2));
+        let options = SaveOptions { deterministic: true, ..default() };
+        let mut scene = Scene::new(world);
+        let fast_scene =
+            FastScene::<Tables, Inlines>::from_bevy_filtered::<()>(&mut scene, options);
+        let mut bytes = Header::new(&Schema::of::<Tables, Inlines>())
+            .to_bytes()
+            .to_vec();
+        bytes.extend_from_slice(&rkyv::to_bytes::<_, 1024>(&fast_scene).unwrap());
+        bytes
+    }
+
+    #[test]
+    fn report() {
+        let bytes = save();
+        let (header, payload) = Header::read(&bytes).unwrap();
+        let payload = header::aligned(payload);
+        let scene = unsafe { ArchivedFastScene::<Tables, Inlines>::from_payload(&payload) };
+        let report = Report::new(header, bytes.len(), scene.unwrap());
+
+        assert_eq!(report.entity_count, 4);
+        assert_eq!(report.hierarchy_depth, 3);
+        let components: Vec<_> = report
+            .components
+            .iter()
+            .map(|c| (c.info.storage, c.occupancy, c.table_len))
+            .collect();
+        assert_eq!(
+            components,
+            [
+                (Storage::Table, 2, Some(2)),
+                (Storage::DedupTable, 3, Some(2)),
+                (Storage::Inline, 2, None),
+            ]
+        );
+
+        // The entity array has the scene root and 4 entities.
+        let bytes_report = report.bytes;
+        let key = mem::size_of::<Archived<Option<NonZeroU16>>>();
+        let inline = mem::size_of::<Archived<Option<Id<Level>>>>();
+        assert_eq!(bytes_report.header, header::LEN);
+        assert_eq!(bytes_report.hierarchy, 5 * mem::size_of::<u32>());
+        assert_eq!(bytes_report.keys, 5 * 2 * key);
+        assert_eq!(bytes_report.inline, 5 * inline);
+        // Two `Health` and two distinct `Team` values.
+        assert_eq!(bytes_report.tables, 2 * 4 + 2 * 4);
+        let entity_array = bytes_report.hierarchy + bytes_report.inline + bytes_report.keys;
+        let total = header::LEN + entity_array + bytes_report.tables + bytes_report.other;
+        assert_eq!(total, report.file_size);
+
+        let text = report.to_string();
+        assert!(text.contains("hierarchy depth: 3\n"));
+        let health = type_name::<Health>();
+        assert!(text.contains(&format!("\tTable {health}: 2 (50.0%), table size: 2\n")));
+    }
+
+    #[test]
+    fn dump_entities() {
+        let bytes = save();
+        let (_, payload) = Header::read(&bytes).unwrap();
+        let payload = header::aligned(payload);
+        let scene = unsafe { ArchivedFastScene::<Tables, Inlines>::from_payload(&payload) };
+        let mut out = Vec::new();
+        dump(scene.unwrap(), &mut out).unwrap();
+
+        let [health, team, level] = [
+            type_name::<Health>(),
+            type_name::<Team>(),
+            type_name::<Level>(),
+        ];
+        let expected = format!(
+            "#1: {health}, {team}, {level}\n  #2: {health}, {team}\n    #3: {team}\n\
+            #4: {level}\n"
+        );
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+    }
+}
@@ -3,28 +3,60 @@
 // - `entity`: Define a `rkyv`-based scene format parametrized over the kind of
 //   components to ser/deser.
 // - `entity::storage`: Define storage types to store components in [`FastScene`]
+// - `entity::storage::auto`: Pick the storage of `Auto` proxies when saving.
+// - `advise`: Recommend a storage for each proxy, given a scene.
+// - `codec`: Encode table columns in the file, decode them at load.
+// - `dynamic`: A runtime-configured alternative to `Plugin!` type lists, with
+//   the same file format.
+// - `header`: The header of `.hollow_bvyfst` files, to check a file is
+//   compatible with the current version and [`schema::Schema`].
+// - `hooks`: Run code when hollow scenes are spawned.
 // - `hierarchy`: how to load from/to a bevy hierarchy to/from a [`FastScene`].
 // - `scene`: define the [`FastScene`] struct, used to proxy bevy entities
 // - `plugin`: Define the bevy plugin
 // - `plugin::{saver,loader}`: Define bevy `AssetLoader` and `AssetSaver` for
 //   bevy's `Scene` type based on a [`FastScene`].
+// - `plugin::convert`: Convert dynamic scene files into `.hollow_bvyfst` files
+//   without running the game.
+// - `plugin::chunked`: Spawn large scenes over several frames.
+// - `plugin::reload`: Update spawned scenes in place when their file changes.
+// - `proxy`: `ArchiveProxy` helpers, and proxies for common bevy components.
+// - `required`: Insert bundles on spawned entities that have a given component.
+// - `strings`: Store each string once per scene, see `strings::InternedStr`.
+// - `names`: Index entities of a [`FastScene`] by their `Name` path.
+// - `inspect`: Print statistics and content of `.hollow_bvyfst` files.
+// - `query`: Read-only access to an archived [`FastScene`], without spawning it.
+// - `schema`: Runtime description of the components stored in a [`FastScene`].
 // - `scene`: Define [`FastScene`]. However, most of the interesting code for
 //   loading/saving the scene is in `hierarchy`. While the interesting code to
 //   convert a list of types into a serializable data structure is in `entity`.
+pub mod advise;
+pub mod codec;
+pub mod dynamic;
 mod entity;
+pub mod header;
 mod hierarchy;
+pub mod hooks;
+pub mod inspect;
+mod names;
 #[cfg(feature = "bevy_plugin")]
 mod plugin;
 pub mod proxy;
+pub mod query;
+pub mod required;
 mod scene;
+pub mod schema;
+pub mod strings;
 mod version;
 
-pub use crate::entity::ArchiveProxy;
+pub use crate::entity::{ArchiveProxy, ColumnarProxy};
+pub use hierarchy::{ChunkedSpawn, SpawnBudget};
+pub use names::EntityPaths;
 #[cfg(feature = "bevy_plugin")]
-pub use plugin::{Plugin, RkyvTypeNonsense};
+pub use plugin::{chunked, convert, reload, Plugin, RkyvTypeNonsense};
 pub use rkyv::{Archive, Deserialize, Serialize};
 
-use scene::FastScene;
+pub use scene::{ArchivedFastScene, FastScene, HollowSceneRoot, SaveOptions};
 
 /// Expose bevy types used in `Plugin!` macro to check they are correct.
 #[doc(hidden)]
@@ -32,16 +64,70 @@ pub mod __priv {
     pub use bevy::reflect::Reflect;
 }
 
+// All archived types are `repr(C)`, so that `dynamic::DynamicSchema` can
+// compute their layout at runtime.
+
+/// A node of the type lists built by `Plugin!`.
+///
+/// Unlike tuples, archived `HCons` have a defined layout.
+#[derive(Archive, Deserialize, Serialize, Default, Clone, Copy)]
+#[archive_attr(repr(C))]
+#[doc(hidden)]
+pub struct HCons<H, T>(pub H, pub T);
+
 #[derive(Archive, Deserialize, Serialize, Default)]
+#[archive_attr(repr(C))]
 #[doc(hidden)]
 pub struct Inline<C>(Option<C>);
 
 #[derive(Archive, Deserialize, Serialize)]
+#[archive_attr(repr(C))]
 #[doc(hidden)]
 pub struct Table<C> {
     table: Vec<C>,
 }
 
 #[derive(Archive, Deserialize, Serialize)]
+#[archive_attr(repr(C))]
 #[doc(hidden)]
 pub struct DedupTable<C>(pub(crate) Table<C>);
+
+#[derive(Archive, Deserialize, Serialize)]
+#[archive_attr(repr(C))]
+#[doc(hidden)]
+pub struct Columnar<C: ColumnarProxy> {
+    columns: C::Columns,
+    len: u32,
+}
+
+/// A `Table` or `DedupTable` stored with the `E` [`codec::Codec`].
+#[derive(Archive, Serialize)]
+#[archive_attr(repr(C))]
+#[doc(hidden)]
+pub struct Encoded<T, E> {
+    /// The values, only used when saving.
+    #[with(rkyv::with::Skip)]
+    table: T,
+    bytes: Vec<u8>,
+    len: u32,
+    _codec: std::marker::PhantomData<fn(E)>,
+}
+
+/// A proxy stored `Inline`, in a `Table` or in a `DedupTable`, whichever is
+/// the smallest for the scene being saved.
+#[derive(Archive, Serialize)]
+#[archive_attr(repr(C))]
+#[doc(hidden)]
+pub struct Auto<C> {
+    /// The value of each entity of the entity slice, only used when saving.
+    #[with(rkyv::with::Skip)]
+    pending: Vec<Option<C>>,
+    /// The picked `schema::Storage`, as in `header::Header::auto`.
+    storage: u8,
+    /// The value of each entity of the entity slice, when `Inline`.
+    inline: Vec<Option<C>>,
+    /// The key of each entity of the entity slice, when a table.
+    keys: Vec<Option<std::num::NonZeroU16>>,
+    /// The values `keys` point to, starting at 1.
+    values: Vec<C>,
+}
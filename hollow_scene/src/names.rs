@@ -0,0 +1,106 @@
+//! Index entities by their `Name` path, such as `Level/Doors/Door_03`.
+//!
+//! The path of an entity is the `Name` of all its ancestors and itself,
+//! separated by `/`. Entities without a `Name`, and their descendants, do not
+//! have a path.
+
+use std::alloc::Layout;
+
+use bevy::ecs::entity::{EntityMapper, MapEntities};
+use bevy::ecs::reflect::{ReflectComponent, ReflectMapEntities};
+use bevy::prelude::{Component, Entity, Name, Reflect, World};
+use bevy::utils::HashMap;
+use rkyv::{Archive, Archived, Deserialize, Serialize};
+
+use crate::dynamic::{in_bytes, str_in_bytes};
+
+/// Sorted list of `Name` paths and the index of the entity they point to.
+#[derive(Clone, Default, Archive, Deserialize, Serialize)]
+pub struct NameIndex {
+    paths: Vec<(String, u32)>,
+}
+impl NameIndex {
+    /// Index all named entities of an entity slice.
+    ///
+    /// `children` is the descendant count of each element of the slice, and
+    /// `ids` their bevy `Entity` in `world`, as returned by
+    /// [`crate::hierarchy::build`].
+    pub(crate) fn new(world: &World, children: &[u32], ids: &[Entity]) -> Self {
+        let mut paths = Vec::new();
+        // `(path, one past last descendant)` of the ancestors of current entity.
+        let mut ancestors: Vec<(Option<String>, usize)> = Vec::new();
+
+        // The first entity is the scene root, it never has a name.
+        for (index, (children, id)) in children.iter().zip(ids).enumerate().skip(1) {
+            while matches!(ancestors.last(), Some(&(_, end)) if end <= index) {
+                ancestors.pop();
+            }
+            let name = world.get::<Name>(*id).map(Name::as_str);
+            let path = match (ancestors.last(), name) {
+                (None, Some(name)) => Some(name.to_string()),
+                (Some((Some(parent), _)), Some(name)) => Some(format!("{parent}/{name}")),
+                _ => None,
+            };
+            if let Some(path) = &path {
+                paths.push((path.clone(), index as u32));
+            }
+            ancestors.push((path, index + 1 + *children as usize));
+        }
+        // When several entities have the same path, keep the first one.
+        paths.sort_by(|(left, _), (right, _)| left.cmp(right));
+        paths.dedup_by(|(left, _), (right, _)| left == right);
+        NameIndex { paths }
+    }
+}
+impl ArchivedNameIndex {
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+    /// The index of the entity at `path`, if any.
+    pub fn get(&self, path: &str) -> Option<usize> {
+        let found = self.paths.binary_search_by(|(p, _)| p.as_str().cmp(path));
+        found.ok().map(|i| self.paths[i].1 as usize)
+    }
+    /// All paths and the index of the entity they point to, sorted by path.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, usize)> + '_ {
+        self.paths.iter().map(|(p, i)| (p.as_str(), *i as usize))
+    }
+    /// Whether the paths of this index, which is within `bytes`, are all
+    /// within `bytes` and point to one of the `entity_count` entities.
+    pub(crate) fn check(&self, bytes: &[u8], entity_count: usize) -> bool {
+        let ptr = self.paths.as_ptr().cast();
+        let layout = Layout::new::<Archived<(String, u32)>>();
+        let valid = |(path, index): &Archived<(String, u32)>| {
+            str_in_bytes(bytes, path) && (*index as usize) < entity_count
+        };
+        in_bytes(bytes, ptr, layout, self.paths.len()) && self.paths.iter().all(valid)
+    }
+    /// The [`EntityPaths`] of a scene spawned with `ids` as entities.
+    pub(crate) fn to_component(&self, ids: &[Entity]) -> EntityPaths {
+        let paths = self.iter().map(|(p, i)| (p.to_string(), ids[i]));
+        EntityPaths { paths: paths.collect() }
+    }
+}
+
+/// The spawned `Entity` of each `Name` path in a hollow scene.
+///
+/// This is added to the root entity of the scene if it was saved with
+/// [`crate::SaveOptions::name_index`].
+#[derive(Component, Reflect, Clone, Debug, Default)]
+#[reflect(Component, MapEntities)]
+pub struct EntityPaths {
+    paths: HashMap<String, Entity>,
+}
+impl EntityPaths {
+    /// The entity at `path`, if any.
+    pub fn get(&self, path: &str) -> Option<Entity> {
+        self.paths.get(path).copied()
+    }
+}
+impl MapEntities for EntityPaths {
+    fn map_entities(&mut self, entity_mapper: &mut EntityMapper) {
+        for entity in self.paths.values_mut() {
+            *entity = entity_mapper.get_or_reserve(*entity);
+        }
+    }
+}
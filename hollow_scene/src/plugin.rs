@@ -1,19 +1,29 @@
 //! Define the bevy plugin
 
+pub mod chunked;
+pub mod convert;
+#[cfg(feature = "gltf")]
+pub mod extras;
 mod loader;
 mod processor;
+pub mod reload;
 mod saver;
 
-use std::marker::PhantomData;
-
-use ::bevy::prelude::AssetApp;
+use ::bevy::ecs::query::ReadOnlyWorldQuery;
+use ::bevy::ecs::world::EntityMut;
+use ::bevy::prelude::{AssetApp, FromWorld};
 use bevy::prelude as bevy;
 use rkyv::ser::serializers::{
     AlignedSerializer, AllocScratch, CompositeSerializer, FallbackScratch, HeapScratch,
     SharedSerializeMap,
 };
 
-use crate::entity::{Inlines, Tables};
+use crate::advise::{Advice, Advise};
+use crate::entity::{Inlines, ParTables, TableStorage, Tables};
+use crate::hooks::{HollowSceneSpawned, PluginHooks, SpawnHooks};
+use crate::required::RequiredBundles;
+use crate::schema::Schema;
+use crate::{EntityPaths, HollowSceneRoot};
 
 /// Initialize the fast scene [`Plugin`]
 ///
@@ -22,8 +32,10 @@ use crate::entity::{Inlines, Tables};
 /// ```text
 /// Plugin!(
 ///    Inline[<ty>,*]
-///    DedupTable[<ty>,*]
-///    Table[<ty>,*]
+///    DedupTable[<ty> (=> <codec>)?,*]
+///    Table[<ty> (=> <codec>)?,*]
+///    Columnar[<ty>,*]
+///    Auto[<ty>,*]
 ///    Extras[]
 /// );
 /// ```
@@ -31,7 +43,7 @@ use crate::entity::{Inlines, Tables};
 /// The order is important, and `Extras` is indeed always followed by an empty
 /// list (it isn't implemented yet).
 ///
-/// `Plugin!` accepts four storage types, and each storage types holds specific
+/// `Plugin!` accepts six storage types, and each storage types holds specific
 /// [`crate::ArchiveProxy`], things that read and write to components.
 ///
 /// Consider the file format as an array of entities. Each entity contains
@@ -58,43 +70,99 @@ use crate::entity::{Inlines, Tables};
 ///   \
 ///   Note that this is a O(n²) operation at save-time, with `n` the number of
 ///   distinct components (typically this is O(n) for zero-sized types)
+/// - `Columnar`: Same as `Table`, except the values are split into one array
+///   per field, see [`crate::ColumnarProxy`]. Use this for numeric components
+///   such as transforms, arrays of a single field compress much better.
+///   \
+///   Values in `Columnar` storage can't be read with [`crate::query`].
+/// - `Auto`: Stored as `Inline`, `Table` or `DedupTable`, whichever is the
+///   smallest for the scene being saved, picked again for each file. The
+///   values are never in the entity array, entities with an `Auto`
+///   component are found by their index. The picked storage is recorded in
+///   the file header, see [`crate::header::Header::auto`].
+///   \
+///   There can't be more than 16 `Auto` proxies, and values in `Auto`
+///   storage can't be read with [`crate::query`].
 /// - `Extras`: **NOT IMPLEMENTED**
+///
+/// To fill tables in parallel when saving, see
+/// [`crate::SaveOptions::parallel`], call [`Plugin::with_parallel_save`]. It
+/// requires the proxies in `Table`, `DedupTable` and `Auto` storage, and the
+/// columns of `Columnar` proxies, to be `Send`.
+///
+/// Proxies in `Table` and `DedupTable` storage can be followed by
+/// `=> <codec>` to encode their table in the file, for example
+/// `Table[Id<Position> => codec::Delta]`. See [`crate::codec`].
 #[macro_export]
 macro_rules! Plugin {
     (
         $(  Inline[$( $inline:ty ),* $(,)?] $(,)?  )?
-        $(  DedupTable[$( $dedup_table:ty ),* $(,)?] $(,)?  )?
-        $(  Table[$( $table:ty ),* $(,)?] $(,)?  )?
+        $(  DedupTable[$( $dedup_table:ty $(=> $dedup_codec:ty)? ),* $(,)?] $(,)?  )?
+        $(  Table[$( $table:ty $(=> $table_codec:ty)? ),* $(,)?] $(,)?  )?
+        $(  Columnar[$( $columnar:ty ),* $(,)?] $(,)?  )?
+        $(  Auto[$( $auto:ty ),* $(,)?] $(,)?  )?
         $(  Extras[]  )?
     ) => {{
         fn is_proxy<T: $crate::ArchiveProxy>() {}
         fn is_reflect<T: $crate::ArchiveProxy>() where T::Target: $crate::__priv::Reflect {}
         fn is_partial_eq<T: $crate::ArchiveProxy>() where T: ::core::cmp::PartialEq<T::Target> {}
+        fn is_columnar<T: $crate::ColumnarProxy>() where T::Target: ::core::clone::Clone {}
+        fn is_words<T: $crate::codec::Words>() {}
+        fn is_codec<T: $crate::codec::Codec>() {}
 
         $(  $(is_proxy::<$table>();)*  )?
         $(  $(is_proxy::<$dedup_table>();)*  )?
         $(  $(is_proxy::<$inline>();)*  )?
+        $(  $(is_columnar::<$columnar>();)*  )?
+        $(  $(is_proxy::<$auto>();)*  )?
         $(  $(is_reflect::<$table>();)*  )?
         $(  $(is_reflect::<$dedup_table>();)*  )?
         $(  $(is_reflect::<$inline>();)*  )?
+        $(  $(is_reflect::<$columnar>();)*  )?
+        $(  $(is_reflect::<$auto>();)*  )?
         $(  $(is_partial_eq::<$dedup_table>();)*  )?
+        $(  $( $(is_words::<$table>(); is_codec::<$table_codec>();)? )*  )?
+        $(  $( $(is_words::<$dedup_table>(); is_codec::<$dedup_codec>();)? )*  )?
 
         $crate::Plugin::<
-            Plugin![@table [ $( $($table,)* )? ] Plugin![@dedup $($($dedup_table,)*)?]],
+            Plugin![@table [ $( $( ($table $(, $table_codec)?) )* )? ]
+                Plugin![@dedup [ $( $( ($dedup_table $(, $dedup_codec)?) )* )? ]
+                    Plugin![@columnar [ $($($columnar,)*)? ]
+                        Plugin![@auto $($($auto,)*)?]]]],
             Plugin![@inline $( $($inline,)* )?],
         >::IGNORE_THIS_ERROR_you_are_seeing_this_because_one_of_the_types_you_used_as_argument_to_Plugin_wasnt_valid___check_the_earlier_errors_to_know_which_ones()
     }};
     (@inline ) => { () };
     (@inline $head:ty, $($tail:ty,)*) => {
-        ($crate::Inline<$head>, Plugin!(@inline $($tail,)*) )
+        $crate::HCons<$crate::Inline<$head>, Plugin!(@inline $($tail,)*)>
     };
     (@table [] $tail:ty ) => { $tail };
-    (@table [ $head:ty, $($tail:ty,)* ] $remaining:ty) => {
-        ($crate::Table<$head>, Plugin!(@table [$($tail,)*] $remaining))
+    (@table [ ($head:ty) $($tail:tt)* ] $remaining:ty) => {
+        $crate::HCons<$crate::Table<$head>, Plugin!(@table [$($tail)*] $remaining)>
+    };
+    (@table [ ($head:ty, $codec:ty) $($tail:tt)* ] $remaining:ty) => {
+        $crate::HCons<
+            $crate::Encoded<$crate::Table<$head>, $codec>,
+            Plugin!(@table [$($tail)*] $remaining),
+        >
+    };
+    (@dedup [] $tail:ty ) => { $tail };
+    (@dedup [ ($head:ty) $($tail:tt)* ] $remaining:ty) => {
+        $crate::HCons<$crate::DedupTable<$head>, Plugin!(@dedup [$($tail)*] $remaining)>
     };
-    (@dedup ) => { () };
-    (@dedup $head:ty, $($tail:ty,)*) => {
-        ($crate::DedupTable<$head>, Plugin!(@dedup $($tail,)*) )
+    (@dedup [ ($head:ty, $codec:ty) $($tail:tt)* ] $remaining:ty) => {
+        $crate::HCons<
+            $crate::Encoded<$crate::DedupTable<$head>, $codec>,
+            Plugin!(@dedup [$($tail)*] $remaining),
+        >
+    };
+    (@columnar [] $tail:ty ) => { $tail };
+    (@columnar [ $head:ty, $($tail:ty,)* ] $remaining:ty) => {
+        $crate::HCons<$crate::Columnar<$head>, Plugin!(@columnar [$($tail,)*] $remaining)>
+    };
+    (@auto ) => { () };
+    (@auto $head:ty, $($tail:ty,)*) => {
+        $crate::HCons<$crate::Auto<$head>, Plugin!(@auto $($tail,)*)>
     };
 }
 
@@ -125,7 +193,14 @@ impl<T> RkyvTypeNonsense for T where
 /// You muse use the [`Plugin!`] macro to create an instance of this plugin.
 /// It provides enhanced error messages, and constructs transparently the inane
 /// nonsense of a type parameter you need to specify to get it working.
-pub struct Plugin<Ts, Is>(PhantomData<fn(Ts, Is)>);
+pub struct Plugin<Ts: Tables, Is: Inlines> {
+    from_bevy: saver::FromBevy<Ts, Is>,
+    name: Option<&'static str>,
+    hooks: SpawnHooks,
+    required: RequiredBundles,
+    #[cfg(feature = "gltf")]
+    extras: extras::ExtrasMappers,
+}
 
 impl<Ts: Tables + 'static, Is: Inlines + 'static> Plugin<Ts, Is>
 where
@@ -137,7 +212,139 @@ where
     #[allow(non_snake_case)]
     pub fn IGNORE_THIS_ERROR_you_are_seeing_this_because_one_of_the_types_you_used_as_argument_to_Plugin_wasnt_valid___check_the_earlier_errors_to_know_which_ones(
     ) -> Self {
-        Plugin(PhantomData)
+        Plugin {
+            from_bevy: Default::default(),
+            name: None,
+            hooks: Default::default(),
+            required: Default::default(),
+            #[cfg(feature = "gltf")]
+            extras: Default::default(),
+        }
+    }
+    /// Only save entities matching the `F` query filter.
+    ///
+    /// Entities not matching `F` are skipped together with their descendants.
+    /// Typical filters are `With<Marker>` to only save marked entities, or
+    /// `Without<Marker>` to skip editor helpers, debug gizmos etc.
+    pub fn with_filter<F: ReadOnlyWorldQuery + 'static>(mut self) -> Self {
+        self.from_bevy = self.from_bevy.filtered::<F>();
+        self
+    }
+    /// Fill each table in a separate task when saving with
+    /// [`crate::SaveOptions::parallel`].
+    ///
+    /// Without this, `parallel` is ignored. This requires the proxies in
+    /// `Table`, `DedupTable` and `Auto` storage, and the columns of
+    /// `Columnar` proxies, to be `Send`.
+    pub fn with_parallel_save(mut self) -> Self
+    where
+        Ts: ParTables,
+    {
+        self.from_bevy.par_insert = Some(TableStorage::par_insert_values);
+        self
+    }
+    /// Use `.{name}.hollow_bvyfst` files, and process `.{name}.myscn.ron`
+    /// scenes (and `.{name}.gltf` with the `gltf` feature) with this plugin.
+    ///
+    /// Use this to add several `Plugin!` to the same app, for example one for
+    /// levels and one for UI layouts. Files with the plain `.hollow_bvyfst`
+    /// extension are still read with the schema they were saved with, whatever
+    /// the plugin bevy picks to load them.
+    pub fn with_name(mut self, name: &'static str) -> Self {
+        self.name = Some(name);
+        self
+    }
+    /// Call `hook` on spawned entities of scenes saved with this plugin's
+    /// schema when `C` is inserted.
+    ///
+    /// See [`crate::hooks`] for details.
+    pub fn on_spawn<C: bevy::Component>(
+        mut self,
+        hook: impl Fn(&mut EntityMut) + Send + Sync + 'static,
+    ) -> Self {
+        self.hooks.add::<C>(hook);
+        self
+    }
+    /// When a spawned entity has `C`, also insert `B::default()`.
+    ///
+    /// See [`crate::required`] for details.
+    pub fn with_required<C: bevy::Component, B: bevy::Bundle + Default>(mut self) -> Self {
+        self.required.add::<C, B>();
+        self
+    }
+    /// Insert `B::default()` on the scene root entities, instead of a
+    /// `SpatialBundle`.
+    ///
+    /// See [`crate::required`] for details.
+    pub fn with_root_bundle<B: bevy::Bundle + Default>(mut self) -> Self {
+        self.required.set_root::<B>();
+        self
+    }
+    /// The extension of `.hollow_bvyfst` files saved with this plugin.
+    fn extension(&self) -> String {
+        match self.name {
+            Some(name) => format!("{name}.hollow_bvyfst"),
+            None => "hollow_bvyfst".to_string(),
+        }
+    }
+    /// The required bundles of scenes loaded with the default loader settings,
+    /// their components don't need to be saved.
+    fn loaded_required(&self) -> RequiredBundles {
+        loader::LoaderSettings::default().required(&self.required)
+    }
+    /// Call `mapper` on glTF nodes with a `key` custom property when
+    /// converting glTF scenes.
+    ///
+    /// See [`extras`] for details.
+    #[cfg(feature = "gltf")]
+    pub fn with_extras_mapper(
+        mut self,
+        key: impl Into<String>,
+        mapper: impl Fn(&serde_json::Value, &mut EntityMut) + Send + Sync + 'static,
+    ) -> Self {
+        self.extras.add(key, mapper);
+        self
+    }
+    /// Run the `bvyfst` command line tool to inspect `.hollow_bvyfst` files
+    /// saved with this plugin.
+    ///
+    /// See [`crate::inspect`] for details.
+    pub fn inspect_cli(self) -> anyhow::Result<()> {
+        crate::inspect::run::<Ts, Is>()
+    }
+    /// The storage recommended for each proxy of this plugin, given the
+    /// entities of `world`.
+    ///
+    /// See [`crate::advise`] for details.
+    pub fn advise(&self, world: &bevy::World) -> Advice
+    where
+        Ts: Advise,
+        Is: Advise,
+    {
+        Advice::new::<Ts, Is>(world)
+    }
+    /// A [`convert::Converter`] to turn dynamic scene files into
+    /// `.hollow_bvyfst` files without running the game.
+    ///
+    /// `registry` must contain all the components of the converted scenes.
+    pub fn converter(&self, registry: bevy::AppTypeRegistry) -> convert::Converter<Ts, Is> {
+        let options = Default::default();
+        let extension = self.extension();
+        convert::Converter {
+            registry,
+            from_bevy: self.from_bevy,
+            required: self.loaded_required(),
+            extension,
+            options,
+        }
+    }
+    /// Run the scene conversion command line tool, see [`convert::Converter::run`].
+    pub fn convert_cli(self, registry: bevy::AppTypeRegistry) -> anyhow::Result<()>
+    where
+        Ts: Advise,
+        Is: Advise,
+    {
+        self.converter(registry).run(std::env::args_os().skip(1))
     }
 }
 
@@ -148,8 +355,32 @@ where
     Is: RkyvTypeNonsense,
 {
     fn build(&self, app: &mut bevy::App) {
+        let world = &mut app.world;
+        let mut hooks = world.get_resource_or_insert_with(PluginHooks::default);
+        hooks.add(Schema::of::<Ts, Is>().fingerprint(), &self.hooks);
+
+        let schemas = world.get_resource_or_insert_with(loader::Schemas::default);
+        schemas.add::<Ts, Is>();
+
+        let mut loader = loader::Loader::<Ts, Is>::from_world(world);
+        loader.extensions = loader::extensions(self.name);
+        loader.required = self.required.clone();
+        #[cfg(feature = "gltf")]
+        loader.extras.extend(&self.extras);
         app.init_asset::<bevy::Scene>()
-            .init_asset_loader::<loader::Loader<Ts, Is>>();
-        processor::insert::<Ts, Is>(&mut app.world);
+            .init_asset::<chunked::HollowArchive>()
+            .register_type::<EntityPaths>()
+            .register_type::<HollowSceneRoot>()
+            .add_event::<HollowSceneSpawned>()
+            .register_asset_loader(loader)
+            .add_systems(bevy::Update, chunked::spawn_chunks::<Ts, Is>);
+        let required = self.loaded_required();
+        processor::insert::<Ts, Is>(&mut app.world, self.from_bevy, required, self.name);
+
+        // Several plugins may be added, but scenes should only be reloaded once.
+        if !app.world.contains_resource::<reload::Snapshots>() {
+            app.init_resource::<reload::Snapshots>()
+                .add_systems(bevy::Update, reload::reload_scenes);
+        }
     }
 }
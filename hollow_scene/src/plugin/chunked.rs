@@ -0,0 +1,152 @@
+//! Spawn large hollow scenes over several frames.
+//!
+//! Spawning a scene with bevy's `SceneBundle` spawns all its entities in a
+//! single frame. For scenes with tens of thousands of entities, this causes
+//! a visible hitch. Instead, add a [`ChunkedScene`] component to an entity,
+//! the scene is spawned as children of this entity a chunk at a time, within
+//! a per-frame [`SpawnBudget`]:
+//!
+//! ```text
+//! commands.spawn((
+//!     ChunkedScene::new(asset_server.load("level.hollow_bvyfst#Archive")),
+//!     SpatialBundle::default(),
+//! ));
+//! ```
+//!
+//! `.hollow_bvyfst` scenes loaded with the `chunked` [`LoaderSettings`] have
+//! a `#Archive` labeled [`HollowArchive`] asset, the raw data of the file.
+//! Set it in the `.meta` file of the scene, or set the `chunked`
+//! [`SaveOptions`] of the saver for processed scenes.
+//!
+//! The [`SpawnProgress`] component is added to the entity and updated after
+//! each chunk. Parents are always spawned before their children.
+//! [`HollowSceneSpawned`] is sent once the whole scene is spawned.
+//!
+//! [`LoaderSettings`]: super::loader::LoaderSettings
+//! [`SaveOptions`]: crate::SaveOptions
+
+use bevy::asset::{Asset, Assets, Handle};
+use bevy::prelude::{Component, Entity, Local, Mut, Resource, World};
+use bevy::reflect::TypePath;
+
+use crate::entity::{Inlines, Tables};
+use crate::hooks::{HollowSceneSpawned, PluginHooks};
+use crate::required::RequiredBundles;
+use crate::{schema::Schema, ArchivedFastScene, ChunkedSpawn, SpawnBudget};
+
+/// The label of the [`HollowArchive`] of `.hollow_bvyfst` scenes.
+pub const LABEL: &str = "Archive";
+
+/// The content of a `.hollow_bvyfst` file, without the header.
+#[derive(Asset, TypePath)]
+pub struct HollowArchive {
+    pub(super) fingerprint: u64,
+    pub(super) payload: rkyv::AlignedVec,
+    /// The required bundles of the file's loader settings and plugin.
+    pub(super) required: RequiredBundles,
+}
+
+/// Spawn a scene as children of this entity over several frames, see the
+/// [module docs](self).
+#[derive(Component, Clone, Debug)]
+pub struct ChunkedScene {
+    pub archive: Handle<HollowArchive>,
+    /// How much to spawn each frame.
+    pub budget: SpawnBudget,
+}
+impl ChunkedScene {
+    /// Spawn `archive` with the default [`SpawnBudget`].
+    pub fn new(archive: Handle<HollowArchive>) -> Self {
+        ChunkedScene { archive, budget: SpawnBudget::default() }
+    }
+}
+
+/// How many entities of a [`ChunkedScene`] are spawned.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SpawnProgress {
+    pub spawned: usize,
+    pub total: usize,
+}
+impl SpawnProgress {
+    /// Whether all the entities of the scene are spawned.
+    pub fn is_done(&self) -> bool {
+        self.spawned == self.total
+    }
+}
+
+/// The state of a [`ChunkedScene`] still being spawned.
+#[derive(Component)]
+struct Spawning(ChunkedSpawn);
+
+/// Spawn a chunk of each [`ChunkedScene`] saved with the `Ts` and `Is` schema.
+pub(super) fn spawn_chunks<Ts: Tables + 'static, Is: Inlines + 'static>(
+    world: &mut World,
+    mut fingerprint: Local<Option<u64>>,
+) {
+    // Computing the schema hashes all the type names, only do it once.
+    let fingerprint = *fingerprint.get_or_insert_with(|| Schema::of::<Ts, Is>().fingerprint());
+
+    let mut scenes = world.query::<(Entity, &ChunkedScene, Option<&SpawnProgress>)>();
+    let scenes: Vec<_> = scenes
+        .iter(world)
+        .filter(|(.., progress)| !progress.map_or(false, SpawnProgress::is_done))
+        .map(|(entity, scene, _)| (entity, scene.archive.id(), scene.budget))
+        .collect();
+
+    let hooks = world.get_resource::<PluginHooks>();
+    let hooks = hooks.and_then(|h| h.get(fingerprint)).cloned();
+
+    world.resource_scope(|world, archives: Mut<Assets<HollowArchive>>| {
+        for (root, archive, budget) in scenes {
+            let Some(archive) = archives.get(archive) else {
+                continue;
+            };
+            // Another plugin's schema, it spawns this scene.
+            if archive.fingerprint != fingerprint {
+                continue;
+            }
+            // SAFETY: the loader checked the header with this schema, and the
+            // file is trusted, see `ArchivedFastScene::from_payload`.
+            let archived = unsafe { ArchivedFastScene::<Ts, Is>::from_payload(&archive.payload) };
+            // unwrap: the loader already read the root from this payload
+            let archived = archived.unwrap();
+
+            let spawning = world.entity_mut(root).take::<Spawning>();
+            let mut state = spawning.map_or_else(|| archived.chunked_spawn(root), |s| s.0);
+            let required = Some(archive.required.clone());
+            let done = with_resource(world, hooks.clone(), |world| {
+                with_resource(world, required, |world| {
+                    archived.spawn_chunk(world, &mut state, budget)
+                })
+            });
+
+            let progress = SpawnProgress { spawned: state.spawned(), total: state.total() };
+            world.entity_mut(root).insert(progress);
+            if done {
+                let entity_map = state.into_ids();
+                world.send_event(HollowSceneSpawned { root, entity_map });
+            } else {
+                world.entity_mut(root).insert(Spawning(state));
+            }
+        }
+    });
+}
+
+/// Run `f` with `resource` in `world`, if any, then restore the previous one.
+fn with_resource<R: Resource, T>(
+    world: &mut World,
+    resource: Option<R>,
+    f: impl FnOnce(&mut World) -> T,
+) -> T {
+    let Some(resource) = resource else {
+        return f(world);
+    };
+    let previous = world.remove_resource::<R>();
+    world.insert_resource(resource);
+    let ret = f(world);
+    world.remove_resource::<R>();
+    if let Some(previous) = previous {
+        world.insert_resource(previous);
+    }
+    ret
+}
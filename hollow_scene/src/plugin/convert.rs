@@ -0,0 +1,449 @@
+//! Convert dynamic scenes from and into `.hollow_bvyfst` files without running
+//! the game.
+//!
+//! Like the `bvyfst` inspection tool (see [`crate::inspect`]), the converter
+//! needs to know the `Plugin!` schema, and the type registry should contain
+//! all components found in the converted scenes. Define your own binary:
+//!
+//! ```text
+//! // src/bin/convert_scenes.rs
+//! fn main() -> anyhow::Result<()> {
+//!     let mut app = App::new();
+//!     app.add_plugin(TypeRegistrationPlugin)
+//!         .add_plugin(TransformPlugin)
+//!         .add_plugin(HierarchyPlugin)
+//!         .register_type::<ComponentA>();
+//!     let registry = app.world.resource::<AppTypeRegistry>().clone();
+//!
+//!     Plugin!(Inline[proxy::Id<ComponentA>]).convert_cli(registry)
+//! }
+//! ```
+//!
+//! Note that dynamic scene files refer to components by their full type path,
+//! including the crate name. The components must be defined in the crate the
+//! scenes were saved from (or one of its dependencies) for them to be found.
+//!
+//! The converter can also do the opposite: export `.hollow_bvyfst` files back
+//! into `.scn.ron` dynamic scenes, with [`Converter::export_file`] or the
+//! `--export` command line flag.
+
+use std::{any, ffi::OsString, fmt, fs, path::Path, path::PathBuf};
+
+use anyhow::Context;
+use bevy::{
+    prelude::{AppTypeRegistry, Children, Parent, Scene, World},
+    scene::serde::SceneDeserializer,
+    tasks::{ComputeTaskPool, TaskPool},
+};
+use serde::de::DeserializeSeed;
+
+use super::{saver, RkyvTypeNonsense};
+use crate::advise::{Advice, Advise};
+use crate::entity::{Inlines, Tables};
+use crate::header::{self, Header};
+use crate::required::RequiredBundles;
+use crate::schema::Schema;
+use crate::{ArchivedFastScene, EntityPaths, HollowSceneRoot, SaveOptions};
+
+/// File name suffixes of dynamic scenes, converted by [`Converter::convert_dir`].
+pub const SCENE_EXTENSIONS: &[&str] = &["scn", "scn.ron", "myscn", "myscn.ron"];
+
+/// File name suffixes of hollow scenes, exported by [`Converter::export_dir`].
+pub const HOLLOW_EXTENSIONS: &[&str] = &["hollow_bvyfst"];
+
+/// A component of the source scene that isn't in the `Plugin!` schema, and
+/// therefore isn't saved in the converted file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LostComponent {
+    pub name: String,
+    /// How many entities had this component.
+    pub count: usize,
+}
+impl fmt::Display for LostComponent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({} entities)", self.name, self.count)
+    }
+}
+
+/// The result of converting a single scene file, see [`Converter::convert_file`]
+/// and [`Converter::export_file`].
+#[derive(Debug, Clone)]
+pub struct Conversion {
+    pub output: PathBuf,
+    pub size_before: usize,
+    pub size_after: usize,
+    /// Always empty when exporting, components not registered in the type
+    /// registry are silently skipped.
+    pub lost: Vec<LostComponent>,
+}
+
+/// Convert dynamic scene files into `.hollow_bvyfst` files, and back.
+///
+/// Create one with [`crate::Plugin::converter`].
+pub struct Converter<Ts: Tables, Is: Inlines> {
+    pub(super) registry: AppTypeRegistry,
+    pub(super) from_bevy: saver::FromBevy<Ts, Is>,
+    /// The required bundles of the plugin, with the default built-in ones.
+    pub(super) required: RequiredBundles,
+    /// Extension of converted files, see [`crate::Plugin::with_name`].
+    pub(super) extension: String,
+    /// Settings used when saving the converted scenes.
+    pub options: SaveOptions,
+}
+impl<Ts: Tables, Is: Inlines> Converter<Ts, Is>
+where
+    Ts::Keys: RkyvTypeNonsense,
+    Ts: RkyvTypeNonsense,
+    Is: RkyvTypeNonsense,
+{
+    /// Convert the dynamic scene at `input`, writing the result at `output`.
+    ///
+    /// With [`SaveOptions::strict`], fails without writing anything if the
+    /// scene has components not in the `Plugin!` schema.
+    pub fn convert_file(&self, input: &Path, output: &Path) -> anyhow::Result<Conversion> {
+        let source = fs::read(input)?;
+        let mut scene = self.read_scene(&source)?;
+        let lost = lost_components(&scene.world, &Schema::of::<Ts, Is>(), &self.required);
+        saver::check_lost(&lost, self.options)?;
+
+        let bytes = saver::to_bytes(&mut scene, self.options, self.from_bevy)?;
+        write(output, &bytes)?;
+        Ok(Conversion {
+            output: output.to_path_buf(),
+            size_before: source.len(),
+            size_after: bytes.len(),
+            lost,
+        })
+    }
+    /// The storage recommended for each proxy, given the dynamic scene at
+    /// `input`. See [`crate::advise`].
+    pub fn advise_file(&self, input: &Path) -> anyhow::Result<Advice>
+    where
+        Ts: Advise,
+        Is: Advise,
+    {
+        let scene = self.read_scene(&fs::read(input)?)?;
+        Ok(Advice::new::<Ts, Is>(&scene.world))
+    }
+    /// Export the `.hollow_bvyfst` file at `input` as a RON dynamic scene
+    /// at `output`.
+    ///
+    /// The hierarchy is preserved if `Parent` and `Children` are registered.
+    pub fn export_file(&self, input: &Path, output: &Path) -> anyhow::Result<Conversion> {
+        let source = fs::read(input)?;
+        let (header, payload) = Header::read(&source)?;
+        header.check(&Schema::of::<Ts, Is>())?;
+        let payload = header::aligned(payload);
+        // SAFETY: the header was checked with this schema, and the file is
+        // trusted, see `ArchivedFastScene::from_payload`.
+        let scene = unsafe { ArchivedFastScene::<Ts, Is>::from_payload(&payload)? };
+        header.check_auto(&scene.tables.auto_storages()?)?;
+
+        let ron = scene
+            .to_dynamic_scene(&self.registry)
+            .serialize_ron(&self.registry)?;
+        write(output, ron.as_bytes())?;
+        Ok(Conversion {
+            output: output.to_path_buf(),
+            size_before: source.len(),
+            size_after: ron.len(),
+            lost: Vec::new(),
+        })
+    }
+    /// Convert all dynamic scenes in `input` and its subdirectories.
+    ///
+    /// The converted files are written in `output` at the same relative path
+    /// as the source file, with the `hollow_bvyfst` extension (prefixed by the
+    /// plugin name if any). Files are converted in alphabetical order.
+    pub fn convert_dir(&self, input: &Path, output: &Path) -> DirResult {
+        for_each_scene(
+            input,
+            output,
+            SCENE_EXTENSIONS,
+            &self.extension,
+            |input, output| self.convert_file(input, output),
+        )
+    }
+    /// Export all hollow scenes in `input` and its subdirectories.
+    ///
+    /// The exported files are written in `output` at the same relative path
+    /// as the source file, with the `scn.ron` extension.
+    ///
+    /// Returns an error if `input` and `output` are the same directory, since
+    /// this would overwrite the `.scn.ron` files the hollow scenes were
+    /// created from.
+    pub fn export_dir(&self, input: &Path, output: &Path) -> DirResult {
+        if same_dir(input, output) {
+            anyhow::bail!(
+                "Refusing to export {} in itself, this would overwrite the \
+                source .scn.ron files, specify a different OUTPUT_DIR",
+                input.display()
+            );
+        }
+        for_each_scene(
+            input,
+            output,
+            HOLLOW_EXTENSIONS,
+            "scn.ron",
+            |input, output| self.export_file(input, output),
+        )
+    }
+    fn read_scene(&self, source: &[u8]) -> anyhow::Result<Scene> {
+        let mut deserializer = ron::de::Deserializer::from_bytes(source)?;
+        let scene_deserializer = SceneDeserializer { type_registry: &self.registry.read() };
+        let dynamic_scene = scene_deserializer
+            .deserialize(&mut deserializer)
+            .map_err(|e| deserializer.span_error(e))?;
+        Ok(Scene::from_dynamic_scene(&dynamic_scene, &self.registry)?)
+    }
+    /// Run the scene conversion command line tool with the given `args`,
+    /// not including the binary name.
+    ///
+    /// Usage: `[--export] [--advise] [--deterministic] [--name-index] [--parallel] [--strict] <INPUT_DIR> [OUTPUT_DIR]`
+    ///
+    /// When `OUTPUT_DIR` is not specified, converted files are written next
+    /// to their source. Returns an error if any file failed to convert.
+    /// `OUTPUT_DIR` is required with `--export`, see [`Self::export_dir`].
+    ///
+    /// With `--advise`, nothing is written, the [`Advice`] for each dynamic
+    /// scene in `INPUT_DIR` is printed instead.
+    pub fn run(mut self, args: impl IntoIterator<Item = OsString>) -> anyhow::Result<()>
+    where
+        Ts: Advise,
+        Is: Advise,
+    {
+        const USAGE: &str = "Usage: [--export] [--advise] [--deterministic] [--name-index] \
+            [--parallel] [--strict] <INPUT_DIR> [OUTPUT_DIR]";
+        let mut export = false;
+        let mut advise = false;
+        let mut dirs = Vec::new();
+        for arg in args {
+            match arg.to_str() {
+                Some("--export") => export = true,
+                Some("--advise") => advise = true,
+                Some("--deterministic") => self.options.deterministic = true,
+                Some("--name-index") => self.options.name_index = true,
+                Some("--parallel") => {
+                    // The converter runs outside of an `App`, so nothing else
+                    // creates the pool.
+                    ComputeTaskPool::init(TaskPool::default);
+                    self.options.parallel = true;
+                }
+                Some("--strict") => self.options.strict = true,
+                Some("-h" | "--help") => {
+                    println!("{USAGE}");
+                    println!("Convert dynamic scenes into .hollow_bvyfst files.");
+                    println!("\t--export: convert .hollow_bvyfst files into .scn.ron instead");
+                    println!("\t--advise: print the recommended storage of each proxy instead");
+                    println!("\t--deterministic: see SaveOptions::deterministic");
+                    println!("\t--name-index: see SaveOptions::name_index");
+                    println!(
+                        "\t--parallel: see SaveOptions::parallel and Plugin::with_parallel_save"
+                    );
+                    println!("\t--strict: see SaveOptions::strict");
+                    return Ok(());
+                }
+                _ => dirs.push(PathBuf::from(arg)),
+            }
+        }
+        let (input, output) = match dirs.as_slice() {
+            [_] if export => anyhow::bail!("--export requires an OUTPUT_DIR. {USAGE}"),
+            [input] => (input, input),
+            [input, output] => (input, output),
+            _ => anyhow::bail!("Expected one or two directories. {USAGE}"),
+        };
+        if advise {
+            let mut scenes = Vec::new();
+            find_scenes(input, SCENE_EXTENSIONS, &mut scenes)?;
+            scenes.sort();
+            for scene in &scenes {
+                println!("{}:", scene.display());
+                print!("{}", self.advise_file(scene)?);
+            }
+            return Ok(());
+        }
+        let mut failures = 0;
+        let results = if export {
+            self.export_dir(input, output)?
+        } else {
+            self.convert_dir(input, output)?
+        };
+        for (path, result) in results {
+            match result {
+                Ok(conversion) => {
+                    let Conversion { output, size_before, size_after, lost } = conversion;
+                    let output = output.display();
+                    println!("{}: OK -> {output}", path.display());
+                    println!("\tsize: {size_before} -> {size_after} bytes");
+                    for lost in &lost {
+                        println!("\tlost: {lost}");
+                    }
+                }
+                Err(error) => {
+                    failures += 1;
+                    println!("{}: FAILED: {error:#}", path.display());
+                }
+            }
+        }
+        if failures != 0 {
+            anyhow::bail!("{failures} scene(s) failed to convert");
+        }
+        Ok(())
+    }
+}
+
+/// Components in `world` not stored by a `FastScene` with the given `schema`,
+/// nor inserted back by the rules of `required` when loading it.
+pub(crate) fn lost_components(
+    world: &World,
+    schema: &Schema,
+    required: &RequiredBundles,
+) -> Vec<LostComponent> {
+    let stored: Vec<_> = schema
+        .tables
+        .iter()
+        .chain(&schema.inlines)
+        .map(|c| c.target)
+        .collect();
+    // The hierarchy is stored separately, the others are added by the loader.
+    let hierarchy = [
+        any::type_name::<Parent>(),
+        any::type_name::<Children>(),
+        any::type_name::<HollowSceneRoot>(),
+        any::type_name::<EntityPaths>(),
+    ];
+
+    let components = world.components();
+    let mut lost: Vec<LostComponent> = Vec::new();
+    for archetype in world.archetypes().iter().filter(|a| !a.is_empty()) {
+        // Required bundles are inserted when their trigger component is
+        // loaded, that is when entities of this archetype have a stored one.
+        let loaded = |trigger| match components.get_id(trigger) {
+            // unwrap: `get_id` returned the id, so the component exists
+            Some(id) => {
+                archetype.contains(id) && stored.contains(&components.get_info(id).unwrap().name())
+            }
+            None => false,
+        };
+        for id in archetype.components() {
+            // unwrap: the component is in an archetype of `world`, so it exists
+            let info = components.get_info(id).unwrap();
+            let name = info.name();
+            let restored = info
+                .type_id()
+                .map_or(false, |t| required.inserts(t, loaded));
+            if stored.contains(&name) || hierarchy.contains(&name) || restored {
+                continue;
+            }
+            match lost.iter_mut().find(|l| l.name == name) {
+                Some(lost) => lost.count += archetype.len(),
+                None => lost.push(LostComponent { name: name.to_string(), count: archetype.len() }),
+            }
+        }
+    }
+    lost.sort_by(|l, r| l.name.cmp(&r.name));
+    lost
+}
+
+/// The result of converting each file in a directory.
+pub type DirResult = anyhow::Result<Vec<(PathBuf, anyhow::Result<Conversion>)>>;
+
+/// Whether `a` and `b` are the same directory, `false` if either doesn't exist.
+fn same_dir(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Call `convert` on all files in `input` ending with one of `extensions`,
+/// in alphabetical order.
+fn for_each_scene(
+    input: &Path,
+    output: &Path,
+    extensions: &[&str],
+    output_extension: &str,
+    convert: impl Fn(&Path, &Path) -> anyhow::Result<Conversion>,
+) -> DirResult {
+    let mut scenes = Vec::new();
+    find_scenes(input, extensions, &mut scenes)?;
+    scenes.sort();
+
+    let convert = |path: PathBuf| {
+        // unwrap: `find_scenes` only returns files within `input`
+        let relative = path.strip_prefix(input).unwrap();
+        let relative = output_path(relative, extensions, output_extension);
+        let result = convert(&path, &output.join(relative));
+        (path, result)
+    };
+    Ok(scenes.into_iter().map(convert).collect())
+}
+fn find_scenes(dir: &Path, extensions: &[&str], scenes: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    let read_dir = fs::read_dir(dir).with_context(|| format!("Reading {}", dir.display()))?;
+    for entry in read_dir {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_scenes(&path, extensions, scenes)?;
+        } else if has_extension(&path, extensions) {
+            scenes.push(path);
+        }
+    }
+    Ok(())
+}
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+        return false;
+    };
+    extensions
+        .iter()
+        .any(|ext| name.ends_with(&format!(".{ext}")))
+}
+/// `path` with the longest of `extensions` it ends with replaced by `extension`.
+///
+/// Other dots in the file name are kept, so that `a.b.scn.ron` and
+/// `a.c.scn.ron` are not written to the same file.
+fn output_path(path: &Path, extensions: &[&str], extension: &str) -> PathBuf {
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default();
+    let stem = extensions
+        .iter()
+        .filter_map(|ext| name.strip_suffix(ext)?.strip_suffix('.'))
+        .min_by_key(|stem| stem.len())
+        .unwrap_or(name);
+    path.with_file_name(format!("{stem}.{extension}"))
+}
+fn write(path: &Path, bytes: &[u8]) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    Ok(fs::write(path, bytes)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::{output_path, same_dir, HOLLOW_EXTENSIONS, SCENE_EXTENSIONS};
+
+    #[test]
+    fn output_paths() {
+        let hollow = |path: &str| output_path(Path::new(path), SCENE_EXTENSIONS, "hollow_bvyfst");
+        assert_eq!(hollow("a.scn.ron"), PathBuf::from("a.hollow_bvyfst"));
+        assert_eq!(
+            hollow("dir/a.b.scn.ron"),
+            PathBuf::from("dir/a.b.hollow_bvyfst")
+        );
+        assert_eq!(hollow("a.c.myscn"), PathBuf::from("a.c.hollow_bvyfst"));
+        let ron = output_path(Path::new("a.b.hollow_bvyfst"), HOLLOW_EXTENSIONS, "scn.ron");
+        assert_eq!(ron, PathBuf::from("a.b.scn.ron"));
+    }
+
+    #[test]
+    fn same_dirs() {
+        assert!(same_dir(Path::new("src"), Path::new("./src/../src")));
+        assert!(!same_dir(Path::new("src"), Path::new("examples")));
+        assert!(!same_dir(Path::new("src"), Path::new("does_not_exist")));
+    }
+}
@@ -0,0 +1,123 @@
+//! Map glTF node extras to components when converting glTF scenes.
+//!
+//! Custom properties set on nodes in Blender (and other tools) are exported
+//! as the glTF `extras` JSON object of the node. Bevy's glTF loader adds it to
+//! the node entity as a `GltfExtras` component.
+//!
+//! An extras mapper reads the value of a given key in this object, and adds
+//! components to the entity accordingly. For example, to add a collider to all
+//! nodes with a `"collider": "box"` custom property:
+//!
+//! ```text
+//! Plugin!(Inline[ColliderProxy]).with_extras_mapper("collider", |value, entity| {
+//!     if value == "box" {
+//!         entity.insert(Collider::cuboid(0.5, 0.5, 0.5));
+//!     }
+//! })
+//! ```
+//!
+//! Mappers run when the hollow scene loader reads a glTF file, so the
+//! components are saved in the processed `.hollow_bvyfst` file, as long as
+//! they are in the `Plugin!` list.
+
+use std::sync::Arc;
+
+use anyhow::Context;
+use bevy::ecs::world::EntityMut;
+use bevy::gltf::GltfExtras;
+use bevy::prelude::{Entity, Resource, World};
+use serde_json::Value;
+
+/// A function adding components to an entity based on a glTF extras value.
+pub type Mapper = dyn Fn(&Value, &mut EntityMut) + Send + Sync;
+
+/// The extras mappers of a hollow scene loader, see the [module docs](self).
+///
+/// Mappers added with `Plugin::with_extras_mapper` only run in the loader of
+/// that plugin. Insert this as a resource, before adding the plugins, to run
+/// mappers in the loaders of all plugins.
+#[derive(Resource, Clone, Default)]
+pub struct ExtrasMappers {
+    mappers: Vec<(String, Arc<Mapper>)>,
+}
+impl ExtrasMappers {
+    /// Call `mapper` on nodes with a `key` property in their extras.
+    ///
+    /// Several mappers can be added for the same key, they run in the
+    /// order they were added.
+    pub fn add(
+        &mut self,
+        key: impl Into<String>,
+        mapper: impl Fn(&Value, &mut EntityMut) + Send + Sync + 'static,
+    ) {
+        self.mappers.push((key.into(), Arc::new(mapper)));
+    }
+    /// Add all the mappers of `other`.
+    pub fn extend(&mut self, other: &ExtrasMappers) {
+        self.mappers.extend(other.mappers.iter().cloned());
+    }
+    /// Run the mappers on all entities with a `GltfExtras` in `world`.
+    pub fn apply(&self, world: &mut World) -> anyhow::Result<()> {
+        if self.mappers.is_empty() {
+            return Ok(());
+        }
+        let mut query = world.query::<(Entity, &GltfExtras)>();
+        let extras: Vec<_> = query
+            .iter(world)
+            .map(|(e, x)| (e, x.value.clone()))
+            .collect();
+
+        for (entity, extras) in extras {
+            let extras: Value = serde_json::from_str(&extras)
+                .with_context(|| format!("Invalid glTF extras: {extras}"))?;
+            let Some(extras) = extras.as_object() else {
+                continue;
+            };
+            for (key, mapper) in &self.mappers {
+                if let Some(value) = extras.get(key) {
+                    mapper(value, &mut world.entity_mut(entity));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::prelude::Component;
+
+    #[derive(Component, Debug, PartialEq)]
+    enum Collider {
+        Box,
+        Sphere,
+    }
+
+    #[test]
+    fn map_extras() {
+        let mut world = World::new();
+        let extras = |value: &str| GltfExtras { value: value.to_string() };
+        let boxed = world
+            .spawn(extras(r#"{"collider": "box", "other": 1}"#))
+            .id();
+        let sphere = world.spawn(extras(r#"{"collider": "sphere"}"#)).id();
+        let none = world.spawn(extras(r#"{"other": "box"}"#)).id();
+
+        let mut mappers = ExtrasMappers::default();
+        mappers.add("collider", |value, entity| match value.as_str() {
+            Some("box") => {
+                entity.insert(Collider::Box);
+            }
+            Some("sphere") => {
+                entity.insert(Collider::Sphere);
+            }
+            _ => {}
+        });
+        mappers.apply(&mut world).unwrap();
+
+        assert_eq!(world.get::<Collider>(boxed), Some(&Collider::Box));
+        assert_eq!(world.get::<Collider>(sphere), Some(&Collider::Sphere));
+        assert_eq!(world.get::<Collider>(none), None);
+    }
+}
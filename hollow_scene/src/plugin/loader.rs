@@ -1,58 +1,312 @@
+use std::collections::HashMap;
 use std::marker::PhantomData;
+use std::sync::{Arc, Mutex, RwLock};
 
 use anyhow::Result as AnyResult;
+#[cfg(feature = "gltf")]
+use bevy::{
+    animation::AnimationClip,
+    asset::{Asset, ErasedLoadedAsset},
+    gltf::{Gltf, GltfLoader, GltfMesh, GltfNode},
+    pbr::StandardMaterial,
+    prelude::{warn, Image, Mesh},
+    render::mesh::skinning::SkinnedMeshInverseBindposes,
+};
 use bevy::{
     asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
-    prelude::{info, AppTypeRegistry, FromWorld, Scene, World},
+    prelude::{info, AppTypeRegistry, AssetServer, FromWorld, Resource, Scene, World},
     scene::SceneLoader,
     utils::BoxedFuture,
 };
+use serde::{Deserialize, Serialize};
 
+use super::chunked::{self, HollowArchive};
+#[cfg(feature = "gltf")]
+use super::extras::ExtrasMappers;
 use super::processor::Format;
-use crate::{entity::Inlines, entity::Tables, FastScene};
+use crate::header::{self, Header};
+use crate::required::RequiredBundles;
+use crate::{entity::Inlines, entity::Tables, schema::Schema, ArchivedFastScene};
 
 type Ctx<'a, 'b> = &'a mut LoadContext<'b>;
 
+#[cfg(not(feature = "gltf"))]
+const EXTENSIONS: &[&str] = &["hollow_bvyfst", "myscn", "myscn.ron"];
+#[cfg(feature = "gltf")]
+const EXTENSIONS: &[&str] = &["hollow_bvyfst", "myscn", "myscn.ron", "gltf", "glb"];
+
+/// The extensions of the loader of a plugin with the given name, see
+/// [`crate::Plugin::with_name`].
+///
+/// `AssetLoader::extensions` borrows from the loader, so the extensions of
+/// each name are leaked, once, the first time they are requested.
+pub(super) fn extensions(name: Option<&str>) -> &'static [&'static str] {
+    static NAMED: Mutex<Vec<(String, &'static [&'static str])>> = Mutex::new(Vec::new());
+
+    let Some(name) = name else {
+        return EXTENSIONS;
+    };
+    // unwrap: we never panic while holding the lock
+    let mut named = NAMED.lock().unwrap();
+    if let Some((_, extensions)) = named.iter().find(|(n, _)| n == name) {
+        return extensions;
+    }
+    let leak = |ext: &&str| -> &'static str { Box::leak(format!("{name}.{ext}").into_boxed_str()) };
+    let extensions: Vec<_> = EXTENSIONS
+        .iter()
+        .map(leak)
+        .chain(["hollow_bvyfst"])
+        .collect();
+    let extensions = Box::leak(extensions.into_boxed_slice());
+    named.push((name.to_string(), extensions));
+    extensions
+}
+
+/// The settings of the hollow scene loader, in the `.meta` file of the scene.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct LoaderSettings {
+    pub format: Format,
+    /// Insert `GlobalTransform`, and with the `render` feature `Visibility` and
+    /// `ComputedVisibility`, on entities with a `Transform`.
+    ///
+    /// See [`RequiredBundles::add_transform_rule`].
+    pub transform_bundles: bool,
+    /// Insert a `SpatialBundle` on the scene root entities, or the plugin's
+    /// own root bundle if it has one.
+    ///
+    /// See [`RequiredBundles::set_default_root`].
+    pub root_bundle: bool,
+    /// Add the content of `.hollow_bvyfst` files as a `#Archive` labeled
+    /// asset, to spawn them with [`crate::chunked::ChunkedScene`].
+    pub chunked: bool,
+}
+impl Default for LoaderSettings {
+    fn default() -> Self {
+        LoaderSettings {
+            format: Format::default(),
+            transform_bundles: true,
+            root_bundle: true,
+            chunked: false,
+        }
+    }
+}
+impl LoaderSettings {
+    /// The built-in rules enabled by these settings, followed by `plugin`'s.
+    pub(super) fn required(&self, plugin: &RequiredBundles) -> RequiredBundles {
+        let mut required = RequiredBundles::default();
+        if self.transform_bundles {
+            required.add_transform_rule();
+        }
+        if self.root_bundle {
+            required.set_default_root();
+        }
+        required.extend(plugin);
+        if !self.root_bundle {
+            required.clear_root();
+        }
+        required
+    }
+}
+
+/// Spawn an aligned `.hollow_bvyfst` payload saved with a given schema.
+type ReadPayload = fn(&Header, &[u8], &AssetServer, &RequiredBundles) -> AnyResult<Scene>;
+
+/// Spawn `payload`, after checking that its `Auto` storages are the ones
+/// recorded in `header`.
+fn read_payload<Ts: Tables, Is: Inlines>(
+    header: &Header,
+    payload: &[u8],
+    asset_server: &AssetServer,
+    required: &RequiredBundles,
+) -> AnyResult<Scene> {
+    // SAFETY: the header fingerprint is the one of this schema, and the file
+    // is trusted, see `ArchivedFastScene::from_payload`.
+    let fast_scene = unsafe { ArchivedFastScene::<Ts, Is>::from_payload(payload)? };
+    header.check_auto(&fast_scene.tables.auto_storages()?)?;
+    Ok(fast_scene.to_bevy_with_required(asset_server, required))
+}
+
+/// The schemas of all hollow scene `Plugin`s in the app, by fingerprint.
+///
+/// All loaders accept the `hollow_bvyfst` extension, whichever loader bevy
+/// picks uses this to read the file with the schema it was saved with.
+#[derive(Resource, Clone, Default)]
+pub(super) struct Schemas(Arc<RwLock<HashMap<u64, ReadPayload>>>);
+impl Schemas {
+    pub(super) fn add<Ts: Tables + 'static, Is: Inlines + 'static>(&self) {
+        let fingerprint = Schema::of::<Ts, Is>().fingerprint();
+        // unwrap: we never panic while holding the lock
+        let mut schemas = self.0.write().unwrap();
+        schemas.insert(fingerprint, read_payload::<Ts, Is>);
+    }
+    fn get(&self, fingerprint: u64) -> Option<ReadPayload> {
+        // unwrap: we never panic while holding the lock
+        self.0.read().unwrap().get(&fingerprint).copied()
+    }
+}
+
 // TODO: parametrize over loaders
-pub struct Loader<Ts, Is>(SceneLoader, AppTypeRegistry, PhantomData<fn(Ts, Is)>);
+pub struct Loader<Ts, Is> {
+    scene_loader: SceneLoader,
+    #[cfg(feature = "gltf")]
+    gltf_loader: GltfLoader,
+    registry: AppTypeRegistry,
+    asset_server: AssetServer,
+    /// The mappers of the `ExtrasMappers` resource, followed by the plugin's.
+    #[cfg(feature = "gltf")]
+    pub(super) extras: ExtrasMappers,
+    schemas: Schemas,
+    /// The required bundles of the plugin, without the built-in ones.
+    pub(super) required: RequiredBundles,
+    pub(super) extensions: &'static [&'static str],
+    _types: PhantomData<fn(Ts, Is)>,
+}
 
 impl<Ts, Is> FromWorld for Loader<Ts, Is> {
     fn from_world(world: &mut World) -> Self {
-        let scene_loader = FromWorld::from_world(world);
-        let registry = world.resource::<AppTypeRegistry>();
-        Loader(scene_loader, registry.clone(), PhantomData)
+        Loader {
+            scene_loader: FromWorld::from_world(world),
+            // The scene is only read from the glTF file, there is no need to
+            // support compressed textures or custom attributes.
+            #[cfg(feature = "gltf")]
+            gltf_loader: GltfLoader {
+                supported_compressed_formats: Default::default(),
+                custom_vertex_attributes: Default::default(),
+            },
+            registry: world.resource::<AppTypeRegistry>().clone(),
+            asset_server: world.resource::<AssetServer>().clone(),
+            #[cfg(feature = "gltf")]
+            extras: world
+                .get_resource::<ExtrasMappers>()
+                .cloned()
+                .unwrap_or_default(),
+            schemas: world.get_resource::<Schemas>().cloned().unwrap_or_default(),
+            required: RequiredBundles::default(),
+            extensions: EXTENSIONS,
+            _types: PhantomData,
+        }
+    }
+}
+#[cfg(feature = "gltf")]
+impl<Ts, Is> Loader<Ts, Is> {
+    /// Read the default scene of the glTF file in `bytes`.
+    ///
+    /// The glTF's meshes, materials etc. are added as labeled assets of the
+    /// loaded scene, so that the `Handle`s in the scene point to them.
+    /// The [`ExtrasMappers`] are applied to the scene.
+    async fn load_gltf(&self, bytes: &[u8], ctx: Ctx<'_, '_>) -> AnyResult<Scene> {
+        // Load the glTF in a separate context to read the scene value, since
+        // it is not possible to access labeled assets of a `LoadContext`.
+        let mut gltf_ctx = ctx.begin_labeled_asset();
+        let gltf = self
+            .gltf_loader
+            .load(&mut &bytes[..], &(), &mut gltf_ctx)
+            .await?;
+        let gltf: ErasedLoadedAsset = gltf_ctx.finish(gltf, None).into();
+        self.add_labeled_assets(&gltf, ctx)?;
+
+        // unwrap: we just inserted a `Gltf`
+        let handles = gltf.get::<Gltf>().unwrap();
+        let scene = handles.default_scene.as_ref().or(handles.scenes.first());
+        let label = scene.and_then(|h| h.path()).and_then(|p| p.label());
+        let scene = label.and_then(|l| gltf.get_labeled(l.to_string()));
+        let scene = scene.and_then(|s| s.get::<Scene>());
+        let scene = scene.ok_or_else(|| anyhow::anyhow!("The glTF file has no scene"))?;
+        let mut scene = scene.clone_with(&self.registry)?;
+        self.extras.apply(&mut scene.world)?;
+        Ok(scene)
+    }
+    /// Add the labeled assets of `gltf` to `ctx`.
+    ///
+    /// `LoadContext` can't add an already loaded labeled asset, so they are
+    /// copied one at a time, assets of unknown types are skipped.
+    fn add_labeled_assets(&self, gltf: &ErasedLoadedAsset, ctx: Ctx) -> AnyResult<()> {
+        fn copy<A: Asset + Clone>(asset: &ErasedLoadedAsset, label: &str, ctx: Ctx) -> bool {
+            let asset = asset.get::<A>().cloned();
+            asset
+                .map(|a| ctx.add_labeled_asset(label.to_string(), a))
+                .is_some()
+        }
+        for label in gltf.iter_labels() {
+            // unwrap: `label` is one of the labels of `gltf`
+            let asset = gltf.get_labeled(label.to_string()).unwrap();
+            if let Some(scene) = asset.get::<Scene>() {
+                let scene = scene.clone_with(&self.registry)?;
+                ctx.add_labeled_asset(label.to_string(), scene);
+            } else if let Some(bindposes) = asset.get::<SkinnedMeshInverseBindposes>() {
+                let bindposes = SkinnedMeshInverseBindposes::from(bindposes.to_vec());
+                ctx.add_labeled_asset(label.to_string(), bindposes);
+            } else if !(copy::<Mesh>(asset, label, ctx)
+                || copy::<StandardMaterial>(asset, label, ctx)
+                || copy::<Image>(asset, label, ctx)
+                || copy::<GltfMesh>(asset, label, ctx)
+                || copy::<GltfNode>(asset, label, ctx)
+                || copy::<AnimationClip>(asset, label, ctx))
+            {
+                let name = asset.asset_type_name();
+                warn!("Skipping the glTF labeled asset {label}, of unsupported type {name}");
+            }
+        }
+        Ok(())
     }
 }
 
 impl<Ts: Tables + 'static, Is: Inlines + 'static> AssetLoader for Loader<Ts, Is> {
     type Asset = Scene;
-    type Settings = Format;
+    type Settings = LoaderSettings;
 
     fn load<'a>(
         &'a self,
         reader: &'a mut Reader,
-        format: &'a Format,
+        settings: &'a LoaderSettings,
         ctx: Ctx<'a, '_>,
     ) -> BoxedFuture<'a, AnyResult<Scene>> {
         use Format::{Fast, FromFileExtension};
 
         Box::pin(async move {
             // unwrap: this will only be called with the extensions defined in fn extensions
-            match (format, ctx.path().extension().unwrap().to_str().unwrap()) {
+            match (
+                settings.format,
+                ctx.path().extension().unwrap().to_str().unwrap(),
+            ) {
                 (Fast, _) | (FromFileExtension, "hollow_bvyfst") => {
                     let mut bytes = Vec::new();
                     reader.read_to_end(&mut bytes).await?;
-                    let fast_scene = unsafe { rkyv::archived_root::<FastScene<Ts, Is>>(&bytes) };
-                    Ok(fast_scene.to_bevy())
+                    let (header, payload) = Header::read(&bytes)?;
+                    header.check_version()?;
+                    let read = match self.schemas.get(header.fingerprint) {
+                        Some(read) => read,
+                        None => {
+                            header.check(&Schema::of::<Ts, Is>())?;
+                            read_payload::<Ts, Is>
+                        }
+                    };
+                    let payload = header::aligned(payload);
+                    let required = settings.required(&self.required);
+                    let scene = read(&header, &payload, &self.asset_server, &required)?;
+
+                    if settings.chunked {
+                        let fingerprint = header.fingerprint;
+                        let archive = HollowArchive { fingerprint, payload, required };
+                        ctx.add_labeled_asset(chunked::LABEL.to_string(), archive);
+                    }
+                    Ok(scene)
                 }
                 (FromFileExtension, "myscn" | "ron") => {
                     info!("got a dynamic scene, reading it");
-                    let dynamic_scene = self.0.load(reader, &(), ctx).await?;
+                    let dynamic_scene = self.scene_loader.load(reader, &(), ctx).await?;
                     info!("turning dynamic scene into real scene");
-                    let scene = Scene::from_dynamic_scene(&dynamic_scene, &self.1)?;
+                    let scene = Scene::from_dynamic_scene(&dynamic_scene, &self.registry)?;
                     info!("completed the truing of dynamcis cene to real scen");
                     Ok(scene)
                 }
+                #[cfg(feature = "gltf")]
+                (FromFileExtension, "gltf" | "glb") => {
+                    let mut bytes = Vec::new();
+                    reader.read_to_end(&mut bytes).await?;
+                    self.load_gltf(&bytes, ctx).await
+                }
                 (FromFileExtension, ext) => unreachable!(
                     "Loader should only be called with extensions: {:?}, got '{ext}'",
                     self.extensions()
@@ -61,6 +315,87 @@ impl<Ts: Tables + 'static, Is: Inlines + 'static> AssetLoader for Loader<Ts, Is>
         })
     }
     fn extensions(&self) -> &[&str] {
-        &["hollow_bvyfst", "myscn", "myscn.ron"]
+        self.extensions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use super::{extensions, LoaderSettings, EXTENSIONS};
+    use crate::required::RequiredBundles;
+
+    #[test]
+    fn named_extensions() {
+        assert_eq!(extensions(None), EXTENSIONS);
+        let named = extensions(Some("level"));
+        assert!(named.contains(&"level.hollow_bvyfst"));
+        assert!(named.contains(&"level.myscn.ron"));
+        assert!(named.contains(&"hollow_bvyfst"));
+        assert!(!named.contains(&"myscn.ron"));
+        // Only leaked once.
+        assert!(std::ptr::eq(named, extensions(Some("level"))));
+    }
+
+    #[test]
+    fn settings_required() {
+        #[derive(Component, Default)]
+        struct PluginRoot;
+
+        let mut plugin = RequiredBundles::default();
+        plugin.set_root::<(PluginRoot,)>();
+        let mut world = World::new();
+        let mut root = |settings: LoaderSettings| {
+            let mut entity = world.spawn_empty();
+            settings.required(&plugin).apply_root(&mut entity);
+            entity.id()
+        };
+        let with_root = root(LoaderSettings::default());
+        let without = root(LoaderSettings { root_bundle: false, ..default() });
+
+        assert!(world.get::<PluginRoot>(with_root).is_some());
+        assert!(world.get::<Transform>(with_root).is_none());
+        assert!(world.get::<PluginRoot>(without).is_none());
+    }
+
+    #[cfg(feature = "gltf")]
+    #[test]
+    fn load_gltf() {
+        use bevy::{core::TaskPoolPlugin, gltf::GltfNode};
+
+        use super::Loader;
+
+        let mut app = App::new();
+        app.add_plugin(TaskPoolPlugin::default())
+            .add_plugin(AssetPlugin::default())
+            .init_asset::<Scene>()
+            .init_asset::<GltfNode>()
+            .register_type::<Name>()
+            .register_type::<Transform>()
+            .register_type::<GlobalTransform>()
+            .register_type::<Visibility>()
+            .register_type::<ComputedVisibility>()
+            .register_type::<Parent>()
+            .register_type::<Children>();
+        let loader = Loader::<(), ()>::from_world(&mut app.world);
+        app.register_asset_loader(loader);
+
+        let scene: Handle<Scene> = app.world.resource::<AssetServer>().load("tests/cube.gltf");
+        for _ in 0..1000 {
+            app.update();
+            if app.world.resource::<Assets<Scene>>().contains(&scene) {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        let mut scenes = app.world.resource_mut::<Assets<Scene>>();
+        let world = &mut scenes.get_mut(&scene).expect("the glTF loaded").world;
+        let cube = world.query::<(&Name, &Transform)>().single(world);
+        assert_eq!(cube.0.as_str(), "Cube");
+        assert_eq!(cube.1.translation, Vec3::new(1.0, 2.0, 3.0));
+
+        // The glTF's labeled assets are added to the scene's.
+        assert_eq!(app.world.resource::<Assets<GltfNode>>().len(), 1);
     }
 }
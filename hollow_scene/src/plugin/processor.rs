@@ -1,10 +1,11 @@
 use bevy::{
     asset::processor::{AssetProcessor, LoadAndSave},
-    prelude::{info, FromWorld, World},
+    prelude::{info, World},
 };
 use serde::{Deserialize, Serialize};
 
-use super::{loader::Loader, saver::Saver, RkyvTypeNonsense};
+use super::{loader::Loader, saver::FromBevy, saver::Saver, RkyvTypeNonsense};
+use crate::required::RequiredBundles;
 use crate::{entity::Inlines, entity::Tables};
 
 type Processor<T, I> = LoadAndSave<Loader<T, I>, Saver<T, I>>;
@@ -16,13 +17,17 @@ pub enum Format {
     Fast,
 }
 
-pub(super) fn insert<Ts: Tables + 'static, Is: Inlines + 'static>(world: &mut World)
-where
+pub(super) fn insert<Ts: Tables + 'static, Is: Inlines + 'static>(
+    world: &mut World,
+    from_bevy: FromBevy<Ts, Is>,
+    required: RequiredBundles,
+    name: Option<&str>,
+) where
     Ts::Keys: RkyvTypeNonsense,
     Ts: RkyvTypeNonsense,
     Is: RkyvTypeNonsense,
 {
-    let saver = Saver::<Ts, Is>::from_world(world);
+    let saver = Saver::<Ts, Is>::new(world, from_bevy, required);
     let Some(processor) = world.get_resource::<AssetProcessor>() else {
             info!(
                 "Your bevy plugin config isn't setup to use asset processing. \
@@ -36,5 +41,14 @@ where
     );
     processor.register_processor::<Processor<Ts, Is>>(saver.into());
 
-    processor.set_default_processor::<Processor<Ts, Is>>("myscn.ron");
+    let named = |extension: &str| match name {
+        Some(name) => format!("{name}.{extension}"),
+        None => extension.to_string(),
+    };
+    processor.set_default_processor::<Processor<Ts, Is>>(&named("myscn.ron"));
+    #[cfg(feature = "gltf")]
+    {
+        processor.set_default_processor::<Processor<Ts, Is>>(&named("gltf"));
+        processor.set_default_processor::<Processor<Ts, Is>>(&named("glb"));
+    }
 }
@@ -0,0 +1,731 @@
+//! Update spawned hollow scenes in place when their file changes.
+//!
+//! Spawn a scene with the [`ReloadableScene`] component instead of bevy's
+//! `SceneBundle` to get this behavior:
+//!
+//! ```text
+//! commands.spawn((
+//!     ReloadableScene(asset_server.load("level.hollow_bvyfst")),
+//!     SpatialBundle::default(),
+//! ));
+//! ```
+//!
+//! `SceneBundle` instances of scenes read by the hollow scene loader are
+//! updated the same way, once bevy spawned them.
+//!
+//! When bevy reloads the scene asset, the new scene is compared to the
+//! previous one, entity by entity, and only the differences are applied to
+//! the spawned instances:
+//!
+//! - Components added or changed in the scene are inserted on the matching
+//!   spawned entity.
+//! - Components removed from the scene are removed from the spawned entity.
+//! - Entities added to the scene are spawned, entities removed from the scene
+//!   are despawned, with their descendants.
+//!
+//! Scene entities are matched by their index in the entity slice, which
+//! [`crate::hierarchy::build`] orders depth first, children after their
+//! parent and in order. The old and new slices are aligned first, so that
+//! adding or removing an entity doesn't shift the following ones: entities
+//! at the same depth are aligned when they have the same `Name`, and unnamed
+//! ones when all their components are equal. The entities between two
+//! aligned ones are matched in order, the remaining ones are spawned or
+//! despawned.
+//!
+//! Components that are not in the scene, such as gameplay state added at
+//! runtime, are left untouched, so are components whose value didn't change
+//! in the scene file.
+//!
+//! The entity the hollow scene loader spawns scenes under, and the synthetic
+//! root of the entity slice, are not part of the instance: the scene's root
+//! entities are children of the [`ReloadableScene`] entity.
+//!
+//! The [`crate::hooks`] of the scene's plugin run when a component is added
+//! to a spawned entity, and [`HollowSceneSpawned`] is sent when a
+//! [`ReloadableScene`] is first spawned. Its `entity_map` is the spawned
+//! entities, in the order of the scene entities, with the [`ReloadableScene`]
+//! entity in place of the synthetic root.
+//!
+//! For `SceneBundle`s of hollow scenes, all the hooks of the spawned entities
+//! run and [`HollowSceneSpawned`] is sent once bevy spawned the instance.
+
+use std::any::TypeId;
+use std::collections::{HashMap, HashSet};
+use std::iter;
+
+use bevy::asset::{AssetEvent, AssetId, Assets, Handle};
+use bevy::ecs::entity::EntityMap;
+use bevy::ecs::event::{Events, ManualEventReader};
+use bevy::ecs::reflect::{ReflectComponent, ReflectMapEntities};
+use bevy::prelude::{
+    AppTypeRegistry, BuildWorldChildren, Children, Component, DespawnRecursiveExt, Entity, Local,
+    Mut, Name, Parent, Reflect, Resource, Scene, Without, World,
+};
+use bevy::reflect::TypeRegistry;
+use bevy::scene::{SceneInstance, SceneSpawner};
+
+use crate::hooks::{HollowSceneSpawned, PluginHooks, SpawnHooks};
+use crate::HollowSceneRoot;
+
+/// Spawn the scene as children of this entity, and update it in place when
+/// the scene asset is modified, see the [module docs](self).
+#[derive(Component, Clone, Debug)]
+pub struct ReloadableScene(pub Handle<Scene>);
+
+/// The spawned entities of a scene instance.
+#[derive(Component)]
+struct Instance {
+    /// The parent of the spawned scene root entities.
+    root: Entity,
+    /// The spawned entity of each entity of the [`Snapshot`] of the scene.
+    entities: Vec<Entity>,
+}
+impl Instance {
+    /// The spawned entities, after `first`, the entity spawned for the
+    /// synthetic root.
+    fn entity_map(&self, first: Entity) -> Vec<Entity> {
+        iter::once(first)
+            .chain(self.entities.iter().copied())
+            .collect()
+    }
+}
+
+/// The last seen version of every reloaded scene asset.
+#[derive(Resource, Default)]
+pub(super) struct Snapshots(HashMap<AssetId<Scene>, Snapshot>);
+
+struct EntitySnapshot {
+    /// The entity in the scene's world.
+    id: Entity,
+    /// The index of the parent in the snapshot, `None` for scene roots.
+    parent: Option<usize>,
+    /// How many ancestors the entity has in the scene.
+    depth: u32,
+    name: Option<String>,
+    components: HashMap<TypeId, Box<dyn Reflect>>,
+}
+impl EntitySnapshot {
+    /// Whether `other`, in another version of the scene, is the same entity:
+    /// at the same depth, with the same `Name`, or unnamed with the same
+    /// components.
+    fn aligns_with(&self, other: &EntitySnapshot) -> bool {
+        if self.depth != other.depth || self.name != other.name {
+            return false;
+        }
+        if self.name.is_some() {
+            return true;
+        }
+        let mut components = self.components.iter();
+        self.components.len() == other.components.len()
+            && components.all(|(type_id, value)| {
+                let other = other.components.get(type_id);
+                other.and_then(|o| o.reflect_partial_eq(&**value)) == Some(true)
+            })
+    }
+}
+/// A copy of the components of a [`Scene`], to compare with later versions.
+#[derive(Default)]
+struct Snapshot {
+    /// The entities, depth first, so in entity slice order for hollow scenes.
+    entities: Vec<EntitySnapshot>,
+    /// The schema fingerprint of scenes of the hollow scene loader.
+    fingerprint: Option<u64>,
+}
+
+impl Snapshot {
+    fn new(scene: &Scene, registry: &TypeRegistry) -> Self {
+        // The hierarchy is handled separately, as it needs entity mapping.
+        let hierarchy = [TypeId::of::<Parent>(), TypeId::of::<Children>()];
+        let world = &scene.world;
+        let mut entities: Vec<EntitySnapshot> = Vec::new();
+        for (id, parent) in depth_first(world, &scene_roots(world), |_| true) {
+            let entity = world.entity(id);
+            let components = entity.archetype().components().filter_map(|id| {
+                let type_id = world.components().get_info(id)?.type_id()?;
+                if hierarchy.contains(&type_id) {
+                    return None;
+                }
+                let reflect = registry.get(type_id)?.data::<ReflectComponent>()?;
+                Some((type_id, reflect.reflect(entity)?.clone_value()))
+            });
+            let depth = parent.map_or(0, |p| entities[p].depth + 1);
+            let name = entity.get::<Name>().map(|name| name.as_str().to_string());
+            let components = components.collect();
+            entities.push(EntitySnapshot { id, parent, depth, name, components });
+        }
+        let mut roots = world.iter_entities();
+        let fingerprint = roots.find_map(|e| Some(e.get::<HollowSceneRoot>()?.fingerprint));
+        Snapshot { entities, fingerprint }
+    }
+    /// The spawn hooks of the plugin of this scene.
+    fn hooks<'a>(&self, plugins: Option<&'a PluginHooks>) -> Option<&'a SpawnHooks> {
+        plugins?.get(self.fingerprint?)
+    }
+}
+
+/// Above this many `old` × `new` entities between the common prefix and
+/// suffix, [`align`] matches them in order rather than diffing them.
+const MAX_DIFF_CELLS: usize = 1 << 20;
+
+/// The index of the matching `old` entity of each `new` entity, `None` for
+/// added entities.
+///
+/// The longest sequence of entities that [`EntitySnapshot::aligns_with`]
+/// each other in both slices is matched, then the entities between two
+/// matched ones are matched in order.
+fn align(old: &[EntitySnapshot], new: &[EntitySnapshot]) -> Vec<Option<usize>> {
+    let aligned = |(o, n): (&EntitySnapshot, &EntitySnapshot)| o.aligns_with(n);
+    let prefix = old.iter().zip(new).take_while(|&e| aligned(e)).count();
+    let (old_rest, new_rest) = (&old[prefix..], &new[prefix..]);
+    let pairs = old_rest.iter().rev().zip(new_rest.iter().rev());
+    let suffix = pairs.take_while(|&e| aligned(e)).count();
+    let old_mid = &old_rest[..old_rest.len() - suffix];
+    let new_mid = &new_rest[..new_rest.len() - suffix];
+
+    // The aligned (old, new) indices, in order.
+    let mut anchors: Vec<_> = (0..prefix).map(|i| (i, i)).collect();
+    let (n, m) = (old_mid.len(), new_mid.len());
+    if n * m <= MAX_DIFF_CELLS {
+        // `lcs[i * w + j]` is the length of the longest common subsequence
+        // of `old_mid[i..]` and `new_mid[j..]`.
+        let w = m + 1;
+        let mut lcs = vec![0_u32; (n + 1) * w];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lcs[i * w + j] = if old_mid[i].aligns_with(&new_mid[j]) {
+                    lcs[(i + 1) * w + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * w + j].max(lcs[i * w + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < n && j < m {
+            if lcs[i * w + j] == lcs[(i + 1) * w + j] {
+                i += 1;
+            } else if lcs[i * w + j] == lcs[i * w + j + 1] {
+                j += 1;
+            } else {
+                anchors.push((prefix + i, prefix + j));
+                (i, j) = (i + 1, j + 1);
+            }
+        }
+    }
+    let suffix = (0..suffix).rev();
+    anchors.extend(suffix.map(|k| (old.len() - 1 - k, new.len() - 1 - k)));
+
+    let mut matched = vec![None; new.len()];
+    let (mut next_old, mut next_new) = (0, 0);
+    for (i, j) in anchors.into_iter().chain([(old.len(), new.len())]) {
+        for (between_new, between_old) in (next_new..j).zip(next_old..i) {
+            matched[between_new] = Some(between_old);
+        }
+        if j < new.len() {
+            matched[j] = Some(i);
+        }
+        (next_old, next_new) = (i + 1, j + 1);
+    }
+    matched
+}
+
+fn children(world: &World, entity: Entity) -> &[Entity] {
+    world.get::<Children>(entity).map_or(&[], |c| &c[..])
+}
+/// The root entities of the scene in `world`.
+///
+/// These are the children of the synthetic root for scenes of the hollow
+/// scene loader, the entities without parent otherwise.
+fn scene_roots(world: &World) -> Vec<Entity> {
+    let mut wrapper = world
+        .iter_entities()
+        .filter(|e| e.contains::<HollowSceneRoot>());
+    match wrapper.next() {
+        Some(wrapper) => {
+            let synthetic = children(world, wrapper.id()).iter();
+            synthetic
+                .flat_map(|e| children(world, *e))
+                .copied()
+                .collect()
+        }
+        None => {
+            let roots = world.iter_entities().filter(|e| !e.contains::<Parent>());
+            roots.map(|e| e.id()).collect()
+        }
+    }
+}
+/// `roots` and their descendants in depth-first order, skipping the entities
+/// not matching `include` and their descendants.
+///
+/// Returns each entity with the index of its parent.
+fn depth_first(
+    world: &World,
+    roots: &[Entity],
+    include: impl Fn(Entity) -> bool,
+) -> Vec<(Entity, Option<usize>)> {
+    let mut entities = Vec::new();
+    // The entities to visit, the next one last.
+    let mut stack = Vec::new();
+    let push_siblings = |stack: &mut Vec<_>, siblings: &[Entity], parent| {
+        let siblings = siblings.iter().filter(|e| include(**e));
+        stack.extend(siblings.rev().map(|&id| (id, parent)));
+    };
+    push_siblings(&mut stack, roots, None);
+    while let Some((id, parent)) = stack.pop() {
+        push_siblings(&mut stack, children(world, id), Some(entities.len()));
+        entities.push((id, parent));
+    }
+    entities
+}
+/// The [`Instance`] of a hollow scene spawned by bevy's `SceneSpawner`,
+/// `spawned` being the spawned entities.
+fn spawned_instance(world: &World, spawned: &HashSet<Entity>) -> Option<Instance> {
+    let is_spawned = |entity: Entity| spawned.contains(&entity);
+    let wrapper = spawned
+        .iter()
+        .find(|e| world.get::<HollowSceneRoot>(**e).is_some())?;
+    let root = *children(world, *wrapper).iter().find(|e| is_spawned(**e))?;
+    let roots: Vec<_> = children(world, root).iter().copied().collect();
+    let entities = depth_first(world, &roots, is_spawned).into_iter();
+    let entities = entities.map(|(id, _)| id).collect();
+    Some(Instance { root, entities })
+}
+
+/// Apply the differences between `old` and `new` to the instance spawned as
+/// children of `root`.
+///
+/// `instance` is the spawned entity of each entity of `old`, it is replaced
+/// by the spawned entity of each entity of `new`. `hooks` run on inserted
+/// components.
+fn apply(
+    world: &mut World,
+    root: Entity,
+    old: &Snapshot,
+    new: &Snapshot,
+    instance: &mut Vec<Entity>,
+    registry: &TypeRegistry,
+    hooks: Option<&SpawnHooks>,
+) {
+    let reflect = |type_id: TypeId| registry.get(type_id)?.data::<ReflectComponent>();
+    let mut written: HashMap<TypeId, Vec<Entity>> = HashMap::new();
+    let mut inserted = Vec::new();
+    let mut entity_map = EntityMap::default();
+
+    let matched = align(&old.entities, &new.entities);
+    let mut kept = vec![false; instance.len()];
+    let spawned: Vec<_> = matched
+        .iter()
+        .map(|old_index| {
+            let live = old_index.and_then(|i| Some((i, *instance.get(i)?)));
+            let Some((i, live)) = live else {
+                return world.spawn_empty().id();
+            };
+            kept[i] = true;
+            live
+        })
+        .collect();
+
+    let entities = new.entities.iter().zip(&matched).zip(&spawned);
+    for ((new_entity, old_index), &live) in entities {
+        let old_entity = old_index.map(|i| &old.entities[i]);
+        entity_map.insert(new_entity.id, live);
+        // The entity was despawned by something else, leave it alone.
+        let Some(mut live_mut) = world.get_entity_mut(live) else {
+            continue;
+        };
+        for (type_id, value) in &new_entity.components {
+            let old_value = old_entity.and_then(|e| e.components.get(type_id));
+            if old_value.and_then(|old| old.reflect_partial_eq(&**value)) == Some(true) {
+                continue;
+            }
+            let Some(reflect) = reflect(*type_id) else {
+                continue;
+            };
+            if !live_mut.contains_type_id(*type_id) {
+                inserted.push((*type_id, live));
+            }
+            reflect.apply_or_insert(&mut live_mut, &**value);
+            written.entry(*type_id).or_default().push(live);
+        }
+        let old_components = old_entity.into_iter().flat_map(|e| e.components.keys());
+        for type_id in old_components.filter(|t| !new_entity.components.contains_key(t)) {
+            if let Some(reflect) = reflect(*type_id) {
+                reflect.remove(&mut live_mut);
+            }
+        }
+    }
+    // Only map entities in components we just wrote, the others already
+    // point to spawned entities.
+    for (type_id, entities) in &written {
+        let map_entities = registry.get(*type_id);
+        if let Some(map_entities) = map_entities.and_then(|r| r.data::<ReflectMapEntities>()) {
+            map_entities.map_entities(world, &mut entity_map, entities);
+        }
+    }
+    if let Some(hooks) = hooks {
+        for (type_id, live) in inserted {
+            if let Some(mut live) = world.get_entity_mut(live) {
+                hooks.run(type_id, &mut live);
+            }
+        }
+    }
+    for (new_entity, &live) in new.entities.iter().zip(&spawned) {
+        let parent = new_entity.parent.map_or(root, |parent| spawned[parent]);
+        if world.get_entity(parent).is_none() {
+            continue;
+        }
+        let Some(mut live) = world.get_entity_mut(live) else {
+            continue;
+        };
+        if live.get::<Parent>().map(Parent::get) != Some(parent) {
+            live.set_parent(parent);
+        }
+    }
+    // Despawn after updating the hierarchy, so that entities moved out of a
+    // removed entity are not despawned with it.
+    let removed = instance.iter().zip(kept).filter(|(_, kept)| !kept);
+    for (live, _) in removed {
+        if let Some(live) = world.get_entity_mut(*live) {
+            live.despawn_recursive();
+        }
+    }
+    *instance = spawned;
+}
+
+/// Spawn new [`ReloadableScene`]s, and update the existing ones, and the
+/// hollow scenes spawned with a `SceneBundle`, when their scene is modified.
+pub(super) fn reload_scenes(
+    world: &mut World,
+    mut reader: Local<ManualEventReader<AssetEvent<Scene>>>,
+) {
+    let mut modified = HashSet::new();
+    let mut removed = Vec::new();
+    for event in reader.iter(world.resource::<Events<AssetEvent<Scene>>>()) {
+        match event {
+            AssetEvent::Modified { id } => {
+                modified.insert(*id);
+            }
+            AssetEvent::Removed { id } => removed.push(*id),
+            _ => {}
+        }
+    }
+    let registry = world.resource::<AppTypeRegistry>().clone();
+    let registry = registry.read();
+
+    let mut roots = world.query::<(Entity, &ReloadableScene, Option<&Instance>)>();
+    let roots: Vec<_> = roots
+        .iter(world)
+        .map(|(entity, scene, instance)| (entity, scene.0.id(), instance.is_some()))
+        .collect();
+    let mut bundles = world.query_filtered::<
+        (Entity, &Handle<Scene>, &SceneInstance, Option<&Instance>),
+        Without<ReloadableScene>,
+    >();
+    let bundles: Vec<_> = bundles
+        .iter(world)
+        .map(|(entity, scene, instance, spawned)| {
+            (entity, scene.id(), **instance, spawned.is_some())
+        })
+        .collect();
+    let plugin_hooks = world.get_resource::<PluginHooks>().cloned();
+    let plugin_hooks = plugin_hooks.as_ref();
+
+    world.resource_scope(|world, mut snapshots: Mut<Snapshots>| {
+        for id in &removed {
+            snapshots.0.remove(id);
+        }
+        world.resource_scope(|world, scenes: Mut<Assets<Scene>>| {
+            for id in modified {
+                let (Some(scene), Some(old)) = (scenes.get(id), snapshots.0.get_mut(&id)) else {
+                    continue;
+                };
+                let new = Snapshot::new(scene, &registry);
+                let reloadable = roots.iter().filter(|r| r.1 == id && r.2).map(|r| r.0);
+                let bundles = bundles.iter().filter(|b| b.1 == id && b.3).map(|b| b.0);
+                for root in reloadable.chain(bundles) {
+                    let mut instance = world.entity_mut(root).take::<Instance>().unwrap();
+                    let entities = &mut instance.entities;
+                    let hooks = new.hooks(plugin_hooks);
+                    apply(world, instance.root, old, &new, entities, &registry, hooks);
+                    world.entity_mut(root).insert(instance);
+                }
+                *old = new;
+            }
+            for &(root, id, _) in roots.iter().filter(|r| !r.2) {
+                let Some(scene) = scenes.get(id) else {
+                    continue;
+                };
+                let new = snapshots
+                    .0
+                    .entry(id)
+                    .or_insert_with(|| Snapshot::new(scene, &registry));
+                let mut instance = Instance { root, entities: Vec::new() };
+                let (empty, entities) = (Snapshot::default(), &mut instance.entities);
+                let hooks = new.hooks(plugin_hooks);
+                apply(world, root, &empty, new, entities, &registry, hooks);
+                // `root` stands for the synthetic root, the first entity.
+                let entity_map = instance.entity_map(root);
+                world.entity_mut(root).insert(instance);
+                world.send_event(HollowSceneSpawned { root, entity_map });
+            }
+            // Track the hollow scenes bevy spawned, to update them later.
+            for &(bundle, id, instance_id, _) in bundles.iter().filter(|b| !b.3) {
+                let Some(scene) = scenes.get(id) else {
+                    continue;
+                };
+                let mut entities = scene.world.iter_entities();
+                if !entities.any(|e| e.contains::<HollowSceneRoot>()) {
+                    continue;
+                }
+                let spawner = world.resource::<SceneSpawner>();
+                if !spawner.instance_is_ready(instance_id) {
+                    continue;
+                }
+                let spawned = spawner.iter_instance_entities(instance_id).collect();
+                let Some(instance) = spawned_instance(world, &spawned) else {
+                    continue;
+                };
+                let snapshot = snapshots
+                    .0
+                    .entry(id)
+                    .or_insert_with(|| Snapshot::new(scene, &registry));
+                // Bevy already spawned the whole instance, run all the hooks
+                // of each entity.
+                let entity_map = instance.entity_map(instance.root);
+                if let Some(hooks) = snapshot.hooks(plugin_hooks) {
+                    for entity in &entity_map {
+                        hooks.run_all(&mut world.entity_mut(*entity));
+                    }
+                }
+                world.entity_mut(bundle).insert(instance);
+                let root = bundle;
+                world.send_event(HollowSceneSpawned { root, entity_map });
+            }
+        });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+    use bevy::reflect::TypeRegistry;
+
+    use super::{apply, Snapshot};
+    use crate::HollowSceneRoot;
+
+    #[derive(Component, Reflect, Default, Debug, PartialEq)]
+    #[reflect(Component)]
+    struct Health(u32);
+
+    #[derive(Component, Reflect, Default, Debug, PartialEq)]
+    #[reflect(Component)]
+    struct Label(String);
+
+    #[derive(Component)]
+    struct Gameplay;
+
+    fn registry() -> TypeRegistry {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Health>();
+        registry.register::<Label>();
+        registry.register::<Name>();
+        registry
+    }
+    // Spawn `entities` as (components, parent index) in a new scene.
+    fn scene(entities: &[(u32, Option<&str>, Option<usize>)]) -> Scene {
+        let mut world = World::new();
+        let mut ids = Vec::new();
+        for &(health, label, parent) in entities {
+            let mut entity = world.spawn(Health(health));
+            if let Some(label) = label {
+                entity.insert(Label(label.to_string()));
+            }
+            if let Some(parent) = parent {
+                entity.set_parent(ids[parent]);
+            }
+            ids.push(entity.id());
+        }
+        Scene::new(world)
+    }
+
+    #[test]
+    fn update_in_place() {
+        let registry = registry();
+        let mut world = World::new();
+        let root = world.spawn_empty().id();
+        let mut instance = Vec::new();
+
+        let old = scene(&[(1, Some("a"), None), (2, None, Some(0))]);
+        let old = Snapshot::new(&old, &registry);
+        apply(
+            &mut world,
+            root,
+            &Snapshot::default(),
+            &old,
+            &mut instance,
+            &registry,
+            None,
+        );
+
+        let a = instance[0];
+        let b = instance[1];
+        assert_eq!(world.get::<Parent>(a).map(Parent::get), Some(root));
+        assert_eq!(world.get::<Parent>(b).map(Parent::get), Some(a));
+        world.entity_mut(a).insert(Gameplay);
+        world.entity_mut(b).insert(Health(10));
+
+        let new = scene(&[(3, None, None), (2, None, Some(0)), (4, Some("c"), Some(0))]);
+        let new = Snapshot::new(&new, &registry);
+        apply(&mut world, root, &old, &new, &mut instance, &registry, None);
+
+        assert_eq!(instance[0], a);
+        assert_eq!(instance[1], b);
+        assert_eq!(world.get::<Health>(a), Some(&Health(3)));
+        assert_eq!(world.get::<Label>(a), None);
+        assert!(world.get::<Gameplay>(a).is_some());
+        // Unchanged in the scene, so the runtime value is kept.
+        assert_eq!(world.get::<Health>(b), Some(&Health(10)));
+        let c = instance[2];
+        assert_eq!(world.get::<Label>(c), Some(&Label("c".to_string())));
+        assert_eq!(world.get::<Parent>(c).map(Parent::get), Some(a));
+
+        let newer = scene(&[(3, None, None)]);
+        let newer = Snapshot::new(&newer, &registry);
+        apply(
+            &mut world,
+            root,
+            &new,
+            &newer,
+            &mut instance,
+            &registry,
+            None,
+        );
+
+        assert_eq!(instance, [a]);
+        assert!(world.get_entity(b).is_none());
+        assert!(world.get_entity(c).is_none());
+        assert!(world.get::<Children>(a).map_or(true, |c| c.is_empty()));
+        assert!(world.get::<Gameplay>(a).is_some());
+    }
+
+    #[test]
+    fn insert_named_entity() {
+        let registry = registry();
+        let mut world = World::new();
+        let root = world.spawn_empty().id();
+        let mut instance = Vec::new();
+        let named = |names: &[&str]| {
+            let mut world = World::new();
+            for (i, name) in names.iter().enumerate() {
+                world.spawn((Health(i as u32), Name::new(name.to_string())));
+            }
+            Snapshot::new(&Scene::new(world), &registry)
+        };
+        let live_named = |world: &mut World, name: &str| {
+            let mut query = world.query::<(Entity, &Name)>();
+            let mut entities = query.iter(world).filter(|(_, n)| n.as_str() == name);
+            entities.next().map(|(e, _)| e)
+        };
+
+        let old = named(&["a", "b", "c"]);
+        apply(
+            &mut world,
+            root,
+            &Snapshot::default(),
+            &old,
+            &mut instance,
+            &registry,
+            None,
+        );
+        let [a, b, c] = ["a", "b", "c"].map(|name| live_named(&mut world, name).unwrap());
+        world.entity_mut(c).insert(Gameplay);
+
+        let new = named(&["a", "x", "b", "c"]);
+        apply(&mut world, root, &old, &new, &mut instance, &registry, None);
+
+        assert_eq!(live_named(&mut world, "a"), Some(a));
+        assert_eq!(live_named(&mut world, "b"), Some(b));
+        assert_eq!(live_named(&mut world, "c"), Some(c));
+        assert!(world.get::<Gameplay>(c).is_some());
+        assert_eq!(world.get::<Health>(c), Some(&Health(3)));
+        let x = live_named(&mut world, "x").unwrap();
+        assert_eq!(world.get::<Health>(x), Some(&Health(1)));
+        assert_eq!(world.get::<Parent>(x).map(Parent::get), Some(root));
+        assert_eq!(world.query::<&Name>().iter(&world).count(), 4);
+    }
+
+    #[test]
+    fn insert_unnamed_entity() {
+        let registry = registry();
+        let mut world = World::new();
+        let root = world.spawn_empty().id();
+        let mut instance = Vec::new();
+        let snapshot = |healths: &[u32]| {
+            let entities: Vec<(_, Option<&str>, Option<usize>)> =
+                healths.iter().map(|h| (*h, None, None)).collect();
+            Snapshot::new(&scene(&entities), &registry)
+        };
+
+        let old = snapshot(&[1, 2, 3]);
+        apply(
+            &mut world,
+            root,
+            &Snapshot::default(),
+            &old,
+            &mut instance,
+            &registry,
+            None,
+        );
+        let [a, b, c] = [instance[0], instance[1], instance[2]];
+        world.entity_mut(b).insert(Gameplay);
+        world.entity_mut(c).insert(Gameplay);
+
+        // Only the inserted entity is spawned, the following ones keep their
+        // runtime components.
+        let new = snapshot(&[1, 9, 2, 3]);
+        apply(&mut world, root, &old, &new, &mut instance, &registry, None);
+
+        let x = instance[1];
+        assert_eq!(instance, [a, x, b, c]);
+        assert!(![a, b, c].contains(&x));
+        assert_eq!(world.get::<Health>(x), Some(&Health(9)));
+        assert!(world.get::<Gameplay>(x).is_none());
+        assert!(world.get::<Gameplay>(b).is_some());
+        assert!(world.get::<Gameplay>(c).is_some());
+        assert_eq!(world.query::<&Health>().iter(&world).count(), 4);
+
+        // Removing one despawns it, and changing one updates it in place.
+        let newer = snapshot(&[1, 9, 5]);
+        apply(
+            &mut world,
+            root,
+            &new,
+            &newer,
+            &mut instance,
+            &registry,
+            None,
+        );
+
+        assert_eq!(instance, [a, x, b]);
+        assert!(world.get_entity(c).is_none());
+        assert_eq!(world.get::<Health>(b), Some(&Health(5)));
+        assert!(world.get::<Gameplay>(b).is_some());
+    }
+
+    #[test]
+    fn skip_hollow_scene_roots() {
+        let registry = registry();
+        let mut world = World::new();
+        let wrapper = world.spawn(HollowSceneRoot::default()).id();
+        let synthetic = world.spawn(Health(0)).set_parent(wrapper).id();
+        let content = world.spawn(Health(1)).set_parent(synthetic).id();
+        world.spawn(Health(2)).set_parent(content);
+
+        let snapshot = Snapshot::new(&Scene::new(world), &registry);
+
+        let health = |i: usize| snapshot.entities[i].components.values().next().unwrap();
+        assert_eq!(snapshot.entities.len(), 2);
+        assert_eq!(health(0).downcast_ref::<Health>(), Some(&Health(1)));
+        assert_eq!(health(1).downcast_ref::<Health>(), Some(&Health(2)));
+        assert_eq!(snapshot.entities[1].parent, Some(0));
+    }
+}
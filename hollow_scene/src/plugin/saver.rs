@@ -1,17 +1,58 @@
-use std::marker::PhantomData;
-
 use bevy::{
     asset::{io::Writer, saver::AssetSaver, AsyncWriteExt},
-    prelude::{info, AppTypeRegistry, FromWorld, World},
+    ecs::query::ReadOnlyWorldQuery,
+    prelude::{info, warn, AppTypeRegistry, World},
     scene::Scene,
     utils::BoxedFuture,
 };
 use rkyv::ser::{serializers::AllocSerializer, Serializer};
 
-use super::{loader::Loader, processor::Format, RkyvTypeNonsense};
-use crate::{entity::Inlines, entity::Tables, FastScene};
+use super::convert::{lost_components, LostComponent};
+use super::loader::{Loader, LoaderSettings};
+use super::{processor::Format, RkyvTypeNonsense};
+use crate::entity::{Inlines, ParInsert, Tables};
+use crate::header::{self, Header};
+use crate::required::RequiredBundles;
+use crate::schema::Schema;
+use crate::{FastScene, SaveOptions};
 
-pub struct Saver<Ts, Is>(Option<AppTypeRegistry>, PhantomData<fn(Ts, Is)>);
+/// How to convert a bevy `Scene` into a [`FastScene`].
+pub(crate) struct FromBevy<Ts: Tables, Is> {
+    filtered: fn(&mut Scene, SaveOptions, Option<ParInsert<Ts>>) -> FastScene<Ts, Is>,
+    /// Fill tables in parallel when [`SaveOptions::parallel`] is set, `None`
+    /// when the plugin wasn't built with `with_parallel_save`.
+    pub(crate) par_insert: Option<ParInsert<Ts>>,
+}
+impl<Ts: Tables, Is: Inlines> FromBevy<Ts, Is> {
+    /// Only keep entities matching `F`, see [`FastScene::from_bevy_filtered`].
+    pub(crate) fn filtered<F: ReadOnlyWorldQuery>(self) -> Self {
+        FromBevy { filtered: FastScene::from_bevy_with::<F>, ..self }
+    }
+    pub(crate) fn call(self, scene: &mut Scene, options: SaveOptions) -> FastScene<Ts, Is> {
+        (self.filtered)(scene, options, self.par_insert)
+    }
+}
+impl<Ts: Tables, Is: Inlines> Default for FromBevy<Ts, Is> {
+    fn default() -> Self {
+        FromBevy {
+            filtered: FastScene::from_bevy_with::<()>,
+            par_insert: None,
+        }
+    }
+}
+impl<Ts: Tables, Is> Clone for FromBevy<Ts, Is> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<Ts: Tables, Is> Copy for FromBevy<Ts, Is> {}
+
+pub struct Saver<Ts: Tables, Is: Inlines> {
+    registry: Option<AppTypeRegistry>,
+    from_bevy: FromBevy<Ts, Is>,
+    /// The required bundles of the plugin, with the default built-in ones.
+    required: RequiredBundles,
+}
 impl<Ts: Tables + 'static, Is: Inlines + 'static> AssetSaver for Saver<Ts, Is>
 where
     Ts::Keys: RkyvTypeNonsense,
@@ -20,7 +61,7 @@ where
 {
     type Asset = Scene;
 
-    type Settings = Format;
+    type Settings = SaveOptions;
 
     type OutputLoader = Loader<Ts, Is>;
 
@@ -28,28 +69,42 @@ where
         &'a self,
         writer: &'a mut Writer,
         asset: &'a Scene,
-        _: &'a Format,
-    ) -> BoxedFuture<'a, Result<Format, anyhow::Error>> {
+        options: &'a SaveOptions,
+    ) -> BoxedFuture<'a, Result<LoaderSettings, anyhow::Error>> {
         Box::pin(async move {
             info!("Saving a scene as hollow_bvyfst");
-            let bytes = if let Some(registry) = &self.0 {
+            let lost = lost_components(&asset.world, &Schema::of::<Ts, Is>(), &self.required);
+            check_lost(&lost, *options)?;
+            if !lost.is_empty() {
+                warn!(
+                    "Components not in the Plugin! schema are not saved: {}",
+                    listing(&lost)
+                );
+            }
+            let bytes = if let Some(registry) = &self.registry {
                 let mut scene_world = asset.clone_with(registry)?;
-                let fast_scene = FastScene::<Ts, Is>::from_bevy(&mut scene_world);
-                let mut serializer = AllocSerializer::<1024>::default();
-                serializer.serialize_value(&fast_scene)?;
-                serializer.into_serializer().into_inner()
+                to_bytes(&mut scene_world, *options, self.from_bevy)?
             } else {
                 return Err(anyhow::anyhow!(
                     "The appregistry doesn't exist, can't save scenes"
                 ));
             };
-            writer.write(&bytes).await?;
-            Ok(Format::Fast)
+            writer.write_all(&bytes).await?;
+            let chunked = options.chunked;
+            Ok(LoaderSettings {
+                format: Format::Fast,
+                chunked,
+                ..Default::default()
+            })
         })
     }
 }
-impl<Ts: Tables + 'static, Is: Inlines + 'static> FromWorld for Saver<Ts, Is> {
-    fn from_world(world: &mut World) -> Self {
+impl<Ts: Tables + 'static, Is: Inlines + 'static> Saver<Ts, Is> {
+    pub(super) fn new(
+        world: &mut World,
+        from_bevy: FromBevy<Ts, Is>,
+        required: RequiredBundles,
+    ) -> Self {
         let registry = world.get_resource::<AppTypeRegistry>();
         if registry.is_none() {
             info!(
@@ -57,6 +112,139 @@ impl<Ts: Tables + 'static, Is: Inlines + 'static> FromWorld for Saver<Ts, Is> {
                 Scenes won't be saved in the hllwfstbvy format."
             );
         };
-        Saver(registry.map(Clone::clone), PhantomData)
+        Saver {
+            registry: registry.map(Clone::clone),
+            from_bevy,
+            required,
+        }
+    }
+}
+
+/// Fail with the list of `lost` components if `options` is
+/// [`SaveOptions::strict`].
+pub(crate) fn check_lost(lost: &[LostComponent], options: SaveOptions) -> anyhow::Result<()> {
+    if options.strict && !lost.is_empty() {
+        anyhow::bail!(
+            "The scene has components not in the Plugin! schema: {}",
+            listing(lost)
+        );
+    }
+    Ok(())
+}
+fn listing(lost: &[LostComponent]) -> String {
+    let lost: Vec<_> = lost.iter().map(LostComponent::to_string).collect();
+    lost.join(", ")
+}
+
+/// Convert `scene` with `from_bevy` and serialize it, header included.
+pub(crate) fn to_bytes<Ts: Tables, Is: Inlines>(
+    scene: &mut Scene,
+    options: SaveOptions,
+    from_bevy: FromBevy<Ts, Is>,
+) -> anyhow::Result<Vec<u8>>
+where
+    Ts::Keys: RkyvTypeNonsense,
+    Ts: RkyvTypeNonsense,
+    Is: RkyvTypeNonsense,
+{
+    let fast_scene = from_bevy.call(scene, options);
+    let mut serializer = AllocSerializer::<1024>::default();
+    serializer.serialize_value(&fast_scene)?;
+    let payload = serializer.into_serializer().into_inner();
+
+    let header = Header::new(&Schema::of::<Ts, Is>());
+    let header = header.with_auto(&fast_scene.tables.auto_storages())?;
+    let mut bytes = Vec::with_capacity(header::LEN + payload.len());
+    bytes.extend_from_slice(&header.to_bytes());
+    bytes.extend_from_slice(&payload);
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::asset::{io::Writer, saver::AssetSaver};
+    use bevy::prelude::*;
+    use bevy::tasks::TaskPool;
+
+    use super::{check_lost, FromBevy, Saver};
+    use crate::plugin::convert::{lost_components, LostComponent};
+    use crate::required::RequiredBundles;
+    use crate::{proxy::Id, schema::Schema, HCons, Inline, SaveOptions};
+    use crate::{Archive, Deserialize, Serialize};
+
+    #[derive(Component, Clone, Archive, Deserialize, Serialize)]
+    struct Kept;
+
+    #[derive(Component)]
+    struct Dropped;
+
+    #[derive(Component, Default)]
+    struct Restored;
+
+    type Inlines = HCons<Inline<Id<Kept>>, ()>;
+
+    #[test]
+    fn strict_lost_components() {
+        let mut world = World::new();
+        world.spawn((Kept, Dropped)).with_children(|parent| {
+            parent.spawn(Dropped);
+        });
+        let schema = Schema::of::<(), Inlines>();
+        let lost = lost_components(&world, &schema, &RequiredBundles::default());
+        let name = std::any::type_name::<Dropped>().to_string();
+        assert_eq!(lost, [LostComponent { name, count: 2 }]);
+
+        assert!(check_lost(&lost, SaveOptions::default()).is_ok());
+        let strict = SaveOptions { strict: true, ..default() };
+        let error = check_lost(&lost, strict).unwrap_err().to_string();
+        assert!(error.ends_with("Dropped (2 entities)"), "{error}");
+        assert!(check_lost(&[], strict).is_ok());
+    }
+
+    #[test]
+    fn required_are_not_lost() {
+        let mut world = World::new();
+        world.spawn((Kept, Restored));
+        world.spawn(Restored);
+        let schema = Schema::of::<(), Inlines>();
+
+        let lost = lost_components(&world, &schema, &RequiredBundles::default());
+        let name = std::any::type_name::<Restored>().to_string();
+        assert_eq!(lost, [LostComponent { name: name.clone(), count: 2 }]);
+
+        // Only entities with the rule's trigger get `Restored` back, the
+        // root bundle is never inserted on the scene's entities.
+        let mut required = RequiredBundles::with_defaults();
+        required.add::<Kept, Restored>();
+        required.set_root::<Restored>();
+        let lost = lost_components(&world, &schema, &required);
+        assert_eq!(lost, [LostComponent { name, count: 1 }]);
+    }
+
+    #[test]
+    fn strict_save_writes_nothing() {
+        let saver = Saver::<(), Inlines> {
+            registry: Some(AppTypeRegistry::default()),
+            from_bevy: FromBevy::default(),
+            required: RequiredBundles::with_defaults(),
+        };
+        // `Transform` isn't in the schema, so the transform rule doesn't
+        // insert `GlobalTransform` back either.
+        let mut world = World::new();
+        world.spawn((Kept, Transform::default(), GlobalTransform::default()));
+        let scene = Scene::new(world);
+        let strict = SaveOptions { strict: true, ..default() };
+
+        let mut bytes = Vec::new();
+        let writer: &mut Writer = &mut bytes;
+        let results = TaskPool::new().scope(|scope| {
+            scope.spawn(saver.save(writer, &scene, &strict));
+        });
+        let error = results.into_iter().next().unwrap().unwrap_err();
+        let error = error.to_string();
+        let lost = |name: &str| error.contains(&format!("{name} (1 entities)"));
+        assert!(lost(std::any::type_name::<Transform>()), "{error}");
+        assert!(lost(std::any::type_name::<GlobalTransform>()), "{error}");
+        assert!(bytes.is_empty());
     }
 }
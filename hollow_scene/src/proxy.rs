@@ -1,10 +1,44 @@
 //! Proxy types to ser/deser components with a separate layout
+//!
+//! Proxies for common bevy components are behind cargo features:
+//!
+//! - `basic_proxies`: [`TransformProxy`], [`NameProxy`], and
+//!   [`QuantizedTransform`], a lossy transform proxy half the size of
+//!   [`TransformProxy`]. [`TransformProxy`] is also a
+//!   [`crate::ColumnarProxy`].
+//! - `render_proxies`: [`VisibilityProxy`], [`RenderLayersProxy`],
+//!   [`Camera3dProxy`] and [`ProjectionProxy`].
+//! - `light_proxies`: [`PointLightProxy`], [`SpotLightProxy`] and
+//!   [`DirectionalLightProxy`].
+
+#[cfg(feature = "basic_proxies")]
+mod basic;
+#[cfg(feature = "light_proxies")]
+mod light;
+#[cfg(feature = "basic_proxies")]
+mod quantized;
+#[cfg(feature = "render_proxies")]
+mod render;
+
+use std::marker::PhantomData;
 
 use bevy::prelude as bevy;
 use rkyv::{Archive, Deserialize, Infallible, Serialize};
 
 use crate::ArchiveProxy;
 
+#[cfg(feature = "basic_proxies")]
+pub use basic::{ArchivedTransformColumns, NameProxy, TransformColumns, TransformProxy};
+#[cfg(feature = "light_proxies")]
+pub use light::{DirectionalLightProxy, PointLightProxy, ShadowProxy, SpotLightProxy};
+#[cfg(feature = "basic_proxies")]
+pub use quantized::{HalfFloat, QuantizedScale, QuantizedTransform, TranslationEncoding};
+#[cfg(feature = "render_proxies")]
+pub use render::{
+    Camera3dProxy, ClearColorProxy, ProjectionProxy, RenderLayersProxy, ScalingModeProxy,
+    VisibilityProxy,
+};
+
 /// A way to automatically implement [`ArchiveProxy`] for your own type, if you
 /// can implement `Archive` and `Clone` on them.
 #[derive(Clone, Copy, Default, Archive, Deserialize, Serialize)]
@@ -28,3 +62,80 @@ impl<T: PartialEq> PartialEq<T> for Id<T> {
         &self.0 == other
     }
 }
+
+/// Store a `Handle<A>` as the path of the asset it points to.
+///
+/// The handle is loaded with the `AssetServer` when spawning the scene with
+/// the hollow scene loader. Handles without a path, such as handles to assets
+/// added at runtime, are not saved.
+#[derive(Archive, Deserialize, Serialize)]
+pub struct AssetPath<A>(pub Option<String>, PhantomData<fn() -> A>);
+
+impl<A: bevy::Asset> ArchiveProxy for AssetPath<A> {
+    type Target = bevy::Handle<A>;
+    /// Always the default `Handle`, use [`ArchiveProxy::to_target_in`] to load
+    /// the handle.
+    fn to_target(_: &Self::Archived) -> Self::Target {
+        bevy::Handle::default()
+    }
+    fn to_target_in(archive: &Self::Archived, world: Option<&bevy::World>) -> Self::Target {
+        let server = world.and_then(|w| w.get_resource::<bevy::AssetServer>());
+        match (archive.0.as_ref(), server) {
+            (Some(path), Some(server)) => server.load(path.as_str()),
+            _ => bevy::Handle::default(),
+        }
+    }
+    fn from_target(target: &Self::Target) -> Self {
+        AssetPath(target.path().map(|p| p.to_string()), PhantomData)
+    }
+}
+impl<A: bevy::Asset> PartialEq<bevy::Handle<A>> for AssetPath<A> {
+    fn eq(&self, other: &bevy::Handle<A>) -> bool {
+        self.0 == other.path().map(|p| p.to_string())
+    }
+}
+
+/// Save `target` with the `P` proxy, and spawn it back.
+#[cfg(all(test, any(feature = "basic_proxies", feature = "render_proxies")))]
+fn roundtrip<P>(target: &P::Target) -> P::Target
+where
+    P: ArchiveProxy + Serialize<rkyv::ser::serializers::AllocSerializer<256>>,
+{
+    let bytes = rkyv::to_bytes::<_, 256>(&P::from_target(target)).unwrap();
+    let archived = unsafe { rkyv::archived_root::<P>(&bytes) };
+    P::to_target(archived)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{core::TaskPoolPlugin, prelude::*, reflect::TypePath};
+
+    use super::AssetPath;
+    use crate::ArchiveProxy;
+
+    #[derive(Asset, TypePath)]
+    struct Model;
+
+    #[test]
+    fn asset_path() {
+        let mut app = App::new();
+        app.add_plugin(TaskPoolPlugin::default())
+            .add_plugin(AssetPlugin::default())
+            .init_asset::<Model>();
+        let world = &mut app.world;
+        let handle = world
+            .resource::<AssetServer>()
+            .load::<Model>("models/house.glb#Mesh0");
+
+        let bytes = rkyv::to_bytes::<_, 256>(&AssetPath::from_target(&handle)).unwrap();
+        let archived = unsafe { rkyv::archived_root::<AssetPath<Model>>(&bytes) };
+        let path = archived.0.as_ref().map(|p| p.as_str());
+        assert_eq!(path, Some("models/house.glb#Mesh0"));
+        assert_eq!(AssetPath::to_target_in(archived, Some(world)), handle);
+        assert_eq!(AssetPath::to_target_in(archived, None), Handle::default());
+
+        // Added at runtime, so there is no path to save.
+        let added = world.resource_mut::<Assets<Model>>().add(Model);
+        assert!(AssetPath::from_target(&added).0.is_none());
+    }
+}
@@ -0,0 +1,162 @@
+//! Proxies for `Transform` and `Name`.
+//!
+//! `TransformProxy` can also be stored in `Columnar` storage.
+
+use bevy::prelude as bevy;
+use rkyv::{Archive, Deserialize, Serialize};
+
+use crate::{ArchiveProxy, ColumnarProxy};
+
+/// Store a `Transform` as 10 `f32`s, 40 bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Default, Archive, Deserialize, Serialize)]
+pub struct TransformProxy {
+    pub translation: [f32; 3],
+    pub rotation: [f32; 4],
+    pub scale: [f32; 3],
+}
+impl ArchiveProxy for TransformProxy {
+    type Target = bevy::Transform;
+
+    fn to_target(archive: &Self::Archived) -> Self::Target {
+        bevy::Transform {
+            translation: archive.translation.into(),
+            rotation: bevy::Quat::from_array(archive.rotation),
+            scale: archive.scale.into(),
+        }
+    }
+    fn from_target(target: &Self::Target) -> Self {
+        TransformProxy {
+            translation: target.translation.into(),
+            rotation: target.rotation.into(),
+            scale: target.scale.into(),
+        }
+    }
+}
+impl PartialEq<bevy::Transform> for TransformProxy {
+    fn eq(&self, other: &bevy::Transform) -> bool {
+        self == &Self::from_target(other)
+    }
+}
+
+/// The columns of [`TransformProxy`] in `Columnar` storage.
+#[derive(Clone, Debug, Default, Archive, Deserialize, Serialize)]
+#[archive_attr(repr(C))]
+pub struct TransformColumns {
+    pub translation: Vec<[f32; 3]>,
+    pub rotation: Vec<[f32; 4]>,
+    pub scale: Vec<[f32; 3]>,
+}
+impl ColumnarProxy for TransformProxy {
+    type Columns = TransformColumns;
+
+    fn push(self, columns: &mut TransformColumns) {
+        columns.translation.push(self.translation);
+        columns.rotation.push(self.rotation);
+        columns.scale.push(self.scale);
+    }
+    fn len(columns: &ArchivedTransformColumns) -> usize {
+        let len = columns.translation.len().min(columns.rotation.len());
+        len.min(columns.scale.len())
+    }
+    fn to_target_at(
+        columns: &ArchivedTransformColumns,
+        index: usize,
+        _: Option<&bevy::World>,
+    ) -> bevy::Transform {
+        bevy::Transform {
+            translation: columns.translation[index].into(),
+            rotation: bevy::Quat::from_array(columns.rotation[index]),
+            scale: columns.scale[index].into(),
+        }
+    }
+    fn to_targets(
+        columns: &ArchivedTransformColumns,
+        len: usize,
+        _: Option<&bevy::World>,
+        out: &mut Vec<bevy::Transform>,
+    ) {
+        let translations = columns.translation.iter();
+        let fields = translations
+            .zip(columns.rotation.iter())
+            .zip(columns.scale.iter());
+        out.extend(
+            fields
+                .take(len)
+                .map(|((translation, rotation), scale)| bevy::Transform {
+                    translation: (*translation).into(),
+                    rotation: bevy::Quat::from_array(*rotation),
+                    scale: (*scale).into(),
+                }),
+        );
+    }
+}
+
+/// Store a `Name` as a string.
+#[derive(Clone, Debug, PartialEq, Default, Archive, Deserialize, Serialize)]
+pub struct NameProxy(pub String);
+
+impl ArchiveProxy for NameProxy {
+    type Target = bevy::Name;
+
+    fn to_target(archive: &Self::Archived) -> Self::Target {
+        bevy::Name::new(archive.0.as_str().to_string())
+    }
+    fn from_target(target: &Self::Target) -> Self {
+        NameProxy(target.as_str().to_string())
+    }
+}
+impl PartialEq<bevy::Name> for NameProxy {
+    fn eq(&self, other: &bevy::Name) -> bool {
+        self.0 == other.as_str()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use super::{NameProxy, TransformColumns, TransformProxy};
+    use crate::{proxy::roundtrip, ArchiveProxy, ColumnarProxy};
+
+    #[test]
+    fn transform() {
+        let transform = Transform::from_xyz(1.0, -2.0, 3.5)
+            .with_rotation(Quat::from_euler(EulerRot::XYZ, 0.3, 1.2, -2.0))
+            .with_scale(Vec3::new(1.0, 2.0, 0.5));
+        assert_eq!(roundtrip::<TransformProxy>(&transform), transform);
+        assert!(TransformProxy::from_target(&transform) == transform);
+    }
+
+    #[test]
+    fn transform_columns() {
+        let transforms = [
+            Transform::from_xyz(1.0, -2.0, 3.5),
+            Transform::from_scale(Vec3::splat(2.0)).with_rotation(Quat::from_rotation_y(0.5)),
+        ];
+        let mut columns = TransformColumns::default();
+        transforms
+            .iter()
+            .for_each(|t| TransformProxy::from_target(t).push(&mut columns));
+        // A corrupted file, with a missing scale.
+        columns.scale.pop();
+        let bytes = rkyv::to_bytes::<_, 256>(&columns).unwrap();
+        let archived = unsafe { rkyv::archived_root::<TransformColumns>(&bytes) };
+
+        assert_eq!(TransformProxy::len(archived), 1);
+        let mut targets = Vec::new();
+        TransformProxy::to_targets(archived, 1, None, &mut targets);
+        assert_eq!(targets, transforms[..1]);
+        assert_eq!(
+            TransformProxy::to_target_at(archived, 0, None),
+            transforms[0]
+        );
+    }
+
+    #[test]
+    fn name() {
+        let name = Name::new("Level/Doors/Door_03");
+        assert_eq!(roundtrip::<NameProxy>(&name), name);
+        assert!(NameProxy::from_target(&name) == name);
+        assert!(NameProxy::default() == Name::new(""));
+    }
+}
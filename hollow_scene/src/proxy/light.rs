@@ -0,0 +1,200 @@
+//! Proxies for `PointLight`, `SpotLight` and `DirectionalLight`.
+//!
+//! Colors are stored as sRGB `[r, g, b, a]`.
+
+use bevy::prelude as bevy;
+use rkyv::{Archive, Deserialize, Serialize};
+
+use super::render::to_color;
+use crate::ArchiveProxy;
+
+/// The shadow settings shared by all lights.
+#[derive(Clone, Copy, Debug, PartialEq, Archive, Deserialize, Serialize)]
+pub struct ShadowProxy {
+    pub enabled: bool,
+    pub depth_bias: f32,
+    pub normal_bias: f32,
+}
+
+/// Store a `PointLight`.
+#[derive(Clone, Copy, Debug, PartialEq, Archive, Deserialize, Serialize)]
+pub struct PointLightProxy {
+    pub color: [f32; 4],
+    pub intensity: f32,
+    pub range: f32,
+    pub radius: f32,
+    pub shadows: ShadowProxy,
+}
+impl ArchiveProxy for PointLightProxy {
+    type Target = bevy::PointLight;
+
+    fn to_target(archive: &Self::Archived) -> Self::Target {
+        bevy::PointLight {
+            color: to_color(archive.color),
+            intensity: archive.intensity,
+            range: archive.range,
+            radius: archive.radius,
+            shadows_enabled: archive.shadows.enabled,
+            shadow_depth_bias: archive.shadows.depth_bias,
+            shadow_normal_bias: archive.shadows.normal_bias,
+        }
+    }
+    fn from_target(target: &Self::Target) -> Self {
+        PointLightProxy {
+            color: target.color.as_rgba_f32(),
+            intensity: target.intensity,
+            range: target.range,
+            radius: target.radius,
+            shadows: ShadowProxy {
+                enabled: target.shadows_enabled,
+                depth_bias: target.shadow_depth_bias,
+                normal_bias: target.shadow_normal_bias,
+            },
+        }
+    }
+}
+impl PartialEq<bevy::PointLight> for PointLightProxy {
+    fn eq(&self, other: &bevy::PointLight) -> bool {
+        self == &Self::from_target(other)
+    }
+}
+
+/// Store a `SpotLight`.
+#[derive(Clone, Copy, Debug, PartialEq, Archive, Deserialize, Serialize)]
+pub struct SpotLightProxy {
+    pub color: [f32; 4],
+    pub intensity: f32,
+    pub range: f32,
+    pub radius: f32,
+    pub shadows: ShadowProxy,
+    pub outer_angle: f32,
+    pub inner_angle: f32,
+}
+impl ArchiveProxy for SpotLightProxy {
+    type Target = bevy::SpotLight;
+
+    fn to_target(archive: &Self::Archived) -> Self::Target {
+        bevy::SpotLight {
+            color: to_color(archive.color),
+            intensity: archive.intensity,
+            range: archive.range,
+            radius: archive.radius,
+            shadows_enabled: archive.shadows.enabled,
+            shadow_depth_bias: archive.shadows.depth_bias,
+            shadow_normal_bias: archive.shadows.normal_bias,
+            outer_angle: archive.outer_angle,
+            inner_angle: archive.inner_angle,
+        }
+    }
+    fn from_target(target: &Self::Target) -> Self {
+        SpotLightProxy {
+            color: target.color.as_rgba_f32(),
+            intensity: target.intensity,
+            range: target.range,
+            radius: target.radius,
+            shadows: ShadowProxy {
+                enabled: target.shadows_enabled,
+                depth_bias: target.shadow_depth_bias,
+                normal_bias: target.shadow_normal_bias,
+            },
+            outer_angle: target.outer_angle,
+            inner_angle: target.inner_angle,
+        }
+    }
+}
+impl PartialEq<bevy::SpotLight> for SpotLightProxy {
+    fn eq(&self, other: &bevy::SpotLight) -> bool {
+        self == &Self::from_target(other)
+    }
+}
+
+/// Store a `DirectionalLight`.
+///
+/// Its direction is the forward direction of the entity's `Transform`. The
+/// shadow cascades are a separate component, `CascadeShadowConfig`.
+#[derive(Clone, Copy, Debug, PartialEq, Archive, Deserialize, Serialize)]
+pub struct DirectionalLightProxy {
+    pub color: [f32; 4],
+    pub illuminance: f32,
+    pub shadows: ShadowProxy,
+}
+impl ArchiveProxy for DirectionalLightProxy {
+    type Target = bevy::DirectionalLight;
+
+    fn to_target(archive: &Self::Archived) -> Self::Target {
+        bevy::DirectionalLight {
+            color: to_color(archive.color),
+            illuminance: archive.illuminance,
+            shadows_enabled: archive.shadows.enabled,
+            shadow_depth_bias: archive.shadows.depth_bias,
+            shadow_normal_bias: archive.shadows.normal_bias,
+        }
+    }
+    fn from_target(target: &Self::Target) -> Self {
+        DirectionalLightProxy {
+            color: target.color.as_rgba_f32(),
+            illuminance: target.illuminance,
+            shadows: ShadowProxy {
+                enabled: target.shadows_enabled,
+                depth_bias: target.shadow_depth_bias,
+                normal_bias: target.shadow_normal_bias,
+            },
+        }
+    }
+}
+impl PartialEq<bevy::DirectionalLight> for DirectionalLightProxy {
+    fn eq(&self, other: &bevy::DirectionalLight) -> bool {
+        self == &Self::from_target(other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use super::{DirectionalLightProxy, PointLightProxy, SpotLightProxy};
+    use crate::proxy::roundtrip;
+
+    const ORANGE: Color = Color::rgba(1.0, 0.5, 0.0, 1.0);
+
+    #[test]
+    fn point_light() {
+        let light = PointLight {
+            color: ORANGE,
+            intensity: 1200.0,
+            range: 30.0,
+            shadows_enabled: true,
+            ..default()
+        };
+        let spawned = roundtrip::<PointLightProxy>(&light);
+        assert!(PointLightProxy::from_target(&light) == spawned);
+        assert_eq!(spawned.color, ORANGE);
+        assert!(spawned.shadows_enabled);
+    }
+
+    #[test]
+    fn spot_light() {
+        let light = SpotLight {
+            color: ORANGE,
+            outer_angle: 0.8,
+            inner_angle: 0.4,
+            ..default()
+        };
+        let spawned = roundtrip::<SpotLightProxy>(&light);
+        assert!(SpotLightProxy::from_target(&light) == spawned);
+        assert_eq!((spawned.outer_angle, spawned.inner_angle), (0.8, 0.4));
+    }
+
+    #[test]
+    fn directional_light() {
+        let light = DirectionalLight {
+            illuminance: 50_000.0,
+            shadow_depth_bias: 0.05,
+            ..default()
+        };
+        let spawned = roundtrip::<DirectionalLightProxy>(&light);
+        assert!(DirectionalLightProxy::from_target(&light) == spawned);
+        assert_eq!(spawned.illuminance, 50_000.0);
+        assert_eq!(spawned.shadow_depth_bias, 0.05);
+    }
+}
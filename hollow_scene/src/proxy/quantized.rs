@@ -0,0 +1,313 @@
+//! A lossy `Transform` proxy, half the size of [`super::TransformProxy`].
+
+use std::f32::consts::{FRAC_1_SQRT_2, SQRT_2};
+use std::marker::PhantomData;
+
+use bevy::prelude as bevy;
+use rkyv::{Archive, Deserialize, Serialize};
+
+use crate::ArchiveProxy;
+
+/// How [`QuantizedTransform`] stores translations.
+///
+/// Implement it on your own type to store translations relative to the
+/// bounding box of your scenes:
+///
+/// ```text
+/// struct Level;
+/// impl TranslationEncoding for Level {
+///     const BOUNDS: Option<([f32; 3], [f32; 3])> = Some(([-512.0; 3], [512.0; 3]));
+/// }
+/// Plugin!(Inline[QuantizedTransform<Level>])
+/// ```
+pub trait TranslationEncoding: 'static {
+    /// The `(min, max)` corners of the box translations are stored in.
+    ///
+    /// With `Some`, each axis is stored as a 16 bits fixed-point value in the
+    /// box, and the maximum error on each axis is `(max - min) / 131070`.
+    /// Translations outside the box are clamped to it.
+    ///
+    /// With `None`, each axis is stored as a half-float. The maximum error is
+    /// `2⁻¹¹` times the value (`0.05` for `100.0`), translations are clamped
+    /// to `±65504`.
+    const BOUNDS: Option<([f32; 3], [f32; 3])> = None;
+}
+
+/// Store translations as half-floats.
+pub struct HalfFloat;
+impl TranslationEncoding for HalfFloat {}
+
+/// The scale of a [`QuantizedTransform`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Archive, Deserialize, Serialize)]
+pub enum QuantizedScale {
+    /// `Vec3::ONE`, stored exactly.
+    One,
+    /// The same half-float scale on all axis.
+    Uniform(u16),
+    /// A half-float scale per axis.
+    NonUniform([u16; 3]),
+}
+
+/// Store a `Transform` in 20 bytes rather than 40, with some precision loss.
+///
+/// - The translation is stored as configured by `T`, see
+///   [`TranslationEncoding::BOUNDS`].
+/// - The rotation is stored with the "smallest three" encoding: the index of
+///   the largest component of the quaternion, and the three others on 10 bits.
+///   The rotation of spawned entities is at most 0.005 radians (0.3°) off.
+/// - The scale is stored as half-floats, with a maximum error of `2⁻¹¹` times
+///   the value. A scale of `Vec3::ONE` is stored exactly, a uniform scale is
+///   stored only once.
+#[derive(Archive, Deserialize, Serialize)]
+pub struct QuantizedTransform<T: TranslationEncoding = HalfFloat> {
+    pub rotation: u32,
+    pub translation: [u16; 3],
+    pub scale: QuantizedScale,
+    _encoding: PhantomData<fn() -> T>,
+}
+impl<T: TranslationEncoding> Clone for QuantizedTransform<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T: TranslationEncoding> Copy for QuantizedTransform<T> {}
+impl<T: TranslationEncoding> PartialEq for QuantizedTransform<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.rotation == other.rotation
+            && self.translation == other.translation
+            && self.scale == other.scale
+    }
+}
+
+impl<T: TranslationEncoding> ArchiveProxy for QuantizedTransform<T> {
+    type Target = bevy::Transform;
+
+    fn to_target(archive: &Self::Archived) -> Self::Target {
+        let translation = match T::BOUNDS {
+            Some((min, max)) => from_fixed(archive.translation, min, max),
+            None => archive.translation.map(from_half),
+        };
+        let scale = match archive.scale {
+            ArchivedQuantizedScale::One => bevy::Vec3::ONE,
+            ArchivedQuantizedScale::Uniform(scale) => bevy::Vec3::splat(from_half(scale)),
+            ArchivedQuantizedScale::NonUniform(scale) => scale.map(from_half).into(),
+        };
+        bevy::Transform {
+            translation: translation.into(),
+            rotation: from_smallest_three(archive.rotation),
+            scale,
+        }
+    }
+    fn from_target(target: &Self::Target) -> Self {
+        let translation = target.translation.to_array();
+        let translation = match T::BOUNDS {
+            Some((min, max)) => to_fixed(translation, min, max),
+            None => translation.map(to_half),
+        };
+        let bevy::Vec3 { x, y, z } = target.scale;
+        let scale = if target.scale == bevy::Vec3::ONE {
+            QuantizedScale::One
+        } else if x == y && y == z {
+            QuantizedScale::Uniform(to_half(x))
+        } else {
+            QuantizedScale::NonUniform([x, y, z].map(to_half))
+        };
+        QuantizedTransform {
+            rotation: to_smallest_three(target.rotation),
+            translation,
+            scale,
+            _encoding: PhantomData,
+        }
+    }
+}
+impl<T: TranslationEncoding> PartialEq<bevy::Transform> for QuantizedTransform<T> {
+    fn eq(&self, other: &bevy::Transform) -> bool {
+        self == &Self::from_target(other)
+    }
+}
+
+/// The bits of the half-float nearest to `value`, clamped to `±65504`.
+fn to_half(value: f32) -> u16 {
+    let sign = ((value.to_bits() >> 16) & 0x8000) as u16;
+    let value = value.abs();
+    if value.is_nan() {
+        return sign | 0x7e00;
+    }
+    let value = value.min(65504.0);
+    // Below 2⁻¹⁴, half-floats are subnormal, with a fixed step of 2⁻²⁴.
+    if value < 2f32.powi(-14) {
+        return sign | (value * 2f32.powi(24)).round() as u16;
+    }
+    let bits = value.to_bits();
+    let exponent = (bits >> 23) + 15 - 127;
+    let mantissa = bits & 0x7f_ffff;
+    let half = (exponent << 10) | (mantissa >> 13);
+    // Round the 13 dropped bits to nearest, ties to even. A carry into the
+    // exponent is the correct rounding.
+    let dropped = mantissa & 0x1fff;
+    let round_up = dropped > 0x1000 || (dropped == 0x1000 && half & 1 == 1);
+    sign | (half + u32::from(round_up)) as u16
+}
+
+fn from_half(half: u16) -> f32 {
+    let sign = if half & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = i32::from((half >> 10) & 0x1f);
+    let mantissa = f32::from(half & 0x3ff);
+    let value = match exponent {
+        0 => mantissa * 2f32.powi(-24),
+        31 if mantissa == 0.0 => f32::INFINITY,
+        31 => f32::NAN,
+        _ => (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    };
+    sign * value
+}
+
+fn to_fixed(value: [f32; 3], min: [f32; 3], max: [f32; 3]) -> [u16; 3] {
+    let axis = |i: usize| {
+        let relative = (value[i] - min[i]) / (max[i] - min[i]);
+        (relative.clamp(0.0, 1.0) * 65535.0).round() as u16
+    };
+    [axis(0), axis(1), axis(2)]
+}
+
+fn from_fixed(value: [u16; 3], min: [f32; 3], max: [f32; 3]) -> [f32; 3] {
+    let axis = |i: usize| min[i] + f32::from(value[i]) / 65535.0 * (max[i] - min[i]);
+    [axis(0), axis(1), axis(2)]
+}
+
+/// The difference between two consecutive 10 bits quaternion components.
+///
+/// The components that are not the largest are in `[-1/√2, 1/√2]`.
+const QUAT_STEP: f32 = SQRT_2 / 1023.0;
+
+fn to_smallest_three(rotation: bevy::Quat) -> u32 {
+    let rotation = rotation.normalize().to_array();
+    let abs = |i: &usize| rotation[*i].abs();
+    // unwrap: the range is not empty
+    let largest = (0..4).max_by(|a, b| abs(a).total_cmp(&abs(b))).unwrap();
+    // `q` and `-q` are the same rotation, make the largest component positive.
+    let sign = rotation[largest].signum();
+
+    let mut bits = largest as u32;
+    for i in (0..4).filter(|i| *i != largest) {
+        let step = ((rotation[i] * sign + FRAC_1_SQRT_2) / QUAT_STEP).round();
+        bits = (bits << 10) | step.clamp(0.0, 1023.0) as u32;
+    }
+    bits
+}
+
+fn from_smallest_three(bits: u32) -> bevy::Quat {
+    let largest = (bits >> 30) as usize;
+    let mut rotation = [0.0; 4];
+    let mut sum_squares = 0.0;
+    for (n, i) in (0..4).filter(|i| *i != largest).enumerate() {
+        let step = (bits >> (20 - 10 * n)) & 0x3ff;
+        rotation[i] = step as f32 * QUAT_STEP - FRAC_1_SQRT_2;
+        sum_squares += rotation[i] * rotation[i];
+    }
+    rotation[largest] = (1.0 - sum_squares).max(0.0).sqrt();
+    bevy::Quat::from_array(rotation).normalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use super::{from_half, to_half, HalfFloat, QuantizedTransform, TranslationEncoding};
+    use crate::proxy::roundtrip;
+
+    struct Level;
+    impl TranslationEncoding for Level {
+        const BOUNDS: Option<([f32; 3], [f32; 3])> = Some(([-512.0, 0.0, -512.0], [512.0; 3]));
+    }
+
+    /// A xorshift PRNG, returning values in `[0, 1)`.
+    struct Random(u32);
+    impl Random {
+        fn next(&mut self) -> f32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 17;
+            self.0 ^= self.0 << 5;
+            (self.0 >> 8) as f32 / 16_777_216.0
+        }
+        fn range(&mut self, min: f32, max: f32) -> f32 {
+            min + self.next() * (max - min)
+        }
+        fn transform(&mut self, max_translation: f32) -> Transform {
+            let mut translation = || self.range(-max_translation, max_translation);
+            let translation = Vec3::new(translation(), translation(), translation());
+            let axis = Vec3::new(
+                self.range(-1., 1.),
+                self.range(-1., 1.),
+                self.range(0.1, 1.),
+            );
+            let rotation = Quat::from_axis_angle(axis.normalize(), self.range(-6.3, 6.3));
+            let scale = match self.next() {
+                x if x < 0.3 => Vec3::ONE,
+                x if x < 0.6 => Vec3::splat(self.range(0.01, 10.0)),
+                _ => Vec3::new(
+                    self.range(0.1, 4.),
+                    self.range(0.1, 4.),
+                    self.range(0.1, 4.),
+                ),
+            };
+            Transform { translation, rotation, scale }
+        }
+    }
+
+    fn assert_half_error(value: Vec3, spawned: Vec3) {
+        let error = (spawned - value).abs();
+        let max_error = value.abs() * 2f32.powi(-11) + 2f32.powi(-25);
+        assert!(error.cmple(max_error).all(), "{value} != {spawned}");
+    }
+
+    #[test]
+    fn half_floats() {
+        assert_eq!(to_half(0.0), 0);
+        assert_eq!(to_half(-0.0), 0x8000);
+        assert_eq!(to_half(1.0), 0x3c00);
+        assert_eq!(to_half(-2.5), 0xc100);
+        assert_eq!(to_half(65504.0), 0x7bff);
+        assert_eq!(to_half(1e9), 0x7bff);
+        assert_eq!(to_half(2f32.powi(-24)), 1);
+        for half in [0, 1, 0x3ff, 0x400, 0x3c00, 0x3555, 0xc100, 0x7bff] {
+            assert_eq!(to_half(from_half(half)), half);
+        }
+        assert!(from_half(to_half(f32::NAN)).is_nan());
+    }
+
+    #[test]
+    fn random_transforms() {
+        let mut random = Random(0x2545_f491);
+        for _ in 0..10_000 {
+            let transform = random.transform(100.0);
+            let spawned = roundtrip::<QuantizedTransform<HalfFloat>>(&transform);
+
+            assert_half_error(transform.translation, spawned.translation);
+            assert_half_error(transform.scale, spawned.scale);
+            let angle = transform.rotation.angle_between(spawned.rotation);
+            assert!(angle < 0.005, "{angle} rad error");
+            if transform.scale == Vec3::ONE {
+                assert_eq!(spawned.scale, Vec3::ONE);
+            }
+        }
+    }
+
+    #[test]
+    fn bounded_translation() {
+        // Half a step of 1024 units on 16 bits, plus `f32` rounding.
+        let max_error = 1024.0 / 131_070.0 + 1e-4;
+        let mut random = Random(0x9e37_79b9);
+        for _ in 0..10_000 {
+            let mut transform = random.transform(512.0);
+            transform.translation.y = transform.translation.y.abs();
+            let spawned = roundtrip::<QuantizedTransform<Level>>(&transform);
+
+            let error = (spawned.translation - transform.translation).abs();
+            assert!(error.max_element() <= max_error, "{error} error");
+        }
+        let outside = Transform::from_xyz(1000.0, -3.0, -512.0);
+        let spawned = roundtrip::<QuantizedTransform<Level>>(&outside);
+        assert_eq!(spawned.translation, Vec3::new(512.0, 0.0, -512.0));
+    }
+}
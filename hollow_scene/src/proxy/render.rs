@@ -0,0 +1,321 @@
+//! Proxies for `Visibility`, `RenderLayers`, `Camera3d` and `Projection`.
+
+use ::bevy::core_pipeline::{clear_color::ClearColorConfig, core_3d::Camera3dDepthLoadOp};
+use ::bevy::render::camera::{OrthographicProjection, PerspectiveProjection, ScalingMode};
+use ::bevy::render::view::RenderLayers;
+use bevy::prelude as bevy;
+use rkyv::{Archive, Deserialize, Serialize};
+
+use crate::ArchiveProxy;
+
+/// A `Color` as sRGB `[r, g, b, a]`.
+pub(super) fn to_color(rgba: [f32; 4]) -> bevy::Color {
+    let [r, g, b, a] = rgba;
+    bevy::Color::rgba(r, g, b, a)
+}
+
+/// Store a `Visibility` as a single byte.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Archive, Deserialize, Serialize)]
+pub enum VisibilityProxy {
+    #[default]
+    Inherited,
+    Hidden,
+    Visible,
+}
+impl ArchiveProxy for VisibilityProxy {
+    type Target = bevy::Visibility;
+
+    fn to_target(archive: &Self::Archived) -> Self::Target {
+        match archive {
+            ArchivedVisibilityProxy::Inherited => bevy::Visibility::Inherited,
+            ArchivedVisibilityProxy::Hidden => bevy::Visibility::Hidden,
+            ArchivedVisibilityProxy::Visible => bevy::Visibility::Visible,
+        }
+    }
+    fn from_target(target: &Self::Target) -> Self {
+        match target {
+            bevy::Visibility::Inherited => VisibilityProxy::Inherited,
+            bevy::Visibility::Hidden => VisibilityProxy::Hidden,
+            bevy::Visibility::Visible => VisibilityProxy::Visible,
+        }
+    }
+}
+impl PartialEq<bevy::Visibility> for VisibilityProxy {
+    fn eq(&self, other: &bevy::Visibility) -> bool {
+        self == &Self::from_target(other)
+    }
+}
+
+/// Store `RenderLayers` as a bit mask, bit `n` is set for layer `n`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Archive, Deserialize, Serialize)]
+pub struct RenderLayersProxy(pub u32);
+
+impl Default for RenderLayersProxy {
+    /// Only the first layer, like `RenderLayers::default()`.
+    fn default() -> Self {
+        RenderLayersProxy(1)
+    }
+}
+impl ArchiveProxy for RenderLayersProxy {
+    type Target = RenderLayers;
+
+    fn to_target(archive: &Self::Archived) -> Self::Target {
+        let mask = archive.0;
+        let layers: Vec<u8> = (0..32).filter(|layer| mask & (1 << layer) != 0).collect();
+        RenderLayers::from_layers(&layers)
+    }
+    fn from_target(target: &Self::Target) -> Self {
+        RenderLayersProxy(target.iter().fold(0, |mask, layer| mask | (1 << layer)))
+    }
+}
+impl PartialEq<RenderLayers> for RenderLayersProxy {
+    fn eq(&self, other: &RenderLayers) -> bool {
+        self == &Self::from_target(other)
+    }
+}
+
+/// How a camera clears its render target, see `ClearColorConfig`.
+#[derive(Clone, Copy, Debug, PartialEq, Default, Archive, Deserialize, Serialize)]
+pub enum ClearColorProxy {
+    #[default]
+    Default,
+    /// sRGB `[r, g, b, a]`.
+    Custom([f32; 4]),
+    None,
+}
+
+/// Store the clear color and depth load operation of a `Camera3d`.
+///
+/// `depth_texture_usages` is not stored, spawned cameras have the default.
+///
+/// A `Camera3d` alone doesn't render anything, the other components of
+/// `Camera3dBundle` are needed. Insert them with [`crate::required`] or
+/// [`crate::hooks`].
+#[derive(Clone, Copy, Debug, PartialEq, Archive, Deserialize, Serialize)]
+pub struct Camera3dProxy {
+    pub clear_color: ClearColorProxy,
+    /// The value to clear the depth buffer with, `None` to load it instead.
+    pub depth_clear: Option<f32>,
+}
+impl ArchiveProxy for Camera3dProxy {
+    type Target = bevy::Camera3d;
+
+    fn to_target(archive: &Self::Archived) -> Self::Target {
+        let clear_color = match archive.clear_color {
+            ArchivedClearColorProxy::Default => ClearColorConfig::Default,
+            ArchivedClearColorProxy::Custom(rgba) => ClearColorConfig::Custom(to_color(rgba)),
+            ArchivedClearColorProxy::None => ClearColorConfig::None,
+        };
+        let depth_load_op = match archive.depth_clear.as_ref() {
+            Some(depth) => Camera3dDepthLoadOp::Clear(*depth),
+            None => Camera3dDepthLoadOp::Load,
+        };
+        bevy::Camera3d { clear_color, depth_load_op, ..Default::default() }
+    }
+    fn from_target(target: &Self::Target) -> Self {
+        let clear_color = match target.clear_color {
+            ClearColorConfig::Default => ClearColorProxy::Default,
+            ClearColorConfig::Custom(color) => ClearColorProxy::Custom(color.as_rgba_f32()),
+            ClearColorConfig::None => ClearColorProxy::None,
+        };
+        let depth_clear = match target.depth_load_op {
+            Camera3dDepthLoadOp::Clear(depth) => Some(depth),
+            Camera3dDepthLoadOp::Load => None,
+        };
+        Camera3dProxy { clear_color, depth_clear }
+    }
+}
+impl PartialEq<bevy::Camera3d> for Camera3dProxy {
+    fn eq(&self, other: &bevy::Camera3d) -> bool {
+        self == &Self::from_target(other)
+    }
+}
+
+/// How the size of an orthographic projection is computed, see `ScalingMode`.
+#[derive(Clone, Copy, Debug, PartialEq, Archive, Deserialize, Serialize)]
+pub enum ScalingModeProxy {
+    Fixed { width: f32, height: f32 },
+    WindowSize(f32),
+    AutoMin { min_width: f32, min_height: f32 },
+    AutoMax { max_width: f32, max_height: f32 },
+    FixedVertical(f32),
+    FixedHorizontal(f32),
+}
+
+/// Store a `Projection`.
+///
+/// The `area` of orthographic projections is not stored, it is computed by
+/// bevy from the other fields.
+#[derive(Clone, Copy, Debug, PartialEq, Archive, Deserialize, Serialize)]
+pub enum ProjectionProxy {
+    Perspective {
+        fov: f32,
+        aspect_ratio: f32,
+        near: f32,
+        far: f32,
+    },
+    Orthographic {
+        near: f32,
+        far: f32,
+        viewport_origin: [f32; 2],
+        scaling_mode: ScalingModeProxy,
+        scale: f32,
+    },
+}
+impl ArchiveProxy for ProjectionProxy {
+    type Target = bevy::Projection;
+
+    fn to_target(archive: &Self::Archived) -> Self::Target {
+        use ArchivedScalingModeProxy as Archived;
+
+        match archive {
+            ArchivedProjectionProxy::Perspective { fov, aspect_ratio, near, far } => {
+                bevy::Projection::Perspective(PerspectiveProjection {
+                    fov: *fov,
+                    aspect_ratio: *aspect_ratio,
+                    near: *near,
+                    far: *far,
+                })
+            }
+            ArchivedProjectionProxy::Orthographic {
+                near,
+                far,
+                viewport_origin,
+                scaling_mode,
+                scale,
+            } => {
+                let scaling_mode = match *scaling_mode {
+                    Archived::Fixed { width, height } => ScalingMode::Fixed { width, height },
+                    Archived::WindowSize(pixels) => ScalingMode::WindowSize(pixels),
+                    Archived::AutoMin { min_width, min_height } => {
+                        ScalingMode::AutoMin { min_width, min_height }
+                    }
+                    Archived::AutoMax { max_width, max_height } => {
+                        ScalingMode::AutoMax { max_width, max_height }
+                    }
+                    Archived::FixedVertical(height) => ScalingMode::FixedVertical(height),
+                    Archived::FixedHorizontal(width) => ScalingMode::FixedHorizontal(width),
+                };
+                bevy::Projection::Orthographic(OrthographicProjection {
+                    near: *near,
+                    far: *far,
+                    viewport_origin: (*viewport_origin).into(),
+                    scaling_mode,
+                    scale: *scale,
+                    ..Default::default()
+                })
+            }
+        }
+    }
+    fn from_target(target: &Self::Target) -> Self {
+        match target {
+            bevy::Projection::Perspective(p) => ProjectionProxy::Perspective {
+                fov: p.fov,
+                aspect_ratio: p.aspect_ratio,
+                near: p.near,
+                far: p.far,
+            },
+            bevy::Projection::Orthographic(o) => {
+                let scaling_mode = match o.scaling_mode {
+                    ScalingMode::Fixed { width, height } => {
+                        ScalingModeProxy::Fixed { width, height }
+                    }
+                    ScalingMode::WindowSize(pixels) => ScalingModeProxy::WindowSize(pixels),
+                    ScalingMode::AutoMin { min_width, min_height } => {
+                        ScalingModeProxy::AutoMin { min_width, min_height }
+                    }
+                    ScalingMode::AutoMax { max_width, max_height } => {
+                        ScalingModeProxy::AutoMax { max_width, max_height }
+                    }
+                    ScalingMode::FixedVertical(height) => ScalingModeProxy::FixedVertical(height),
+                    ScalingMode::FixedHorizontal(width) => ScalingModeProxy::FixedHorizontal(width),
+                };
+                ProjectionProxy::Orthographic {
+                    near: o.near,
+                    far: o.far,
+                    viewport_origin: o.viewport_origin.into(),
+                    scaling_mode,
+                    scale: o.scale,
+                }
+            }
+        }
+    }
+}
+impl PartialEq<bevy::Projection> for ProjectionProxy {
+    fn eq(&self, other: &bevy::Projection) -> bool {
+        self == &Self::from_target(other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::core_pipeline::{clear_color::ClearColorConfig, core_3d::Camera3dDepthLoadOp};
+    use bevy::prelude::*;
+    use bevy::render::camera::ScalingMode;
+    use bevy::render::view::RenderLayers;
+
+    use super::{Camera3dProxy, ProjectionProxy, RenderLayersProxy, VisibilityProxy};
+    use crate::proxy::roundtrip;
+
+    #[test]
+    fn visibility() {
+        for visibility in [
+            Visibility::Inherited,
+            Visibility::Hidden,
+            Visibility::Visible,
+        ] {
+            assert_eq!(roundtrip::<VisibilityProxy>(&visibility), visibility);
+        }
+    }
+
+    #[test]
+    fn render_layers() {
+        for layers in [
+            RenderLayers::default(),
+            RenderLayers::all(),
+            RenderLayers::none(),
+            RenderLayers::from_layers(&[2, 5, 31]),
+        ] {
+            assert_eq!(roundtrip::<RenderLayersProxy>(&layers), layers);
+        }
+        let default = RenderLayersProxy::default();
+        assert!(default == RenderLayers::default());
+    }
+
+    #[test]
+    fn camera_3d() {
+        let camera = Camera3d {
+            clear_color: ClearColorConfig::Custom(Color::rgba(0.1, 0.2, 0.3, 1.0)),
+            depth_load_op: Camera3dDepthLoadOp::Load,
+            ..default()
+        };
+        let spawned = roundtrip::<Camera3dProxy>(&camera);
+        assert!(Camera3dProxy::from_target(&camera) == spawned);
+        assert!(matches!(spawned.depth_load_op, Camera3dDepthLoadOp::Load));
+
+        let camera = Camera3d::default();
+        let spawned = roundtrip::<Camera3dProxy>(&camera);
+        assert!(matches!(spawned.clear_color, ClearColorConfig::Default));
+        assert!(Camera3dProxy::from_target(&camera) == spawned);
+    }
+
+    #[test]
+    fn projection() {
+        let perspective = Projection::Perspective(PerspectiveProjection {
+            fov: 1.2,
+            aspect_ratio: 1.5,
+            near: 0.5,
+            far: 500.0,
+        });
+        let orthographic = Projection::Orthographic(OrthographicProjection {
+            scaling_mode: ScalingMode::AutoMin { min_width: 10.0, min_height: 8.0 },
+            viewport_origin: Vec2::new(0.0, 1.0),
+            scale: 2.0,
+            ..default()
+        });
+        for projection in [perspective, orthographic] {
+            let proxy = ProjectionProxy::from_target(&projection);
+            assert!(proxy == roundtrip::<ProjectionProxy>(&projection));
+        }
+    }
+}
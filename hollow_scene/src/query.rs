@@ -0,0 +1,150 @@
+//! Read-only access to an [`ArchivedFastScene`] without spawning it.
+//!
+//! ```text
+//! let scene = unsafe { rkyv::archived_root::<FastScene<Ts, Is>>(&bytes) };
+//! for entity in scene.iter() {
+//!     let transform = entity.get::<MyTransform, _>();
+//!     println!("{}: depth {} {transform:?}", entity.index(), entity.depth());
+//! }
+//! ```
+
+use std::marker::PhantomData;
+
+use rkyv::Archived;
+
+use crate::entity::{FindInline, FindTable, Inlines, Tables};
+use crate::scene::{ArchivedFastScene, FastScene};
+use crate::ArchiveProxy;
+
+/// Type-level index of a proxy stored in a `Table` or `DedupTable`.
+pub struct InTables<I>(PhantomData<I>);
+
+/// Type-level index of a proxy stored `Inline`.
+pub struct InInlines<I>(PhantomData<I>);
+
+/// A [`FastScene`] that stores the `P` proxy.
+///
+/// `Idx` is a type-level index, it should always be inferred, use `_` in
+/// place of it.
+pub trait Contains<P: ArchiveProxy, Idx>: rkyv::Archive {
+    /// The archived `P` of entity at `index`, if the entity has it.
+    fn get_archived(archive: &Self::Archived, index: usize) -> Option<&Archived<P>>;
+}
+impl<P, Idx, Ts, Is> Contains<P, InTables<Idx>> for FastScene<Ts, Is>
+where
+    P: ArchiveProxy,
+    Ts: FindTable<P, Idx>,
+    Is: Inlines,
+{
+    fn get_archived(scene: &ArchivedFastScene<Ts, Is>, index: usize) -> Option<&Archived<P>> {
+        let entity = scene.entities.as_ref().get(index)?;
+        scene.tables.get::<P, Idx>(&entity.ref_table_keys)
+    }
+}
+impl<P, Idx, Ts, Is> Contains<P, InInlines<Idx>> for FastScene<Ts, Is>
+where
+    P: ArchiveProxy,
+    Ts: Tables,
+    Is: FindInline<P, Idx>,
+{
+    fn get_archived(scene: &ArchivedFastScene<Ts, Is>, index: usize) -> Option<&Archived<P>> {
+        let entity = scene.entities.as_ref().get(index)?;
+        entity.inline_items.get::<P, Idx>()
+    }
+}
+
+impl<Ts: Tables, Is: Inlines> ArchivedFastScene<Ts, Is> {
+    /// How many entities are stored in this scene.
+    pub fn entity_count(&self) -> usize {
+        // The first entity is the scene root, it is not part of the scene.
+        self.entities.len() - 1
+    }
+    /// The archived `P` of entity at `index`, if the entity has it.
+    ///
+    /// `index` is the position of the entity in the scene's entity list,
+    /// as returned by [`EntityRef::index`].
+    pub fn get<P: ArchiveProxy, Idx>(&self, index: usize) -> Option<&Archived<P>>
+    where
+        FastScene<Ts, Is>: Contains<P, Idx>,
+    {
+        FastScene::<Ts, Is>::get_archived(self, index)
+    }
+    /// The index of the entity at the `Name` path `path`, if any.
+    ///
+    /// Paths are `Name`s of the entity and its ancestors separated by `/`,
+    /// such as `Level/Doors/Door_03`. Only works if the scene was saved with
+    /// [`crate::SaveOptions::name_index`].
+    pub fn index_of_path(&self, path: &str) -> Option<usize> {
+        self.names.get(path)
+    }
+    /// Iterate over all entities in this scene, in depth-first order.
+    pub fn iter(&self) -> Iter<Ts, Is> {
+        Iter { scene: self, next: 1, ancestors: Vec::new() }
+    }
+}
+
+/// An entity in an [`ArchivedFastScene`].
+pub struct EntityRef<'a, Ts: Tables, Is: Inlines> {
+    scene: &'a ArchivedFastScene<Ts, Is>,
+    index: usize,
+    depth: u32,
+    parent: Option<usize>,
+}
+impl<'a, Ts: Tables, Is: Inlines> EntityRef<'a, Ts, Is> {
+    /// The position of this entity in the scene's entity list.
+    pub const fn index(&self) -> usize {
+        self.index
+    }
+    /// How many ancestors this entity has, `0` for root entities.
+    pub const fn depth(&self) -> u32 {
+        self.depth
+    }
+    /// The [`EntityRef::index`] of the parent, `None` for root entities.
+    pub const fn parent(&self) -> Option<usize> {
+        self.parent
+    }
+    /// How many descendants this entity has (children, grand children etc.)
+    pub fn descendant_count(&self) -> u32 {
+        self.scene.entities[self.index].children
+    }
+    /// The archived `P` of this entity, if it has it.
+    pub fn get<P: ArchiveProxy, Idx>(&self) -> Option<&'a Archived<P>>
+    where
+        FastScene<Ts, Is>: Contains<P, Idx>,
+    {
+        self.scene.get::<P, Idx>(self.index)
+    }
+}
+
+/// Iterator over entities of an [`ArchivedFastScene`], see [`ArchivedFastScene::iter`].
+pub struct Iter<'a, Ts: Tables, Is: Inlines> {
+    scene: &'a ArchivedFastScene<Ts, Is>,
+    next: usize,
+    /// `(index, one past last descendant)` of ancestors of `next`.
+    ancestors: Vec<(usize, usize)>,
+}
+impl<'a, Ts: Tables, Is: Inlines> Iterator for Iter<'a, Ts, Is> {
+    type Item = EntityRef<'a, Ts, Is>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.next;
+        let entity = self.scene.entities.as_ref().get(index)?;
+        self.next += 1;
+
+        while matches!(self.ancestors.last(), Some(&(_, end)) if end <= index) {
+            self.ancestors.pop();
+        }
+        let parent = self.ancestors.last().map(|&(parent, _)| parent);
+        let depth = self.ancestors.len() as u32;
+
+        let end = index + 1 + entity.children as usize;
+        self.ancestors.push((index, end));
+
+        Some(EntityRef { scene: self.scene, index, depth, parent })
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.scene.entities.len().saturating_sub(self.next);
+        (remaining, Some(remaining))
+    }
+}
+impl<'a, Ts: Tables, Is: Inlines> ExactSizeIterator for Iter<'a, Ts, Is> {}
@@ -0,0 +1,225 @@
+//! Insert bundles on spawned entities that have a given component.
+//!
+//! Proxies only store what they need to restore a component. An entity loaded
+//! with a `Transform` proxy has no `GlobalTransform`, so it is not rendered
+//! where it should be. [`RequiredBundles`] declares "when an entity has `C`,
+//! also insert `B::default()`":
+//!
+//! ```text
+//! Plugin!(Inline[MyTransform, ColliderProxy])
+//!     .with_required::<Collider, (Velocity, Sleeping)>()
+//!     .with_root_bundle::<MyRootBundle>()
+//! ```
+//!
+//! Two rules are built-in:
+//!
+//! - [`RequiredBundles::add_transform_rule`]: entities with a `Transform` get a
+//!   `GlobalTransform`, and with the `render` feature, `Visibility` and
+//!   `ComputedVisibility`, unless they already have them.
+//! - The root bundle: the entity the scene is spawned under and the scene's
+//!   synthetic root get a `SpatialBundle` (a `TransformBundle` without the
+//!   `render` feature), so that transforms propagate to the scene's entities.
+//!
+//! The hollow scene loader enables both by default, disable them with the
+//! `transform_bundles` and `root_bundle` loader settings in the `.meta` file.
+//!
+//! Rules run once all the components of an entity are inserted, after the
+//! [`crate::hooks`] of those components. The hollow scene loader and
+//! [`crate::chunked::ChunkedScene`] use the rules of the plugin that reads
+//! the file, with the built-in rules enabled in the file's loader settings.
+//! Spawning an [`crate::ArchivedFastScene`] directly only uses the
+//! [`RequiredBundles`] resource of the world, if any.
+
+use std::any::TypeId;
+use std::sync::Arc;
+
+use bevy::ecs::world::EntityMut;
+use bevy::prelude::{Bundle, Component, GlobalTransform, Resource, Transform, World};
+
+/// Insert a bundle on an entity.
+pub type Insert = dyn Fn(&mut EntityMut) + Send + Sync;
+
+#[cfg(feature = "render")]
+type DefaultRoot = bevy::prelude::SpatialBundle;
+#[cfg(not(feature = "render"))]
+type DefaultRoot = bevy::prelude::TransformBundle;
+
+/// Insert a bundle on entities that have the `trigger` component.
+#[derive(Clone)]
+struct Rule {
+    trigger: TypeId,
+    insert: Arc<Insert>,
+    /// The components `insert` may insert.
+    inserted: Arc<[TypeId]>,
+}
+
+/// Bundles to insert on spawned entities, see the [module docs](self).
+#[derive(Resource, Clone, Default)]
+pub struct RequiredBundles {
+    rules: Vec<Rule>,
+    root: Option<Arc<Insert>>,
+}
+impl RequiredBundles {
+    /// The built-in transform rule and root bundle.
+    pub fn with_defaults() -> Self {
+        let mut required = RequiredBundles::default();
+        required.add_transform_rule();
+        required.set_default_root();
+        required
+    }
+    /// When a spawned entity has `C`, also insert `B::default()`.
+    ///
+    /// `B` overwrites the components of the entity it contains, so it
+    /// shouldn't contain components stored in the scene.
+    pub fn add<C: Component, B: Bundle + Default>(&mut self) {
+        let insert = |entity: &mut EntityMut| {
+            entity.insert(B::default());
+        };
+        self.rules.push(Rule {
+            trigger: TypeId::of::<C>(),
+            insert: Arc::new(insert),
+            inserted: component_ids::<B>().into(),
+        });
+    }
+    /// When a spawned entity has a `Transform`, insert the components it
+    /// needs to be positioned and rendered, if it doesn't have them already.
+    pub fn add_transform_rule(&mut self) {
+        #[cfg(not(feature = "render"))]
+        let inserted = [TypeId::of::<GlobalTransform>()];
+        #[cfg(feature = "render")]
+        let inserted = [
+            TypeId::of::<GlobalTransform>(),
+            TypeId::of::<bevy::prelude::Visibility>(),
+            TypeId::of::<bevy::prelude::ComputedVisibility>(),
+        ];
+        self.rules.push(Rule {
+            trigger: TypeId::of::<Transform>(),
+            insert: Arc::new(insert_transform_components),
+            inserted: inserted.into(),
+        });
+    }
+    /// Insert `B::default()` on the scene root entities.
+    ///
+    /// This replaces the previous root bundle.
+    pub fn set_root<B: Bundle + Default>(&mut self) {
+        let insert = |entity: &mut EntityMut| {
+            entity.insert(B::default());
+        };
+        self.root = Some(Arc::new(insert));
+    }
+    /// Insert a `SpatialBundle` on the scene root entities.
+    ///
+    /// Without the `render` feature, a `TransformBundle` is inserted instead.
+    pub fn set_default_root(&mut self) {
+        self.set_root::<DefaultRoot>();
+    }
+    /// Don't insert anything on the scene root entities.
+    pub(crate) fn clear_root(&mut self) {
+        self.root = None;
+    }
+    /// Add all the rules of `other`, and its root bundle if it has one.
+    pub fn extend(&mut self, other: &RequiredBundles) {
+        self.rules.extend(other.rules.iter().cloned());
+        if let Some(root) = &other.root {
+            self.root = Some(root.clone());
+        }
+    }
+    /// Whether `component` is inserted by a rule whose trigger component
+    /// the entity has once loaded, according to `loaded`.
+    ///
+    /// The root bundle is not considered, it is only inserted on the entity
+    /// the scene is spawned under and the scene's synthetic root.
+    pub(crate) fn inserts(&self, component: TypeId, loaded: impl Fn(TypeId) -> bool) -> bool {
+        let inserts = |rule: &Rule| rule.inserted.contains(&component);
+        self.rules
+            .iter()
+            .any(|rule| inserts(rule) && loaded(rule.trigger))
+    }
+    /// Insert the bundles of the rules matching the components of `entity`.
+    pub fn apply(&self, entity: &mut EntityMut) {
+        for rule in &self.rules {
+            if entity.contains_type_id(rule.trigger) {
+                (rule.insert)(entity);
+            }
+        }
+    }
+    /// Insert the root bundle on `entity`, if there is one.
+    pub fn apply_root(&self, entity: &mut EntityMut) {
+        if let Some(insert) = &self.root {
+            insert(entity);
+        }
+    }
+}
+
+/// The `TypeId`s of the components of `B`.
+fn component_ids<B: Bundle + Default>() -> Vec<TypeId> {
+    let mut world = World::new();
+    let entity = world.spawn(B::default()).id();
+    let components = world.entity(entity).archetype().components();
+    let info = |id| world.components().get_info(id);
+    components
+        .filter_map(info)
+        .filter_map(|info| info.type_id())
+        .collect()
+}
+
+fn insert_transform_components(entity: &mut EntityMut) {
+    if !entity.contains::<GlobalTransform>() {
+        entity.insert(GlobalTransform::default());
+    }
+    #[cfg(feature = "render")]
+    {
+        use bevy::prelude::{ComputedVisibility, Visibility};
+
+        if !entity.contains::<Visibility>() {
+            entity.insert(Visibility::default());
+        }
+        if !entity.contains::<ComputedVisibility>() {
+            entity.insert(ComputedVisibility::default());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use super::RequiredBundles;
+
+    #[derive(Component)]
+    struct Collider;
+
+    #[derive(Component, Default, Debug, PartialEq)]
+    struct Body(u32);
+
+    #[test]
+    fn apply_rules() {
+        let mut required = RequiredBundles::with_defaults();
+        required.add::<Collider, (Body,)>();
+
+        let mut world = World::new();
+        let moved = Transform::from_xyz(1.0, 2.0, 3.0);
+        let global = GlobalTransform::from(moved);
+        let entity = world.spawn((Collider, moved)).id();
+        let kept = world.spawn((moved, global)).id();
+        let root = world.spawn_empty().id();
+
+        required.apply(&mut world.entity_mut(entity));
+        required.apply(&mut world.entity_mut(kept));
+        required.apply_root(&mut world.entity_mut(root));
+
+        assert_eq!(world.get::<Body>(entity), Some(&Body(0)));
+        assert_eq!(world.get::<Transform>(entity), Some(&moved));
+        assert_eq!(
+            world.get::<GlobalTransform>(entity),
+            Some(&GlobalTransform::IDENTITY)
+        );
+        assert_eq!(world.get::<GlobalTransform>(kept), Some(&global));
+        assert_eq!(world.get::<Body>(kept), None);
+        assert_eq!(world.get::<Transform>(root), Some(&Transform::IDENTITY));
+        assert_eq!(
+            world.get::<GlobalTransform>(root),
+            Some(&GlobalTransform::IDENTITY)
+        );
+    }
+}
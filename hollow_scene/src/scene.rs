@@ -1,51 +1,294 @@
+use ::bevy::ecs::query::ReadOnlyWorldQuery;
+use ::bevy::ecs::reflect::ReflectComponent;
+use ::bevy::prelude::BuildWorldChildren;
 use bevy::prelude as bevy;
+use std::mem;
+
 use rkyv::{Archive, Deserialize, Serialize};
 
 use crate::{
-    entity::{Entity, Inlines, TableStorage, Tables},
-    hierarchy::{self, Spawn},
+    entity::{Entity, Inlines, ParInsert, ParTables, TableStorage, Tables},
+    header,
+    hierarchy::{self, ChunkedSpawn, Spawn, SpawnBudget},
+    names::NameIndex,
+    required::RequiredBundles,
+    schema::Schema,
+    strings::{Interner, SceneStrings, StringPool},
 };
 
+/// Options controlling how a bevy `Scene` is converted into a [`FastScene`].
+///
+/// This is also the settings of the hollow scene saver.
+#[derive(Clone, Copy, Debug, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct SaveOptions {
+    /// Produce byte-identical output for identical scenes.
+    ///
+    /// Root entities are sorted by `Name`, then by entity index, instead of
+    /// following the ECS's storage order. The order of children is always
+    /// preserved.
+    pub deterministic: bool,
+    /// Store an index of entities by `Name` path (such as `Level/Doors/Door_03`).
+    ///
+    /// See [`ArchivedFastScene::index_of_path`] and [`crate::EntityPaths`].
+    pub name_index: bool,
+    /// Fill each component table in a separate task of the `ComputeTaskPool`.
+    ///
+    /// This speeds up saving scenes with many entities and table components.
+    /// Only [`FastScene::par_from_bevy_filtered`] and plugins built with
+    /// [`crate::Plugin::with_parallel_save`] read this. Without a
+    /// `ComputeTaskPool`, for example outside of a bevy `App`, this is
+    /// ignored.
+    /// The saved scene is the same as without this option, except when several
+    /// tables have proxies using [`crate::strings::InternedStr`]: their
+    /// strings are added to the [`StringPool`] in table order rather than
+    /// entity order. It is still the same from one save to the next.
+    pub parallel: bool,
+    /// Fail saving when the scene has components that are not in the
+    /// `Plugin!` schema, listing them, rather than logging them as a warning.
+    ///
+    /// Those components are not saved, only the hollow scene saver and
+    /// converter check this. Components that a rule of the plugin's
+    /// [`crate::required::RequiredBundles`] inserts back when loading, given
+    /// the stored components of the entity, are not reported.
+    pub strict: bool,
+    /// Load the processed file with the `chunked` loader setting, so that it
+    /// can be spawned with [`crate::chunked::ChunkedScene`].
+    ///
+    /// Only the hollow scene saver reads this.
+    pub chunked: bool,
+}
+
+/// Marks the entity a hollow scene is spawned under in the bevy `Scene` of
+/// the hollow scene loader.
+///
+/// Its only child is the synthetic root of the entity slice, the parent of
+/// the scene's root entities. [`crate::reload`] uses it to skip both.
+#[derive(bevy::Component, bevy::Reflect, Clone, Copy, Debug, Default)]
+#[reflect(Component)]
+pub struct HollowSceneRoot {
+    /// The [`Schema::fingerprint`] of the scene, to run the [`crate::hooks`]
+    /// of the plugin that reads this schema.
+    pub fingerprint: u64,
+}
+
 #[derive(Clone, Archive, Deserialize, Serialize)]
+#[archive_attr(repr(C))]
 pub struct FastScene<Ts: Tables, Is: Inlines> {
     pub entities: Box<[Entity<Ts::Keys, Is>]>,
     pub tables: TableStorage<Ts>,
+    pub names: NameIndex,
+    pub strings: StringPool,
 }
 impl<Ts: Tables, Is: Inlines> ArchivedFastScene<Ts, Is> {
+    /// The archived scene of `payload`, the content of a `.hollow_bvyfst`
+    /// file after its header, copied in an aligned buffer.
+    ///
+    /// Returns an error if `payload` is too short to hold the scene root.
+    ///
+    /// # Safety
+    ///
+    /// `payload` must have been serialized from a `FastScene<Ts, Is>`. This
+    /// is not validated: a file whose [`crate::header::Header::check`] passes
+    /// with `Schema::of::<Ts, Is>()` was saved with proxies of the same type
+    /// names and archived layouts, but its content could still be corrupted
+    /// or crafted, which is undefined behavior. Only read trusted files.
+    pub(crate) unsafe fn from_payload(payload: &[u8]) -> Result<&Self, header::Error> {
+        if payload.len() < mem::size_of::<Self>() {
+            return Err(header::Error::TooShort(payload.len()));
+        }
+        Ok(rkyv::archived_root::<FastScene<Ts, Is>>(payload))
+    }
     pub fn to_bevy(&self) -> bevy::Scene {
         let mut world = bevy::World::new();
+        self.spawn(&mut world);
+        bevy::Scene::new(world)
+    }
+    /// Like [`ArchivedFastScene::to_bevy`], but load [`crate::proxy::AssetPath`]
+    /// handles with `asset_server`.
+    pub fn to_bevy_with_assets(&self, asset_server: &bevy::AssetServer) -> bevy::Scene {
+        let mut world = bevy::World::new();
+        world.insert_resource(asset_server.clone());
+        self.spawn(&mut world);
+        world.remove_resource::<bevy::AssetServer>();
+        bevy::Scene::new(world)
+    }
+    /// Like [`ArchivedFastScene::to_bevy_with_assets`], but also insert the
+    /// `required` bundles, see [`crate::required`].
+    pub fn to_bevy_with_required(
+        &self,
+        asset_server: &bevy::AssetServer,
+        required: &RequiredBundles,
+    ) -> bevy::Scene {
+        let mut world = bevy::World::new();
+        world.insert_resource(asset_server.clone());
+        world.insert_resource(required.clone());
+        self.spawn(&mut world);
+        world.remove_resource::<bevy::AssetServer>();
+        world.remove_resource::<RequiredBundles>();
+        bevy::Scene::new(world)
+    }
+    /// Convert this scene into a `DynamicScene`, for example to save it as RON.
+    ///
+    /// Only components registered in `registry` are kept. Register `Parent`
+    /// and `Children` to preserve the hierarchy.
+    pub fn to_dynamic_scene(&self, registry: &bevy::AppTypeRegistry) -> bevy::DynamicScene {
+        let mut world = bevy::World::new();
+        let (root, ids) = self.spawn(&mut world);
 
-        let root_entity = world.spawn_empty();
-        let spawn = Spawn::new(&self.entities, &self.tables);
-        spawn.children_of(root_entity);
+        // Remove the spawn root and the synthetic scene root, so that the
+        // dynamic scene has the same hierarchy as the one that was saved.
+        let scene_roots = world.get::<bevy::Children>(ids[0]);
+        let scene_roots = scene_roots.map_or(Vec::new(), |c| c.to_vec());
+        for scene_root in scene_roots {
+            world.entity_mut(scene_root).remove_parent();
+        }
+        world.despawn(ids[0]);
+        world.despawn(root);
 
-        bevy::Scene::new(world)
+        world.insert_resource(registry.clone());
+        bevy::DynamicScene::from_world(&world)
+    }
+    /// Prepare spawning this scene as children of `root`, a chunk at a time.
+    ///
+    /// Use this instead of [`ArchivedFastScene::to_bevy`] to spread the
+    /// spawning of large scenes over several frames, see
+    /// [`ArchivedFastScene::spawn_chunk`].
+    pub fn chunked_spawn(&self, root: bevy::Entity) -> ChunkedSpawn {
+        ChunkedSpawn::new(root, self.entities.len())
+    }
+    /// Spawn the next entities of `state` in `world`, within `budget`.
+    ///
+    /// Returns `true` when all the entities are spawned. Parents are always
+    /// spawned before their children, so the spawned part of the scene is a
+    /// valid hierarchy at all time.
+    pub fn spawn_chunk(
+        &self,
+        world: &mut bevy::World,
+        state: &mut ChunkedSpawn,
+        budget: SpawnBudget,
+    ) -> bool {
+        if state.is_done() {
+            return true;
+        }
+        self.chunk(world, state, budget);
+
+        let done = state.is_done();
+        if done && !self.names.is_empty() {
+            let paths = self.names.to_component(state.ids());
+            world.entity_mut(state.root()).insert(paths);
+        }
+        done
+    }
+    /// Spawn this scene in `world` as children of a new entity.
+    ///
+    /// Returns the new entity and the `Entity` of each element of the entity
+    /// slice.
+    fn spawn(&self, world: &mut bevy::World) -> (bevy::Entity, Vec<bevy::Entity>) {
+        let fingerprint = Schema::of::<Ts, Is>().fingerprint();
+        let mut root = world.spawn(HollowSceneRoot { fingerprint });
+        if let Some(required) = root.world().get_resource::<RequiredBundles>().cloned() {
+            required.apply_root(&mut root);
+        }
+        let root = root.id();
+        let mut state = self.chunked_spawn(root);
+        self.chunk(world, &mut state, SpawnBudget::UNLIMITED);
+        let ids = state.into_ids();
+
+        if !self.names.is_empty() {
+            world.entity_mut(root).insert(self.names.to_component(&ids));
+        }
+        (root, ids)
+    }
+    /// Spawn the next entities of `state`, with the [`SceneStrings`] of this
+    /// scene as a resource.
+    fn chunk(&self, world: &mut bevy::World, state: &mut ChunkedSpawn, budget: SpawnBudget) {
+        let has_strings = !self.strings.is_empty();
+        if has_strings {
+            let strings = state
+                .strings
+                .get_or_insert_with(|| self.strings.to_resource());
+            world.insert_resource(strings.clone());
+        }
+        Spawn::new(&self.entities, &self.tables).chunk(world, state, budget);
+        if has_strings {
+            world.remove_resource::<SceneStrings>();
+        }
     }
 }
 impl<Ts: Tables, Is: Inlines> FastScene<Ts, Is> {
     pub fn from_bevy(scene: &mut bevy::Scene) -> Self {
+        Self::from_bevy_filtered::<()>(scene, SaveOptions::default())
+    }
+    /// Like [`FastScene::from_bevy`], but only keep entities matching `F`.
+    ///
+    /// Entities not matching `F` are skipped with all their descendants.
+    /// Use `With<Marker>` to only include marked entities, `Without<Marker>`
+    /// to exclude them, or any other read-only query filter.
+    ///
+    /// [`SaveOptions::parallel`] is ignored, use
+    /// [`FastScene::par_from_bevy_filtered`] to fill tables in parallel.
+    pub fn from_bevy_filtered<F: ReadOnlyWorldQuery>(
+        scene: &mut bevy::Scene,
+        options: SaveOptions,
+    ) -> Self {
+        Self::from_bevy_with::<F>(scene, options, None)
+    }
+    /// Like [`FastScene::from_bevy_filtered`], filling the tables of `Ts` with
+    /// `par_insert` when [`SaveOptions::parallel`] is set.
+    pub(crate) fn from_bevy_with<F: ReadOnlyWorldQuery>(
+        scene: &mut bevy::Scene,
+        options: SaveOptions,
+        par_insert: Option<ParInsert<Ts>>,
+    ) -> Self {
         let mut tables = TableStorage::new();
-        let entities = hierarchy::build(&mut scene.world, &mut tables);
-        FastScene { entities, tables }
+        let world = &mut scene.world;
+        let interner = Interner::default();
+        let (entities, ids) = interner
+            .scope(|| hierarchy::build::<Ts, Is, F>(world, &mut tables, options, par_insert));
+        tables.encode();
+        let names = if options.name_index {
+            let children: Vec<_> = entities.iter().map(|e| e.children).collect();
+            NameIndex::new(world, &children, &ids)
+        } else {
+            NameIndex::default()
+        };
+        let strings = interner.to_pool();
+        FastScene { entities, tables, names, strings }
+    }
+}
+impl<Ts: ParTables, Is: Inlines> FastScene<Ts, Is> {
+    /// Like [`FastScene::from_bevy_filtered`], but honor
+    /// [`SaveOptions::parallel`].
+    ///
+    /// This requires the proxies of `Table`, `DedupTable` and `Auto` storage,
+    /// and the columns of `Columnar` proxies, to be `Send`.
+    pub fn par_from_bevy_filtered<F: ReadOnlyWorldQuery>(
+        scene: &mut bevy::Scene,
+        options: SaveOptions,
+    ) -> Self {
+        Self::from_bevy_with::<F>(scene, options, Some(TableStorage::par_insert_values))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::FastScene;
+    use super::{FastScene, SaveOptions, SpawnBudget};
+    use crate::EntityPaths;
     use crate::{proxy::Id, Archive, Deserialize, Serialize};
     use std::fmt::Write;
+    use std::time::Duration;
 
     macro_rules! inline {
         () => { () };
         ($head:ty, $($tail:ty,)*) => {
-            ($crate::Inline<$head>, inline!($($tail,)*) )
+            $crate::HCons<$crate::Inline<$head>, inline!($($tail,)*)>
         };
     }
     macro_rules! table {
         () => { () };
         ($head:ty, $($tail:ty,)*) => {
-            ($crate::Table<$head>, table!($($tail,)*) )
+            $crate::HCons<$crate::Table<$head>, table!($($tail,)*)>
         };
     }
     macro_rules! make_world {
@@ -66,8 +309,9 @@ mod tests {
         Component,
         Debug, Default, Clone,
         PartialEq, PartialOrd, Eq, Ord,
-        Archive, Serialize, Deserialize,
+        Archive, Serialize, Deserialize, Reflect,
     )]
+    #[reflect(Component)]
     struct A1;
 
     #[rustfmt::skip]
@@ -75,8 +319,9 @@ mod tests {
         Component,
         Debug, Default, Clone,
         PartialEq, PartialOrd, Eq, Ord,
-        Archive, Serialize, Deserialize,
+        Archive, Serialize, Deserialize, Reflect,
     )]
+    #[reflect(Component)]
     struct B1;
 
     #[rustfmt::skip]
@@ -84,8 +329,9 @@ mod tests {
         Component,
         Debug, Default, Clone,
         PartialEq, PartialOrd, Eq, Ord,
-        Archive, Serialize, Deserialize,
+        Archive, Serialize, Deserialize, Reflect,
     )]
+    #[reflect(Component)]
     struct C1(u32);
 
     #[rustfmt::skip]
@@ -93,8 +339,9 @@ mod tests {
         Component,
         Debug, Default, Clone,
         PartialEq, PartialOrd, Eq, Ord,
-        Archive, Serialize, Deserialize,
+        Archive, Serialize, Deserialize, Reflect,
     )]
+    #[reflect(Component)]
     struct A2;
 
     #[rustfmt::skip]
@@ -102,8 +349,9 @@ mod tests {
         Component,
         Debug, Default, Clone,
         PartialEq, PartialOrd, Eq, Ord,
-        Archive, Serialize, Deserialize,
+        Archive, Serialize, Deserialize, Reflect,
     )]
+    #[reflect(Component)]
     struct B2;
 
     #[rustfmt::skip]
@@ -111,8 +359,9 @@ mod tests {
         Component,
         Debug, Default, Clone,
         PartialEq, PartialOrd, Eq, Ord,
-        Archive, Serialize, Deserialize,
+        Archive, Serialize, Deserialize, Reflect,
     )]
+    #[reflect(Component)]
     struct C2(u32);
 
     type Tables = table![Id<A1>, Id<B1>, Id<C1>,];
@@ -192,4 +441,673 @@ mod tests {
             "\n==== old ====\n{old_printed}\n==== new ====\n{new_printed}"
         );
     }
+
+    #[derive(Component)]
+    struct Hidden;
+
+    #[test]
+    fn filter_skips_subtrees() {
+        let mut world = World::new();
+        world.spawn(A2).with_children(|parent| {
+            parent.spawn((B2, Hidden)).with_children(|parent| {
+                parent.spawn(C2(1));
+            });
+            parent.spawn(C2(2));
+        });
+        world.spawn((A1, Hidden));
+        world.spawn(B1);
+
+        let mut scene = Scene::new(world);
+        let fast_scene = FastScene::<Tables, Inlines>::from_bevy_filtered::<Without<Hidden>>(
+            &mut scene,
+            default(),
+        );
+
+        // scene root, A2, C2(2) and B1
+        assert_eq!(fast_scene.entities.len(), 4);
+        assert_eq!(fast_scene.entities[0].children, 3);
+
+        let mut children: Vec<_> = fast_scene.entities.iter().map(|e| e.children).collect();
+        children.sort();
+        assert_eq!(children, [0, 0, 1, 3]);
+
+        // No `Hidden` entity, nor `C2(1)` under the hidden `B2`, was saved.
+        let bytes = rkyv::to_bytes::<_, 0>(&fast_scene).unwrap();
+        let archived = unsafe { rkyv::archived_root::<FastScene<Tables, Inlines>>(&bytes) };
+        let mut world = archived.to_bevy().world;
+        let mut saved: Vec<_> = world
+            .query::<AnyOf<(&A1, &B1, &C1, &A2, &B2, &C2)>>()
+            .iter(&world)
+            .collect();
+        saved.sort();
+        let mut expected = [
+            (
+                None::<&A1>,
+                None::<&B1>,
+                None::<&C1>,
+                Some(&A2),
+                None::<&B2>,
+                None,
+            ),
+            (None, None, None, None, None, Some(&C2(2))),
+            (None, Some(&B1), None, None, None, None),
+        ];
+        expected.sort();
+        assert_eq!(saved, expected);
+
+        let (_, parent) = world.query::<(&C2, &Parent)>().single(&world);
+        assert!(world.get::<A2>(parent.get()).is_some());
+        let (_, parent) = world.query::<(&B1, &Parent)>().single(&world);
+        assert!(world.get::<A2>(parent.get()).is_none());
+    }
+
+    #[test]
+    fn deterministic_output() {
+        fn spawn_roots(names: &[&str]) -> World {
+            let mut world = World::new();
+            for name in names {
+                let value = u32::from(name.as_bytes()[0]);
+                let mut entity = world.spawn((Name::new(name.to_string()), C1(value)));
+                if value % 2 == 0 {
+                    entity.insert(A1);
+                }
+                entity.with_children(|parent| {
+                    parent.spawn((B1, C2(value)));
+                });
+            }
+            world
+        }
+        fn save(world: World) -> rkyv::AlignedVec {
+            let options = SaveOptions { deterministic: true, ..default() };
+            let mut scene = Scene::new(world);
+            let fast_scene =
+                FastScene::<Tables, Inlines>::from_bevy_filtered::<()>(&mut scene, options);
+            rkyv::to_bytes::<_, 0>(&fast_scene).unwrap()
+        }
+        let first = save(spawn_roots(&["alpha", "beta", "gamma", "delta", "epsilon"]));
+        let second = save(spawn_roots(&["delta", "beta", "epsilon", "alpha", "gamma"]));
+
+        assert_eq!(first.as_slice(), second.as_slice());
+    }
+
+    #[test]
+    fn dedup_table_keys() {
+        type Dedup = crate::HCons<crate::DedupTable<Id<C1>>, ()>;
+
+        let mut world = World::new();
+        for value in [1, 2, 1, 3, 2, 1] {
+            world.spawn(C1(value));
+        }
+        let fast_scene = FastScene::<Dedup, ()>::from_bevy(&mut Scene::new(world));
+        assert_eq!(fast_scene.tables.component_count_of(0), 3);
+
+        // Deduplicated values point to the value they are equal to, not the
+        // next one in the table.
+        let bytes = rkyv::to_bytes::<_, 0>(&fast_scene).unwrap();
+        let archived = unsafe { rkyv::archived_root::<FastScene<Dedup, ()>>(&bytes) };
+        let mut world = archived.to_bevy().world;
+        let mut values: Vec<_> = world.query::<&C1>().iter(&world).map(|c| c.0).collect();
+        values.sort();
+        assert_eq!(values, [1, 1, 1, 2, 2, 3]);
+    }
+
+    #[test]
+    fn parallel_output() {
+        use bevy::tasks::{ComputeTaskPool, TaskPool};
+
+        type Dedup = crate::HCons<crate::DedupTable<Id<C1>>, table![Id<A1>, Id<B1>,]>;
+        type Autos = crate::HCons<crate::Auto<Id<C1>>, crate::HCons<crate::Auto<Id<A1>>, ()>>;
+
+        fn world() -> World {
+            let mut world = World::new();
+            for i in 0..50_u32 {
+                let mut entity = world.spawn((C1(i % 7), C2(i)));
+                if i % 3 == 0 {
+                    entity.insert(A1);
+                }
+                entity.with_children(|parent| {
+                    parent.spawn((B1, C1(i % 5)));
+                    parent.spawn(A2);
+                });
+            }
+            world
+        }
+        macro_rules! save {
+            ($tables:ty, $parallel:expr) => {{
+                let options = SaveOptions {
+                    deterministic: true,
+                    parallel: $parallel,
+                    ..default()
+                };
+                let mut scene = Scene::new(world());
+                let fast_scene = FastScene::<$tables, Inlines>::par_from_bevy_filtered::<()>(
+                    &mut scene, options,
+                );
+                rkyv::to_bytes::<_, 0>(&fast_scene).unwrap()
+            }};
+        }
+        // Without a pool, `parallel` is ignored.
+        ComputeTaskPool::init(TaskPool::default);
+        assert_eq!(
+            save!(Tables, false).as_slice(),
+            save!(Tables, true).as_slice()
+        );
+        assert_eq!(
+            save!(Dedup, false).as_slice(),
+            save!(Dedup, true).as_slice()
+        );
+        assert_eq!(
+            save!(Autos, false).as_slice(),
+            save!(Autos, true).as_slice()
+        );
+    }
+
+    #[test]
+    fn query_archived() {
+        let mut world = World::new();
+        world.spawn((A1, C1(1))).with_children(|parent| {
+            parent.spawn(C2(2)).with_children(|parent| {
+                parent.spawn((B1, C1(3)));
+            });
+            parent.spawn(A2);
+        });
+        let fast_scene = FastScene::<Tables, Inlines>::from_bevy(&mut Scene::new(world));
+        let bytes = rkyv::to_bytes::<_, 0>(&fast_scene).unwrap();
+        let archived = unsafe { rkyv::archived_root::<FastScene<Tables, Inlines>>(&bytes) };
+
+        assert_eq!(archived.entity_count(), 4);
+
+        let entities: Vec<_> = archived
+            .iter()
+            .map(|e| {
+                let c1 = e.get::<Id<C1>, _>().map(|c| c.0 .0);
+                let c2 = e.get::<Id<C2>, _>().map(|c| c.0 .0);
+                (e.depth(), e.parent(), c1, c2)
+            })
+            .collect();
+        assert_eq!(
+            entities,
+            [
+                (0, None, Some(1), None),
+                (1, Some(1), None, Some(2)),
+                (2, Some(2), Some(3), None),
+                (1, Some(1), None, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn name_index() {
+        let mut world = World::new();
+        world.spawn(Name::new("Level")).with_children(|parent| {
+            parent.spawn(Name::new("Doors")).with_children(|parent| {
+                parent.spawn((Name::new("Door_01"), C1(1)));
+                parent.spawn((Name::new("Door_03"), C1(3)));
+            });
+            parent.spawn(A1).with_children(|parent| {
+                parent.spawn(Name::new("Hidden"));
+            });
+        });
+        let options = SaveOptions { name_index: true, ..default() };
+        let mut scene = Scene::new(world);
+        let fast_scene =
+            FastScene::<Tables, Inlines>::from_bevy_filtered::<()>(&mut scene, options);
+        let bytes = rkyv::to_bytes::<_, 0>(&fast_scene).unwrap();
+        let archived = unsafe { rkyv::archived_root::<FastScene<Tables, Inlines>>(&bytes) };
+
+        let door = archived.index_of_path("Level/Doors/Door_03").unwrap();
+        assert_eq!(archived.get::<Id<C1>, _>(door).map(|c| c.0 .0), Some(3));
+        assert_eq!(archived.index_of_path("Level/Doors"), Some(2));
+        assert_eq!(archived.index_of_path("Level/Hidden"), None);
+        assert_eq!(archived.index_of_path("Hidden"), None);
+
+        let mut new_world = archived.to_bevy().world;
+        let paths = new_world.query::<&EntityPaths>().single(&new_world);
+        let door = paths.get("Level/Doors/Door_03").unwrap();
+        assert_eq!(new_world.get::<C1>(door), Some(&C1(3)));
+    }
+
+    #[test]
+    fn chunked_spawn() {
+        let mut world = World::new();
+        world.spawn((A1, C1(1))).with_children(|parent| {
+            parent.spawn(C2(2)).with_children(|parent| {
+                parent.spawn((B1, C1(3)));
+            });
+            parent.spawn(A2);
+        });
+        world.spawn(B2);
+        // Sort roots, so that the `B2` root is last.
+        let options = SaveOptions { deterministic: true, ..default() };
+        let mut scene = Scene::new(world);
+        let fast_scene =
+            FastScene::<Tables, Inlines>::from_bevy_filtered::<()>(&mut scene, options);
+        let bytes = rkyv::to_bytes::<_, 0>(&fast_scene).unwrap();
+        let archived = unsafe { rkyv::archived_root::<FastScene<Tables, Inlines>>(&bytes) };
+
+        let mut world = World::new();
+        let root = world.spawn_empty().id();
+        let mut state = archived.chunked_spawn(root);
+        // An empty budget still spawns one entity.
+        let empty = SpawnBudget { max_entities: 0, max_time: Some(Duration::ZERO) };
+        assert!(!archived.spawn_chunk(&mut world, &mut state, empty));
+        assert_eq!(state.spawned(), 1);
+        let budget = SpawnBudget { max_entities: 2, max_time: None };
+        let mut chunks = 1;
+        while !archived.spawn_chunk(&mut world, &mut state, budget) {
+            assert_eq!(state.spawned(), 1 + chunks * 2);
+            chunks += 1;
+        }
+        assert_eq!(chunks, 3);
+        assert_eq!((state.spawned(), state.total()), (6, 6));
+
+        let ids = state.ids();
+        let parent = |i: usize| world.get::<Parent>(ids[i]).map(Parent::get);
+        assert_eq!(parent(0), Some(root));
+        assert_eq!(parent(1), Some(ids[0]));
+        assert_eq!(parent(2), Some(ids[1]));
+        assert_eq!(parent(3), Some(ids[2]));
+        assert_eq!(parent(4), Some(ids[1]));
+        assert_eq!(parent(5), Some(ids[0]));
+        assert_eq!(world.get::<C1>(ids[3]), Some(&C1(3)));
+        assert_eq!(world.get::<C2>(ids[2]), Some(&C2(2)));
+        assert!(world.get::<B2>(ids[5]).is_some());
+        let children = world.get::<Children>(ids[1]).unwrap();
+        assert_eq!(&children[..], &[ids[2], ids[4]]);
+    }
+
+    #[test]
+    fn spawn_hooks() {
+        let mut world = World::new();
+        world.spawn(C1(1)).with_children(|parent| {
+            parent.spawn(C2(2));
+            parent.spawn((A2, C1(3)));
+        });
+        let fast_scene = FastScene::<Tables, Inlines>::from_bevy(&mut Scene::new(world));
+        let bytes = rkyv::to_bytes::<_, 0>(&fast_scene).unwrap();
+        let archived = unsafe { rkyv::archived_root::<FastScene<Tables, Inlines>>(&bytes) };
+
+        let mut hooks = crate::hooks::SpawnHooks::default();
+        hooks.add::<C1>(|entity| {
+            let value = entity.get::<C1>().unwrap().0;
+            entity.insert(C2(value * 10));
+        });
+        let mut world = World::new();
+        world.insert_resource(hooks);
+        let (_, ids) = archived.spawn(&mut world);
+
+        // `C1` is a table component, inserted before the inline `C2`.
+        assert_eq!(world.get::<C2>(ids[1]), Some(&C2(10)));
+        assert_eq!(world.get::<C2>(ids[2]), Some(&C2(2)));
+        assert_eq!(world.get::<C2>(ids[3]), Some(&C2(30)));
+    }
+
+    #[test]
+    fn required_bundles() {
+        let mut world = World::new();
+        world.spawn(C1(1)).with_children(|parent| {
+            parent.spawn(C2(2));
+        });
+        let fast_scene = FastScene::<Tables, Inlines>::from_bevy(&mut Scene::new(world));
+        let bytes = rkyv::to_bytes::<_, 0>(&fast_scene).unwrap();
+        let archived = unsafe { rkyv::archived_root::<FastScene<Tables, Inlines>>(&bytes) };
+
+        let mut required = crate::required::RequiredBundles::with_defaults();
+        required.add::<C2, (A2, B1)>();
+        let mut world = World::new();
+        world.insert_resource(required);
+        let (root, ids) = archived.spawn(&mut world);
+
+        // The spawn root and the synthetic scene root get the root bundle.
+        assert!(world.get::<GlobalTransform>(root).is_some());
+        assert!(world.get::<GlobalTransform>(ids[0]).is_some());
+        assert!(world.get::<GlobalTransform>(ids[1]).is_none());
+        assert!(world.get::<A2>(ids[1]).is_none());
+        assert!(world.get::<A2>(ids[2]).is_some());
+        assert!(world.get::<B1>(ids[2]).is_some());
+    }
+
+    #[test]
+    fn interned_strings() {
+        use crate::strings::InternedStr;
+        use crate::ArchiveProxy;
+
+        #[derive(Component, Debug, PartialEq)]
+        struct Label(String);
+
+        #[derive(Archive, Serialize, Deserialize)]
+        struct LabelProxy(InternedStr);
+
+        impl ArchiveProxy for LabelProxy {
+            type Target = Label;
+            fn to_target(archive: &Self::Archived) -> Label {
+                Self::to_target_in(archive, None)
+            }
+            fn to_target_in(archive: &Self::Archived, world: Option<&World>) -> Label {
+                Label(archive.0.get(world).unwrap_or_default().to_string())
+            }
+            fn from_target(target: &Label) -> Self {
+                LabelProxy(InternedStr::new(&target.0))
+            }
+        }
+        impl PartialEq<Label> for LabelProxy {
+            fn eq(&self, other: &Label) -> bool {
+                self.0 == InternedStr::new(&other.0)
+            }
+        }
+        #[derive(Component, Debug, PartialEq)]
+        struct Tag(String);
+
+        #[derive(Archive, Serialize, Deserialize)]
+        struct TagProxy(InternedStr);
+
+        impl ArchiveProxy for TagProxy {
+            type Target = Tag;
+            fn to_target(archive: &Self::Archived) -> Tag {
+                Self::to_target_in(archive, None)
+            }
+            fn to_target_in(archive: &Self::Archived, world: Option<&World>) -> Tag {
+                Tag(archive.0.get(world).unwrap_or_default().to_string())
+            }
+            fn from_target(target: &Tag) -> Self {
+                TagProxy(InternedStr::new(&target.0))
+            }
+        }
+        type Labels = table![LabelProxy,];
+        type Dedup = crate::HCons<crate::DedupTable<LabelProxy>, ()>;
+        type Both = table![LabelProxy, TagProxy,];
+
+        fn labels() -> World {
+            let mut world = World::new();
+            for i in 0..50 {
+                world.spawn(Label(["door", "wall", "floor"][i % 3].to_string()));
+                world.spawn((
+                    Label(format!("prop_{}", i % 7)),
+                    Tag(format!("tag_{}", i % 5)),
+                ));
+            }
+            world
+        }
+        macro_rules! save {
+            ($tables:ty, $parallel:expr) => {{
+                let options = SaveOptions { parallel: $parallel, ..default() };
+                let mut scene = Scene::new(labels());
+                let fast_scene =
+                    FastScene::<$tables, ()>::par_from_bevy_filtered::<()>(&mut scene, options);
+                rkyv::to_bytes::<_, 0>(&fast_scene).unwrap()
+            }};
+        }
+        // Each task interns strings separately, then they are added to the
+        // pool in table order.
+        bevy::tasks::ComputeTaskPool::init(bevy::tasks::TaskPool::default);
+        assert_eq!(
+            save!(Labels, false).as_slice(),
+            save!(Labels, true).as_slice()
+        );
+        assert_eq!(
+            save!(Dedup, false).as_slice(),
+            save!(Dedup, true).as_slice()
+        );
+        // The tags are added after the labels, so the tag table is filled
+        // again on this thread.
+        let bytes = save!(Both, true);
+        assert_eq!(bytes.as_slice(), save!(Both, true).as_slice());
+        let archived = unsafe { rkyv::archived_root::<FastScene<Both, ()>>(&bytes) };
+        assert_eq!(archived.strings.get(10), Some("tag_0"));
+        let mut world = World::new();
+        archived.spawn(&mut world);
+        let mut pairs: Vec<_> = (world.query::<(&Label, &Tag)>().iter(&world))
+            .map(|(label, tag)| (label.0.clone(), tag.0.clone()))
+            .collect();
+        let mut expected: Vec<_> = (0..50)
+            .map(|i| (format!("prop_{}", i % 7), format!("tag_{}", i % 5)))
+            .collect();
+        pairs.sort();
+        expected.sort();
+        assert_eq!(pairs, expected);
+
+        let mut world = World::new();
+        for label in ["door", "wall", "door", "door", "wall"] {
+            world.spawn(Label(label.to_string()));
+        }
+        let fast_scene = FastScene::<Labels, ()>::from_bevy(&mut Scene::new(world));
+        let bytes = rkyv::to_bytes::<_, 0>(&fast_scene).unwrap();
+        let archived = unsafe { rkyv::archived_root::<FastScene<Labels, ()>>(&bytes) };
+        assert_eq!(archived.strings.len(), 2);
+
+        let mut world = World::new();
+        let (_, ids) = archived.spawn(&mut world);
+        let labels: Vec<_> = ids[1..]
+            .iter()
+            .map(|id| &world.get::<Label>(*id).unwrap().0)
+            .collect();
+        assert_eq!(labels, ["door", "wall", "door", "door", "wall"]);
+        assert!(world
+            .get_resource::<crate::strings::SceneStrings>()
+            .is_none());
+    }
+
+    #[test]
+    fn columnar() {
+        use crate::{ArchiveProxy, Columnar, ColumnarProxy, HCons};
+
+        #[derive(Component, Debug, Clone, Copy, PartialEq)]
+        struct Pair(u32, u8);
+
+        #[derive(Archive, Serialize, Deserialize)]
+        struct PairProxy(u32, u8);
+
+        #[derive(Default, Archive, Serialize, Deserialize)]
+        #[archive_attr(repr(C))]
+        struct PairColumns {
+            first: Vec<u32>,
+            second: Vec<u8>,
+        }
+        impl ArchiveProxy for PairProxy {
+            type Target = Pair;
+            fn to_target(archive: &Self::Archived) -> Pair {
+                Pair(archive.0, archive.1)
+            }
+            fn from_target(target: &Pair) -> Self {
+                PairProxy(target.0, target.1)
+            }
+        }
+        impl ColumnarProxy for PairProxy {
+            type Columns = PairColumns;
+            fn push(self, columns: &mut PairColumns) {
+                columns.first.push(self.0);
+                // Lose zeros, as a corrupted file would.
+                if self.1 != 0 {
+                    columns.second.push(self.1);
+                }
+            }
+            fn len(columns: &ArchivedPairColumns) -> usize {
+                columns.first.len().min(columns.second.len())
+            }
+            fn to_target_at(
+                columns: &ArchivedPairColumns,
+                index: usize,
+                _: Option<&World>,
+            ) -> Pair {
+                Pair(columns.first[index], columns.second[index])
+            }
+        }
+        type Columns = HCons<Columnar<PairProxy>, table![Id<C1>,]>;
+
+        let mut world = World::new();
+        world.spawn((Pair(1, 2), C1(3)));
+        world.spawn(C1(4));
+        world.spawn(Pair(5, 6));
+        world.spawn(Pair(7, 0));
+        let fast_scene = FastScene::<Columns, ()>::from_bevy(&mut Scene::new(world));
+        assert_eq!(fast_scene.tables.component_count_of(0), 3);
+        let bytes = rkyv::to_bytes::<_, 0>(&fast_scene).unwrap();
+        let archived = unsafe { rkyv::archived_root::<FastScene<Columns, ()>>(&bytes) };
+        assert_eq!(archived.tables.lens(), [3, 2]);
+
+        let mut world = World::new();
+        let (_, ids) = archived.spawn(&mut world);
+        let pairs: Vec<_> = ids[1..].iter().map(|id| world.get::<Pair>(*id)).collect();
+        // The last pair is past the end of the `second` column.
+        assert_eq!(pairs, [Some(&Pair(1, 2)), None, Some(&Pair(5, 6)), None]);
+        assert_eq!(world.get::<C1>(ids[1]), Some(&C1(3)));
+        assert_eq!(world.get::<C1>(ids[2]), Some(&C1(4)));
+    }
+
+    #[test]
+    fn encoded_tables() {
+        use crate::codec::{Delta, Dictionary, Words};
+        use crate::{DedupTable, Encoded, HCons, Table};
+
+        impl Words for C1 {
+            const WORDS: usize = 1;
+            fn to_words(&self, out: &mut Vec<u32>) {
+                out.push(self.0);
+            }
+            fn from_words(words: &[u32]) -> Self {
+                C1(words[0])
+            }
+        }
+        impl Words for C2 {
+            const WORDS: usize = 1;
+            fn to_words(&self, out: &mut Vec<u32>) {
+                out.push(self.0);
+            }
+            fn from_words(words: &[u32]) -> Self {
+                C2(words[0])
+            }
+        }
+        type Encodeds = HCons<
+            Encoded<Table<Id<C1>>, Delta>,
+            HCons<Encoded<DedupTable<Id<C2>>, Dictionary>, table![Id<A1>,]>,
+        >;
+
+        let mut world = World::new();
+        for i in 0..100 {
+            world.spawn((C1(1000 + i), C2(i % 3)));
+        }
+        world.spawn(A1);
+        let fast_scene = FastScene::<Encodeds, ()>::from_bevy(&mut Scene::new(world));
+        let bytes = rkyv::to_bytes::<_, 0>(&fast_scene).unwrap();
+        let archived = unsafe { rkyv::archived_root::<FastScene<Encodeds, ()>>(&bytes) };
+        assert_eq!(archived.tables.lens(), [100, 3, 1]);
+        // 2 bytes for the first value, then 1 byte per difference.
+        assert_eq!(archived.tables.sizes()[0], 2 + 99);
+
+        let mut world = World::new();
+        let (_, ids) = archived.spawn(&mut world);
+        for (i, id) in ids[1..101].iter().enumerate() {
+            let i = i as u32;
+            assert_eq!(world.get::<C1>(*id), Some(&C1(1000 + i)));
+            assert_eq!(world.get::<C2>(*id), Some(&C2(i % 3)));
+        }
+        assert_eq!(world.get::<C1>(ids[101]), None);
+        assert!(world.get::<A1>(ids[101]).is_some());
+    }
+
+    #[test]
+    fn auto_storage() {
+        use crate::schema::Storage;
+        use crate::{Auto, HCons};
+
+        type Autos = HCons<Auto<Id<A1>>, HCons<Auto<Id<C1>>, HCons<Auto<Id<C2>>, ()>>>;
+
+        let mut world = World::new();
+        for i in 0..100 {
+            let mut entity = world.spawn((A1, C1(i % 2)));
+            if i % 10 == 0 {
+                entity.insert(C2(i));
+            }
+        }
+        let fast_scene = FastScene::<Autos, ()>::from_bevy(&mut Scene::new(world));
+        let storages = fast_scene.tables.auto_storages();
+        assert_eq!(
+            storages,
+            [Storage::Inline, Storage::DedupTable, Storage::Table]
+        );
+        let bytes = rkyv::to_bytes::<_, 0>(&fast_scene).unwrap();
+        let archived = unsafe { rkyv::archived_root::<FastScene<Autos, ()>>(&bytes) };
+        assert_eq!(archived.tables.lens(), [100, 2, 10]);
+        assert_eq!(archived.tables.auto_storages().unwrap(), storages);
+
+        let mut world = World::new();
+        let (_, ids) = archived.spawn(&mut world);
+        assert!(world.get::<A1>(ids[0]).is_none());
+        for (i, id) in ids[1..].iter().enumerate() {
+            let i = i as u32;
+            let c2 = (i % 10 == 0).then_some(C2(i));
+            assert!(world.get::<A1>(*id).is_some());
+            assert_eq!(world.get::<C1>(*id), Some(&C1(i % 2)));
+            assert_eq!(world.get::<C2>(*id), c2.as_ref());
+        }
+    }
+
+    #[test]
+    fn deep_chain() {
+        const DEPTH: u32 = 100_000;
+        let mut world = World::new();
+        let mut parent = world.spawn(C2(0)).id();
+        for i in 1..DEPTH {
+            parent = world.spawn(C2(i)).set_parent(parent).id();
+        }
+        let fast_scene = FastScene::<Tables, Inlines>::from_bevy(&mut Scene::new(world));
+        let children: Vec<_> = fast_scene.entities.iter().map(|e| e.children).collect();
+        let expected: Vec<_> = (0..=DEPTH).rev().collect();
+        assert_eq!(children, expected);
+
+        let bytes = rkyv::to_bytes::<_, 0>(&fast_scene).unwrap();
+        let archived = unsafe { rkyv::archived_root::<FastScene<Tables, Inlines>>(&bytes) };
+        let mut world = World::new();
+        let (_, ids) = archived.spawn(&mut world);
+        assert_eq!(ids.len(), DEPTH as usize + 1);
+        for (i, pair) in ids.windows(2).enumerate().skip(1) {
+            let parent = world.get::<Parent>(pair[1]).map(Parent::get);
+            assert_eq!(parent, Some(pair[0]));
+            assert_eq!(world.get::<C2>(pair[1]), Some(&C2(i as u32)));
+        }
+    }
+
+    #[test]
+    fn export_dynamic_scene() {
+        let mut world = World::new();
+        world.spawn(A1).with_children(|parent| {
+            parent.spawn((B1, C1(3)));
+            parent.spawn(C2(2)).with_children(|parent| {
+                parent.spawn(A2);
+            });
+        });
+        world.spawn(B2);
+        let fast_scene = FastScene::<Tables, Inlines>::from_bevy(&mut Scene::new(world));
+        let bytes = rkyv::to_bytes::<_, 0>(&fast_scene).unwrap();
+        let archived = unsafe { rkyv::archived_root::<FastScene<Tables, Inlines>>(&bytes) };
+
+        let registry = AppTypeRegistry::default();
+        registry.write().register::<Parent>();
+        registry.write().register::<Children>();
+        registry.write().register::<C1>();
+        registry.write().register::<C2>();
+        let dynamic_scene = archived.to_dynamic_scene(&registry);
+        assert_eq!(dynamic_scene.entities.len(), 5);
+
+        let mut world = Scene::from_dynamic_scene(&dynamic_scene, &registry)
+            .unwrap()
+            .world;
+        let roots = world
+            .query_filtered::<(), Without<Parent>>()
+            .iter(&world)
+            .count();
+        let children: usize = world
+            .query::<&Children>()
+            .iter(&world)
+            .map(|c| c.len())
+            .sum();
+        assert_eq!(roots, 2);
+        assert_eq!(children, 3);
+
+        // Proxied values survive the round trip, in both storages.
+        let c1: Vec<_> = world.query::<&C1>().iter(&world).cloned().collect();
+        let c2: Vec<_> = world.query::<&C2>().iter(&world).cloned().collect();
+        assert_eq!(c1, [C1(3)]);
+        assert_eq!(c2, [C2(2)]);
+    }
 }
@@ -0,0 +1,189 @@
+//! Runtime description of the components stored in a [`crate::FastScene`].
+
+use std::{any, mem};
+
+use crate::entity::{Inlines, Tables};
+use crate::ArchiveProxy;
+
+/// How a component is stored, see the `Plugin!` macro documentation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Storage {
+    Inline,
+    Table,
+    DedupTable,
+    Columnar,
+    /// One of `Inline`, `Table` or `DedupTable`, picked for each file, see
+    /// [`crate::header::Header::auto`].
+    Auto,
+}
+impl Storage {
+    pub const fn name(self) -> &'static str {
+        match self {
+            Storage::Inline => "Inline",
+            Storage::Table => "Table",
+            Storage::DedupTable => "DedupTable",
+            Storage::Columnar => "Columnar",
+            Storage::Auto => "Auto",
+        }
+    }
+}
+
+/// A single component in a [`Schema`].
+#[derive(Debug, Clone)]
+pub struct ComponentInfo {
+    pub storage: Storage,
+    /// The [`ArchiveProxy`] type name.
+    pub proxy: &'static str,
+    /// The proxied bevy `Component` type name.
+    pub target: &'static str,
+    /// Size of the archived proxy, not counting out-of-line data.
+    pub archived_size: usize,
+    /// Alignment of the archived proxy.
+    pub archived_align: usize,
+    /// The [`crate::codec::Codec`] name, if the table is encoded.
+    pub codec: Option<&'static str>,
+}
+impl ComponentInfo {
+    pub fn new<P: ArchiveProxy>(storage: Storage) -> Self {
+        ComponentInfo {
+            storage,
+            proxy: any::type_name::<P>(),
+            target: any::type_name::<P::Target>(),
+            archived_size: mem::size_of::<P::Archived>(),
+            archived_align: mem::align_of::<P::Archived>(),
+            codec: None,
+        }
+    }
+    pub fn with_codec(self, codec: &'static str) -> Self {
+        ComponentInfo { codec: Some(codec), ..self }
+    }
+}
+
+/// The list of components a [`crate::FastScene`] stores, in storage order.
+#[derive(Debug, Clone)]
+pub struct Schema {
+    /// Components in `Table`, `DedupTable`, `Columnar` and `Auto` storage.
+    pub tables: Vec<ComponentInfo>,
+    /// Components in `Inline` storage.
+    pub inlines: Vec<ComponentInfo>,
+}
+impl Schema {
+    pub fn of<Ts: Tables, Is: Inlines>() -> Self {
+        let mut tables = Vec::new();
+        let mut inlines = Vec::new();
+        Ts::describe(&mut tables);
+        Is::describe(&mut inlines);
+        Schema { tables, inlines }
+    }
+    /// A hash of the schema, used to check a file was saved with it.
+    ///
+    /// It depends on the storage kind, the full type name (module path
+    /// included) and the archived size and alignment of each proxy, and on
+    /// the codec name of encoded tables. Two proxies with the same name in
+    /// different modules, or a proxy whose fields changed size, give
+    /// different fingerprints. Moving a proxy to another module changes the
+    /// fingerprint too, and so might a rust version changing how
+    /// `type_name` formats types.
+    pub fn fingerprint(&self) -> u64 {
+        // FNV-1a, we need a hash stable across platforms and rust versions.
+        const PRIME: u64 = 0x0000_0100_0000_01b3;
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let mut write = |bytes: &[u8]| {
+            for byte in bytes {
+                hash ^= u64::from(*byte);
+                hash = hash.wrapping_mul(PRIME);
+            }
+        };
+        for info in self.tables.iter().chain(&self.inlines) {
+            write(info.storage.name().as_bytes());
+            write(info.proxy.as_bytes());
+            write(&(info.archived_size as u64).to_le_bytes());
+            write(&(info.archived_align as u64).to_le_bytes());
+            if let Some(codec) = info.codec {
+                write(b"=>");
+                write(codec.as_bytes());
+            }
+            write(b";");
+        }
+        hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Component;
+
+    use super::{ComponentInfo, Schema, Storage};
+    use crate::entity::{Inlines, Tables};
+    use crate::proxy::Id;
+    use crate::{Archive, Deserialize, HCons, Inline, Serialize, Table};
+
+    mod a {
+        use bevy::prelude::Component;
+
+        use crate::{Archive, Deserialize, Serialize};
+
+        #[derive(Component, Clone, Archive, Deserialize, Serialize)]
+        pub struct Health(pub u32);
+    }
+    mod b {
+        use bevy::prelude::Component;
+
+        use crate::{Archive, Deserialize, Serialize};
+
+        #[derive(Component, Clone, Archive, Deserialize, Serialize)]
+        pub struct Health(pub u32);
+    }
+
+    fn info(storage: Storage, proxy: &'static str, size: usize, align: usize) -> ComponentInfo {
+        ComponentInfo {
+            storage,
+            proxy,
+            target: "unused",
+            archived_size: size,
+            archived_align: align,
+            codec: None,
+        }
+    }
+    fn fingerprint<Ts: Tables, Is: Inlines>() -> u64 {
+        Schema::of::<Ts, Is>().fingerprint()
+    }
+
+    #[test]
+    fn stable_fingerprint() {
+        // The fingerprint is written in files, it must not change for a
+        // given schema description.
+        let empty = Schema { tables: Vec::new(), inlines: Vec::new() };
+        assert_eq!(empty.fingerprint(), 0xcbf2_9ce4_8422_2325);
+        let schema = Schema {
+            tables: vec![info(Storage::Table, "game::Position", 8, 4).with_codec("Delta")],
+            inlines: vec![info(Storage::Inline, "game::Tag", 0, 1)],
+        };
+        assert_eq!(schema.fingerprint(), 0xe848_6d8c_3b77_9367);
+        // The target type isn't part of it.
+        let mut other_target = schema.clone();
+        other_target.tables[0].target = "game::Other";
+        assert_eq!(other_target.fingerprint(), schema.fingerprint());
+    }
+
+    #[test]
+    fn fingerprint_changes() {
+        type A = HCons<Table<Id<a::Health>>, ()>;
+        type B = HCons<Table<Id<b::Health>>, ()>;
+        type AInline = HCons<Inline<Id<a::Health>>, ()>;
+
+        assert_eq!(fingerprint::<A, ()>(), fingerprint::<A, ()>());
+        // Same name in another module.
+        assert_ne!(fingerprint::<A, ()>(), fingerprint::<B, ()>());
+        // Other storage.
+        assert_ne!(fingerprint::<A, ()>(), fingerprint::<(), AInline>());
+        let schema = Schema::of::<A, ()>();
+        // Other archived layout.
+        let mut wider = schema.clone();
+        wider.tables[0].archived_size = 8;
+        assert_ne!(wider.fingerprint(), schema.fingerprint());
+        let mut encoded = schema.clone();
+        encoded.tables[0] = encoded.tables[0].clone().with_codec("Delta");
+        assert_ne!(encoded.fingerprint(), schema.fingerprint());
+    }
+}
@@ -0,0 +1,221 @@
+//! Store each string once per scene, see [`InternedStr`].
+
+use std::alloc::Layout;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use bevy::prelude::{Resource, World};
+use rkyv::{string::ArchivedString, Archive, Deserialize, Serialize};
+
+use crate::dynamic::{in_bytes, str_in_bytes};
+
+/// The strings of a scene, shared by all its [`InternedStr`].
+#[derive(Clone, Default, Archive, Deserialize, Serialize)]
+pub struct StringPool {
+    strings: Vec<String>,
+}
+impl ArchivedStringPool {
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+    /// The string at `index`, if any.
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.strings.get(index).map(|s| s.as_str())
+    }
+    /// The string of `interned`, to read archived proxies without spawning
+    /// them, see [`crate::query`].
+    pub fn resolve(&self, interned: &ArchivedInternedStr) -> Option<&str> {
+        self.get(interned.0 as usize)
+    }
+    /// Whether the strings of this pool, which is within `bytes`, are all
+    /// within `bytes`.
+    pub(crate) fn check(&self, bytes: &[u8]) -> bool {
+        let ptr = self.strings.as_ptr().cast();
+        let layout = Layout::new::<ArchivedString>();
+        in_bytes(bytes, ptr, layout, self.strings.len())
+            && self.strings.iter().all(|s| str_in_bytes(bytes, s))
+    }
+    /// The [`SceneStrings`] resource of a scene with this pool.
+    pub(crate) fn to_resource(&self) -> SceneStrings {
+        SceneStrings(self.strings.iter().map(|s| s.as_str().into()).collect())
+    }
+}
+
+/// A string stored once in the scene's [`StringPool`], whatever the number of
+/// components it appears in.
+///
+/// Use it in proxies of components with strings that repeat a lot, such as
+/// `Name`s or asset paths:
+///
+/// ```text
+/// #[derive(Archive, Deserialize, Serialize)]
+/// struct LabelProxy(InternedStr);
+///
+/// impl ArchiveProxy for LabelProxy {
+///     type Target = Label;
+///     fn to_target(archive: &Self::Archived) -> Label {
+///         Self::to_target_in(archive, None)
+///     }
+///     fn to_target_in(archive: &Self::Archived, world: Option<&World>) -> Label {
+///         // `None` without the scene's strings, such as in `to_target`.
+///         Label(archive.0.get(world).unwrap_or_default().to_string())
+///     }
+///     fn from_target(target: &Label) -> Self {
+///         LabelProxy(InternedStr::new(&target.0))
+///     }
+/// }
+/// ```
+///
+/// [`InternedStr::new`] panics outside of scene saving, so `from_target` of
+/// such proxies can only be called by the hollow scene saver, the converter
+/// and [`crate::FastScene::from_bevy`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Archive, Deserialize, Serialize)]
+pub struct InternedStr(u32);
+
+impl InternedStr {
+    /// Add `string` to the pool of the scene being saved.
+    ///
+    /// # Panics
+    ///
+    /// When not called while saving a scene, that is, outside of
+    /// [`crate::ArchiveProxy::from_target`] and `PartialEq` implementations
+    /// of proxies.
+    pub fn new(string: &str) -> Self {
+        let interner = Interner::current();
+        let interner = interner.expect("InternedStr::new called outside of scene saving");
+        InternedStr(interner.intern(string))
+    }
+}
+impl ArchivedInternedStr {
+    /// The string, read from the [`SceneStrings`] of `world`.
+    ///
+    /// This is `None` when `world` is `None` or not a world the scene is being
+    /// spawned in, for example when calling [`crate::ArchiveProxy::to_target`].
+    pub fn get<'w>(&self, world: Option<&'w World>) -> Option<&'w str> {
+        let strings = world?.get_resource::<SceneStrings>()?;
+        strings.0.get(self.0 as usize).map(|s| &**s)
+    }
+}
+
+/// The strings of the scene being spawned.
+///
+/// This resource is only present while the scene is spawned, use it
+/// with [`ArchivedInternedStr::get`].
+#[derive(Resource, Clone, Debug)]
+pub struct SceneStrings(Arc<[Box<str>]>);
+
+#[derive(Clone, Default)]
+struct Strings {
+    indices: HashMap<String, u32>,
+    pool: Vec<String>,
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Interner>> = RefCell::new(None);
+}
+
+/// The [`StringPool`] of a scene being saved.
+#[derive(Clone, Default)]
+pub(crate) struct Interner(Arc<Mutex<Strings>>);
+
+impl Interner {
+    /// The interner of the scene being saved on this thread, if any.
+    pub(crate) fn current() -> Option<Interner> {
+        CURRENT.with(|current| current.borrow().clone())
+    }
+    /// Run `f`, with [`InternedStr::new`] adding strings to this interner.
+    pub(crate) fn scope<R>(&self, f: impl FnOnce() -> R) -> R {
+        /// Restore the previous interner, even if `f` panics.
+        struct Restore(Option<Interner>);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                CURRENT.with(|current| *current.borrow_mut() = self.0.take());
+            }
+        }
+        let previous = CURRENT.with(|current| current.borrow_mut().replace(self.clone()));
+        let _restore = Restore(previous);
+        f()
+    }
+    fn intern(&self, string: &str) -> u32 {
+        // unwrap: we never panic while holding the lock
+        let mut strings = self.0.lock().unwrap();
+        if let Some(index) = strings.indices.get(string) {
+            return *index;
+        }
+        let index = u32::try_from(strings.pool.len()).unwrap();
+        strings.indices.insert(string.to_string(), index);
+        strings.pool.push(string.to_string());
+        index
+    }
+    /// A separate interner, starting with the strings interned so far.
+    pub(crate) fn fork(&self) -> Interner {
+        // unwrap: we never panic while holding the lock
+        let strings = self.0.lock().unwrap().clone();
+        Interner(Arc::new(Mutex::new(strings)))
+    }
+    /// Intern the strings of `fork`, in order.
+    ///
+    /// Returns whether they all have the same index in this interner as in
+    /// `fork`.
+    pub(crate) fn merge(&self, fork: &Interner) -> bool {
+        // unwrap: we never panic while holding the lock
+        let pool = fork.0.lock().unwrap().pool.clone();
+        let mut same = true;
+        for (index, string) in pool.iter().enumerate() {
+            same &= self.intern(string) as usize == index;
+        }
+        same
+    }
+    /// The strings interned so far.
+    pub(crate) fn to_pool(&self) -> StringPool {
+        // unwrap: we never panic while holding the lock
+        let strings = self.0.lock().unwrap();
+        StringPool { strings: strings.pool.clone() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::World;
+
+    use super::{InternedStr, Interner, StringPool};
+
+    #[test]
+    fn intern() {
+        let interner = Interner::default();
+        let (door, wall, door_again) = interner.scope(|| {
+            let door = InternedStr::new("door");
+            let wall = InternedStr::new("wall");
+            (door, wall, InternedStr::new("door"))
+        });
+        assert_eq!(door, door_again);
+        assert_ne!(door, wall);
+        assert!(Interner::current().is_none());
+
+        let (first, second) = (interner.fork(), interner.fork());
+        first.scope(|| InternedStr::new("floor"));
+        second.scope(|| (InternedStr::new("wall"), InternedStr::new("roof")));
+        assert!(interner.merge(&first));
+        // "roof" is at 2 in `second`, but "floor" took it.
+        assert!(!interner.merge(&second));
+        let roof = interner.scope(|| InternedStr::new("roof"));
+        assert_eq!(roof, InternedStr(3));
+
+        let pool = interner.to_pool();
+        let bytes = rkyv::to_bytes::<_, 256>(&pool).unwrap();
+        let archived = unsafe { rkyv::archived_root::<StringPool>(&bytes) };
+        assert_eq!(archived.len(), 4);
+
+        let bytes = rkyv::to_bytes::<_, 16>(&wall).unwrap();
+        let wall = unsafe { rkyv::archived_root::<InternedStr>(&bytes) };
+        let mut world = World::new();
+        assert_eq!(wall.get(Some(&world)), None);
+        world.insert_resource(archived.to_resource());
+        assert_eq!(wall.get(Some(&world)), Some("wall"));
+        assert_eq!(wall.get(None), None);
+    }
+}
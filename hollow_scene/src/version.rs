@@ -4,7 +4,7 @@ use thiserror::Error;
 
 pub const DIGIT_COUNT: usize = 5;
 const ASCII_OFFSET: u8 = b' ';
-pub const VERSION: Version = Version(1);
+pub const VERSION: Version = Version(6);
 
 pub struct Version(u16);
 impl fmt::Display for Version {
@@ -37,6 +37,9 @@ const fn digits_represents(version: u16, ascii: &[u8]) -> bool {
 }
 
 impl Version {
+    pub const fn get(&self) -> u16 {
+        self.0
+    }
     pub fn get_version_slice(ascii: &[u8]) -> Result<u16, Error> {
         let too_short = Error::TooShort(ascii.len());
 